@@ -0,0 +1,105 @@
+use crate::config::{Config, ProfileRule};
+use chrono::{Local, NaiveTime};
+use std::process::Command;
+
+/// Returns the profile of the first rule of `Config.auto_profiles` matching
+/// the time of day and power state
+///
+/// The power state is only queried from UPower if a rule needs it.
+pub fn select(config: &Config) -> Option<&str> {
+    let rules = config.auto_profiles.as_ref()?;
+    let now = Local::now().time();
+    let on_battery = match rules.iter().any(|v| v.on_battery.is_some()) {
+        true => on_battery(),
+        false => None,
+    };
+
+    let rule = rules.iter().find(|v| matches(v, now, on_battery))?;
+    log::info!("Using profile {} by rule", rule.profile);
+    Some(&rule.profile)
+}
+
+/// Returns `true` if all conditions of the rule are met
+///
+/// The time range may wrap around midnight, e.g. from `22:00` to `07:00`.
+/// A rule on the power state doesn't match if it's unknown.
+fn matches(rule: &ProfileRule, now: NaiveTime, on_battery: Option<bool>) -> bool {
+    let after = rule.after.as_deref().map(parse_time);
+    let before = rule.before.as_deref().map(parse_time);
+    let in_time = match (after, before) {
+        (Some(None), _) | (_, Some(None)) => false,
+        (Some(Some(a)), Some(Some(b))) if a > b => now >= a || now < b,
+        (Some(Some(a)), Some(Some(b))) => now >= a && now < b,
+        (Some(Some(a)), None) => now >= a,
+        (None, Some(Some(b))) => now < b,
+        (None, None) => true,
+    };
+
+    in_time
+        && rule
+            .on_battery
+            .is_none_or(|v| on_battery.is_some_and(|state| state == v))
+}
+
+/// Returns the time of `HH:MM`
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    let time = NaiveTime::parse_from_str(value, "%H:%M").ok();
+    if time.is_none() {
+        log::warn!("Invalid time of auto profile rule: {}", value);
+    }
+    time
+}
+
+/// Returns `true` if the system runs on battery, by `upower --dump`
+fn on_battery() -> Option<bool> {
+    let output = Command::new("upower").arg("--dump").output().ok()?;
+    parse_on_battery(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Returns the `on-battery` state of the UPower daemon
+fn parse_on_battery(dump: &str) -> Option<bool> {
+    let value = dump
+        .lines()
+        .find_map(|v| v.trim().strip_prefix("on-battery:"))?;
+    match value.trim() {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_auto_profile_rules() {
+    let time = |v| NaiveTime::parse_from_str(v, "%H:%M").unwrap();
+    let night = ProfileRule {
+        profile: "night".to_string(),
+        after: Some("22:00".to_string()),
+        before: Some("07:00".to_string()),
+        on_battery: None,
+    };
+    assert!(matches(&night, time("23:30"), None));
+    assert!(matches(&night, time("06:59"), None));
+    assert!(!matches(&night, time("07:00"), None));
+    assert!(!matches(&night, time("12:00"), None));
+
+    let battery = ProfileRule {
+        profile: "low-power".to_string(),
+        after: None,
+        before: None,
+        on_battery: Some(true),
+    };
+    assert!(matches(&battery, time("12:00"), Some(true)));
+    assert!(!matches(&battery, time("12:00"), Some(false)));
+    assert!(!matches(&battery, time("12:00"), None));
+
+    let invalid = ProfileRule {
+        after: Some("10pm".to_string()),
+        ..night
+    };
+    assert!(!matches(&invalid, time("23:30"), None));
+
+    let dump =
+        "Daemon:\n  daemon-version:  1.90.2\n  on-battery:      yes\n  lid-is-closed:   no\n";
+    assert_eq!(parse_on_battery(dump), Some(true));
+    assert_eq!(parse_on_battery(""), None);
+}
@@ -0,0 +1,35 @@
+use crate::error::Error;
+use std::process::{Command, Stdio};
+
+/// Set in the environment of the background worker, so it handles the link
+/// itself instead of spawning another worker
+const ENV_WORKER: &str = "MPV_HANDLER_WORKER";
+
+/// Returns `true` if this process is the background worker of a link
+pub fn is_worker() -> bool {
+    std::env::var_os(ENV_WORKER).is_some()
+}
+
+/// Handle the link of `args` in a detached copy of the handler and return
+/// right away
+///
+/// The browser waits for the handler to exit, so resolving a playlist
+/// would block it otherwise. The worker runs in its own process group, so
+/// it isn't killed with the browser, and its output is discarded.
+pub fn spawn(args: &[&str]) -> Result<(), Error> {
+    let exe = std::env::current_exe()?;
+    let mut command = Command::new(exe);
+    command
+        .args(args)
+        .env(ENV_WORKER, "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    let child = command.spawn()?;
+    log::info!("Enqueuing in background worker {}", child.id());
+    Ok(())
+}
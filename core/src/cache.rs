@@ -0,0 +1,107 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Cached values of a namespace, stored in `<cache dir>/<namespace>.json`
+///
+/// Values are kept as JSON with the time they were stored, so each caller
+/// decides its own TTL when reading.
+#[derive(Debug, Default, serde::Deserialize, Serialize)]
+struct CacheFile {
+    entries: HashMap<String, CacheEntry>,
+}
+
+#[derive(Debug, serde::Deserialize, Serialize)]
+struct CacheEntry {
+    time: u64,
+    value: serde_json::Value,
+}
+
+/// Returns the cached value of `key` if it is younger than `ttl`
+pub fn get<T: DeserializeOwned>(namespace: &str, key: &str, ttl: Duration) -> Option<T> {
+    let file = CacheFile::load(namespace);
+    let entry = file.entries.get(key)?;
+
+    if now().saturating_sub(entry.time) > ttl.as_secs() {
+        return None;
+    }
+
+    serde_json::from_value(entry.value.clone()).ok()
+}
+
+/// Store the value of `key`, entries older than `ttl` are dropped
+///
+/// Failures are ignored, the cache is best-effort.
+pub fn put<T: Serialize>(namespace: &str, key: &str, value: &T, ttl: Duration) {
+    let Ok(value) = serde_json::to_value(value) else {
+        return;
+    };
+
+    let mut file = CacheFile::load(namespace);
+    let now = now();
+    file.entries
+        .retain(|_, v| now.saturating_sub(v.time) <= ttl.as_secs());
+    file.entries
+        .insert(key.to_string(), CacheEntry { time: now, value });
+
+    if let Err(e) = file.save(namespace) {
+        log::warn!("Failed to save cache {}: {}", namespace, e);
+    }
+}
+
+/// Remove the cached values of every namespace
+///
+/// Returns the number of removed namespaces.
+pub fn clear() -> Result<usize, crate::error::Error> {
+    let Some(dir) = crate::config::get_cache_dir() else {
+        return Ok(0);
+    };
+    let entries = match std::fs::read_dir(dir) {
+        Ok(v) => v,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|v| v == "json") {
+            std::fs::remove_file(path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+impl CacheFile {
+    fn load(namespace: &str) -> CacheFile {
+        cache_path(namespace)
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, namespace: &str) -> Result<(), crate::error::Error> {
+        if let Some(path) = cache_path(namespace) {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, serde_json::to_string(self)?)?;
+        }
+        Ok(())
+    }
+}
+
+fn cache_path(namespace: &str) -> Option<PathBuf> {
+    let mut path = crate::config::get_cache_dir()?;
+    path.push(format!("{namespace}.json"));
+    Some(path)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |v| v.as_secs())
+}
@@ -0,0 +1,263 @@
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Interval between checks for a cancel request while a command runs
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Time given to finish the current item after SIGINT or SIGTERM
+const SIGNAL_GRACE: Duration = Duration::from_secs(3);
+/// Exit code after SIGINT or SIGTERM, `128 + SIGINT` like shells
+pub const EXIT_INTERRUPTED: u8 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+/// Commands running by `output_timeout`, killed on a signal
+static CHILDREN: Mutex<Vec<Arc<Mutex<Child>>>> = Mutex::new(Vec::new());
+/// Start of the running batch, earlier cancel requests are ignored
+static SINCE: Mutex<Option<SystemTime>> = Mutex::new(None);
+/// Set by `stopped` once the handler finished after a signal
+static STOPPED: (Mutex<bool>, Condvar) = (Mutex::new(false), Condvar::new());
+
+/// Install SIGINT and SIGTERM (Ctrl-C on Windows) handlers
+///
+/// On a signal, running commands are killed and batches stop like on
+/// `mpv-handler cancel`, so the items enqueued so far are still recorded.
+/// The process exits anyway if it isn't `stopped` within `SIGNAL_GRACE`.
+pub fn install_signal_handlers() {
+    let result = ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        log::info!("Interrupted, finishing current item");
+        kill_children();
+
+        let (lock, condvar) = &STOPPED;
+        let Ok(stopped) = lock.lock() else {
+            return;
+        };
+        let stopped = condvar.wait_timeout_while(stopped, SIGNAL_GRACE, |v| !*v);
+        if stopped.is_ok_and(|(v, _)| *v) {
+            return;
+        }
+        crate::temp::cleanup();
+        std::process::exit(EXIT_INTERRUPTED.into());
+    });
+    if let Err(e) = result {
+        log::warn!("Failed to install signal handlers: {}", e);
+    }
+}
+
+/// Tell the signal handler the interrupted work stopped, so the process
+/// exits by itself without waiting for `SIGNAL_GRACE`
+pub fn stopped() {
+    let (lock, condvar) = &STOPPED;
+    if let Ok(mut stopped) = lock.lock() {
+        *stopped = true;
+        condvar.notify_all();
+    }
+}
+
+/// Kill the commands run by `output_timeout`
+fn kill_children() {
+    let Ok(children) = CHILDREN.lock() else {
+        return;
+    };
+    for child in children.iter() {
+        if let Ok(mut child) = child.lock() {
+            log::info!("Killing process {}", child.id());
+            let _ = child.kill();
+        }
+    }
+}
+
+/// Returns `true` if the process received SIGINT or SIGTERM
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Request running batches to stop, used by `mpv-handler cancel`
+pub fn request() -> std::io::Result<()> {
+    if let Some(path) = cancel_path() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Recreated, as requests are dated by their modification time
+        let _ = std::fs::remove_file(&path);
+        std::fs::write(path, "")?;
+    }
+    Ok(())
+}
+
+/// Forget previous cancel requests, called when a batch starts
+pub fn clear() {
+    if let Ok(mut since) = SINCE.lock() {
+        *since = Some(SystemTime::now());
+    }
+}
+
+/// Remove the cancel request once a batch stopped for it
+pub fn honored() {
+    if let Some(path) = cancel_path() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Returns `true` if cancelling was requested since the batch started,
+/// or the process was interrupted
+///
+/// Before any batch, requests count since the first check of the process.
+pub fn is_requested() -> bool {
+    if interrupted() {
+        return true;
+    }
+    let Some(path) = cancel_path() else {
+        return false;
+    };
+    let Ok(mut since) = SINCE.lock() else {
+        return false;
+    };
+    requested_since(&path, *since.get_or_insert_with(SystemTime::now))
+}
+
+/// Returns `true` if the cancel request at `path` was made after `since`
+fn requested_since(path: &std::path::Path, since: SystemTime) -> bool {
+    std::fs::metadata(path)
+        .and_then(|v| v.modified())
+        .is_ok_and(|v| v >= since)
+}
+
+/// Run the command like `Command::output`, killing it on a cancel request
+///
+/// Returns `None` if the command was cancelled.
+pub fn output(command: &mut Command) -> std::io::Result<Option<Output>> {
+    output_timeout(command, None)
+}
+
+/// Run the command like `output`, killing it once `timeout` elapsed
+///
+/// Returns a `TimedOut` error if the command was killed by the timeout.
+pub fn output_timeout(
+    command: &mut Command,
+    timeout: Option<Duration>,
+) -> std::io::Result<Option<Output>> {
+    let start = std::time::Instant::now();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Read both pipes while waiting, so a full pipe doesn't block the child
+    let read = |pipe: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut data = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut data);
+            }
+            data
+        })
+    };
+    let stdout = read(child.stdout.take().map(|v| Box::new(v) as _));
+    let stderr = read(child.stderr.take().map(|v| Box::new(v) as _));
+
+    let child = Arc::new(Mutex::new(child));
+    if let Ok(mut children) = CHILDREN.lock() {
+        children.push(Arc::clone(&child));
+    }
+    let status = wait_child(&child, command, start, timeout);
+    if let Ok(mut children) = CHILDREN.lock() {
+        children.retain(|v| !Arc::ptr_eq(v, &child));
+    }
+    let Some(status) = status? else {
+        return Ok(None);
+    };
+
+    Ok(Some(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    }))
+}
+
+/// Wait for the child of `output_timeout`, returns `None` if it's cancelled
+fn wait_child(
+    child: &Mutex<Child>,
+    command: &Command,
+    start: std::time::Instant,
+    timeout: Option<Duration>,
+) -> std::io::Result<Option<std::process::ExitStatus>> {
+    loop {
+        let mut child = child
+            .lock()
+            .map_err(|_| std::io::Error::other("child lock poisoned"))?;
+        // Children killed by the signal handler are cancelled, not failed
+        if is_requested() {
+            log::info!("Cancelled, killing {:?}", command.get_program());
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if timeout.is_some_and(|v| start.elapsed() >= v) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("{:?} timed out", command.get_program()),
+            ));
+        }
+        drop(child);
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn cancel_path() -> Option<PathBuf> {
+    let mut path = crate::config::get_state_dir()?;
+    path.push("cancel");
+    Some(path)
+}
+
+#[cfg(unix)]
+#[test]
+fn test_output_timeout() {
+    let start = std::time::Instant::now();
+    let result = output_timeout(
+        Command::new("sleep").arg("10"),
+        Some(Duration::from_millis(200)),
+    );
+    assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    let output = output_timeout(&mut Command::new("true"), Some(Duration::from_secs(5))).unwrap();
+    assert!(output.unwrap().status.success());
+
+    // Running commands are killed by the signal handler
+    let start = std::time::Instant::now();
+    let running = std::thread::spawn(|| crate::cancel::output(Command::new("sleep").arg("10")));
+    while CHILDREN.lock().unwrap().is_empty() {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    kill_children();
+    let output = running.join().unwrap().unwrap();
+    assert!(!output.unwrap().status.success());
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_requested_since() {
+    let mut path = std::env::temp_dir();
+    path.push(format!("mpv-handler-test-cancel-{}", std::process::id()));
+    let start = SystemTime::now();
+
+    assert!(!requested_since(&path, start));
+    std::fs::write(&path, "").unwrap();
+    // Requests made before the batch started are stale
+    assert!(!requested_since(&path, start + Duration::from_secs(60)));
+    assert!(requested_since(&path, start - Duration::from_secs(60)));
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,1082 @@
+use crate::error::Error;
+use crate::runner::Runner;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Config of mpv-handler
+///
+/// - `mpv`: mpv binary path
+/// - `ytdl`: yt-dlp binary path
+/// - `proxy: HTTP(S) proxy server address
+/// - `socket`: mpv IPC socket path, may contain `{uid}`, `{user}`, `{display}` and `{profile}`, or `tcp://host:port` of a remote mpv
+/// - `channel_order`: enqueue order of channel uploads and feed episodes
+/// - `subscriptions`: channel/playlist URLs checked by `subs update`
+/// - `sponsorblock`: SponsorBlock segments skipped by the companion script
+/// - `dearrow`: use DeArrow community titles for enqueued YouTube videos
+/// - `notify`: show desktop notifications after enqueueing
+/// - `profiles`: options applied per mpv profile
+/// - `impersonate`: yt-dlp impersonate target, e.g. `chrome`
+/// - `pickformat`: pick the format of single videos from a dialog by default
+/// - `max_bitrate`: cap the video bitrate in kbit/s
+/// - `bandwidth_probe`: URL downloaded briefly to estimate the bandwidth
+/// - `metered`: apply data-saver mode on metered connections, defaults to `true`
+/// - `audio_lang`: preferred audio language of dubbed videos, e.g. `en`
+/// - `detach`: exit after launching mpv instead of waiting for it
+/// - `now_playing`: notify when the playing item changes, requires `mpv-handler daemon`
+/// - `discord`: Discord Rich Presence published by `mpv-handler daemon`
+/// - `scrobble`: scrobbling of music tracks by `mpv-handler daemon`
+/// - `history`: record the watch history from `mpv-handler daemon`, defaults to `true`
+/// - `sites`: default format rules per site domain
+/// - `sort`: sort order of fetched playlist and channel entries
+/// - `filter`: title and duration rules of fetched playlist and channel entries
+/// - `sidecars`: write a JSON metadata file per enqueued item into the session directory
+/// - `title_from`: source of enqueued titles, e.g. `ytdlp:%(artist)s - %(track)s`
+/// - `trust_domains`: ask before opening links of never seen domains
+/// - `playlist_end`: maximum number of playlist and channel entries probed
+/// - `concurrency`: number of playlist items resolved by yt-dlp at once, defaults to `4`
+/// - `background`: handle links in a detached background worker, returning to the browser right away
+/// - `log_dir`: directory of the mpv session logs
+/// - `logs`: rotation and retention of the mpv session logs
+/// - `dedupe_profiles`: skip links already queued by the mpv instance of another profile
+/// - `dialog`: dialog program, `zenity` by default
+/// - `screen`: monitor of new mpv windows, overridden by the `screen` parameter
+/// - `workspace`: workspace new mpv windows are moved to
+/// - `auto_profiles`: rules selecting the profile of new instances without `profile` parameter
+/// - `ytdl_format`: yt-dlp format selector, replaces `ytdl-format` of `mpv.conf`
+/// - `content_filter`: command allowing or blocking each item before it is played or enqueued
+/// - `max_queue_length`: maximum number of pending items in the queue
+/// - `max_queue_hours`: maximum known duration of the pending items, in hours
+/// - `idle_priority`: resolve items with yt-dlp at idle CPU and IO priority
+/// - `osd`: show items enqueued to a running mpv on its OSD, enabled by default
+/// - `osd_duration`: milliseconds enqueued items are shown on the OSD
+/// - `runner`: runs yt-dlp and mpv, set by library users instead of `config.toml`
+/// - `streamlink`: streamlink binary path
+/// - `pickitems`: pick the playlist items to enqueue from a checklist dialog by default
+/// - `party`: watch party of `mpv-handler daemon`, experimental
+/// - `resume`: resume enqueued playlist items at their position in the history, enabled by default
+/// - `ytdl_timeout`: seconds before a hung yt-dlp is killed and retried, `0` disables
+/// - `ytdl_retries`: retries of a timed out yt-dlp, with exponential backoff
+/// - `url_cache_ttl`: minutes resolved titles and direct URLs are cached, `0` disables
+/// - `refresh_after`: minutes after which `mpv-handler daemon` resolves the direct URL of the next item again, `0` disables
+/// - `downgrade_on_buffering`: re-resolve the playing prefetched item of `mpv-handler daemon` at a lower resolution when it repeatedly buffers
+/// - `title_lang`: preferred language of translated titles of playlist items and videos, e.g. `de`
+/// - `dedupe`: whether items already in the queue of mpv by video ID are skipped, `skip`, `ask` or `allow` (default)
+/// - `env`: environment variables of spawned mpv and yt-dlp, e.g. `LIBVA_DRIVER_NAME`
+/// - `download_dir`: directory of the `download` plugin, the download directory of the user by default
+/// - `keep_ld_env`: keep `LD_LIBRARY_PATH` and `LD_PRELOAD` for mpv instead of removing them (Unix)
+/// - `log_level`: least severe level of logged messages, `info` by default, `--verbose` logs `debug`
+/// - `log_file`: also log to `mpv-handler.log` in the config directory
+/// - `instances`: named mpv instances with their own socket, picked by the `instance` parameter or `sites`
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Config {
+    pub mpv: Option<String>,
+    pub ytdl: Option<String>,
+    pub proxy: Option<String>,
+    pub socket: Option<String>,
+    pub channel_order: Option<ChannelOrder>,
+    pub subscriptions: Option<Vec<String>>,
+    pub sponsorblock: Option<SponsorBlock>,
+    pub dearrow: Option<bool>,
+    pub notify: Option<bool>,
+    pub profiles: Option<HashMap<String, Profile>>,
+    pub impersonate: Option<String>,
+    pub pickformat: Option<bool>,
+    pub max_bitrate: Option<u32>,
+    pub bandwidth_probe: Option<String>,
+    pub metered: Option<bool>,
+    pub audio_lang: Option<String>,
+    pub detach: Option<bool>,
+    pub now_playing: Option<bool>,
+    pub discord: Option<Discord>,
+    pub scrobble: Option<Scrobble>,
+    pub history: Option<bool>,
+    pub sites: Option<HashMap<String, Site>>,
+    pub sort: Option<PlaylistSort>,
+    pub filter: Option<EntryFilter>,
+    pub sidecars: Option<bool>,
+    pub title_from: Option<String>,
+    pub trust_domains: Option<bool>,
+    pub playlist_end: Option<usize>,
+    pub concurrency: Option<usize>,
+    pub background: Option<bool>,
+    pub log_dir: Option<String>,
+    pub logs: Option<LogRetention>,
+    pub dedupe_profiles: Option<bool>,
+    pub dialog: Option<DialogBackend>,
+    pub screen: Option<u32>,
+    pub workspace: Option<String>,
+    pub auto_profiles: Option<Vec<ProfileRule>>,
+    pub ytdl_format: Option<String>,
+    pub content_filter: Option<String>,
+    pub max_queue_length: Option<usize>,
+    pub max_queue_hours: Option<f64>,
+    pub idle_priority: Option<bool>,
+    pub osd: Option<bool>,
+    pub osd_duration: Option<u64>,
+    #[serde(skip)]
+    pub runner: Runner,
+    pub streamlink: Option<String>,
+    pub pickitems: Option<bool>,
+    pub party: Option<Party>,
+    pub resume: Option<bool>,
+    pub ytdl_timeout: Option<u64>,
+    pub ytdl_retries: Option<u32>,
+    pub url_cache_ttl: Option<u64>,
+    pub refresh_after: Option<u64>,
+    pub downgrade_on_buffering: Option<bool>,
+    pub title_lang: Option<String>,
+    pub dedupe: Option<Dedupe>,
+    pub env: Option<HashMap<String, String>>,
+    pub download_dir: Option<String>,
+    pub keep_ld_env: Option<bool>,
+    pub log_level: Option<LogLevel>,
+    pub log_file: Option<bool>,
+    pub instances: Option<HashMap<String, Instance>>,
+}
+
+/// Options applied when the mpv profile of the same name is used
+///
+/// - `loudnorm`: normalize loudness, for mixed-source music queues
+/// - `gapless`: play back-to-back tracks without gaps
+/// - `audio_device`: mpv audio device, as listed by `mpv --audio-device=help`
+/// - `queue`: queue of the enqueued items, the playlist of mpv by default
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Profile {
+    pub loudnorm: Option<bool>,
+    pub gapless: Option<bool>,
+    pub audio_device: Option<String>,
+    pub queue: Option<QueueBackend>,
+}
+
+/// Queue of the items enqueued with a profile, see `crate::sink`
+///
+/// - `mpd`: queue of the MPD server at `address`, e.g. `localhost:6600`
+/// - `m3u`: rolling M3U playlist at `path`, keeping the latest `keep` items
+///   (`100` by default), served over HTTP on `listen` by `mpv-handler daemon`
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum QueueBackend {
+    Mpv,
+    Mpd {
+        address: String,
+    },
+    M3u {
+        path: String,
+        keep: Option<usize>,
+        listen: Option<String>,
+    },
+}
+
+/// Named mpv instance of `Config.instances`, e.g. `[instances.music]`
+///
+/// Links of the instance are queued by its socket, launching the instance
+/// if it isn't running.
+///
+/// - `socket`: IPC socket path, with the variables of `Config.socket`
+/// - `profile`: mpv profile of the instance, the `profile` of the link takes
+///   precedence
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Instance {
+    pub socket: String,
+    pub profile: Option<String>,
+}
+
+/// Default rules of a site, the table name is its domain
+///
+/// Subdomains are matched too, the longest matching domain is used.
+/// `quality`, `v_codec`, `profile` and `cookies` of the link take precedence.
+///
+/// - `quality`, `v_codec`, `profile`, `cookies`: same values as the link
+///   parameters
+/// - `format_sort`: extra yt-dlp format-sort fields, e.g. `["abr"]`
+/// - `format`: yt-dlp format selector, e.g. `bestaudio/best` for audio-only
+/// - `proxy`, `socket`: replace `Config.proxy` and `Config.socket`
+/// - `instance`: name of `Config.instances` queueing the links of the site
+/// - `live_resolver`: `streamlink` resolves live streams by streamlink,
+///   detected by `is_live` of yt-dlp
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Site {
+    pub quality: Option<String>,
+    pub v_codec: Option<String>,
+    #[serde(default)]
+    pub format_sort: Vec<String>,
+    pub format: Option<String>,
+    pub profile: Option<String>,
+    pub cookies: Option<String>,
+    pub proxy: Option<String>,
+    pub socket: Option<String>,
+    pub instance: Option<String>,
+    pub live_resolver: Option<LiveResolver>,
+}
+
+/// Program resolving the live streams of a site
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LiveResolver {
+    Ytdl,
+    Streamlink,
+}
+
+/// SponsorBlock config, skips segments of the given categories
+///
+/// See <https://wiki.sponsor.ajay.app/w/Types#Category> for categories.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct SponsorBlock {
+    #[serde(default = "default_sponsorblock_categories")]
+    pub categories: Vec<String>,
+}
+
+/// Discord Rich Presence config
+///
+/// - `client_id`: application ID from the Discord developer portal
+/// - `hidden_domains`: domains only shown as "Watching something"
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Discord {
+    pub client_id: String,
+    #[serde(default)]
+    pub hidden_domains: Vec<String>,
+}
+
+/// Watch party config of `mpv-handler daemon`, experimental
+///
+/// - `listen`: address to host on, e.g. `0.0.0.0:7700`
+/// - `follow`: address of the host to follow, e.g. `192.168.1.2:7700`
+/// - `token`: token of this device paired on the host by `mpv-handler pair`
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Party {
+    pub listen: Option<String>,
+    pub follow: Option<String>,
+    pub token: Option<String>,
+}
+
+/// Scrobbling config, tracks are scrobbled to each configured service
+///
+/// - `profiles`: mpv profiles whose items are scrobbled
+/// - `listenbrainz_token`: ListenBrainz user token
+/// - `lastfm_api_key`, `lastfm_api_secret`: Last.fm API account
+/// - `lastfm_session_key`: Last.fm session key of the user
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Scrobble {
+    #[serde(default = "default_scrobble_profiles")]
+    pub profiles: Vec<String>,
+    pub listenbrainz_token: Option<String>,
+    pub lastfm_api_key: Option<String>,
+    pub lastfm_api_secret: Option<String>,
+    pub lastfm_session_key: Option<String>,
+}
+
+/// Rule of `Config.auto_profiles`, selecting `profile` while all of its
+/// conditions are met
+///
+/// - `after`, `before`: time range of the day as `HH:MM`, may wrap around
+///   midnight
+/// - `on_battery`: power state reported by UPower
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct ProfileRule {
+    pub profile: String,
+    pub after: Option<String>,
+    pub before: Option<String>,
+    pub on_battery: Option<bool>,
+}
+
+/// Rotation and retention of the mpv session logs
+///
+/// - `keep`: number of logs kept, defaults to `10`
+/// - `max_age`: days logs are kept
+/// - `max_size`: size in KiB after which a log continues in a new file
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct LogRetention {
+    pub keep: Option<usize>,
+    pub max_age: Option<u64>,
+    pub max_size: Option<u64>,
+}
+
+/// Program showing the dialogs of the handler
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DialogBackend {
+    Zenity,
+    Kdialog,
+    Yad,
+    Rofi,
+    Dmenu,
+    /// Never ask, defaults are used and prompts are declined
+    #[serde(rename = "none")]
+    Disabled,
+}
+
+/// Least severe level of logged messages
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+/// Handling of enqueued items whose video is already in the queue
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Dedupe {
+    Skip,
+    Ask,
+    Allow,
+}
+
+/// Enqueue order of the latest uploads fetched from a channel or feed
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelOrder {
+    Newest,
+    Oldest,
+}
+
+/// Rules of playlist entries, titles are matched as regular expressions
+///
+/// - `include`: keep only entries matching any of them
+/// - `exclude`: drop entries matching any of them
+/// - `min_duration`, `max_duration`: drop entries outside the range, in seconds
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct EntryFilter {
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    pub min_duration: Option<f64>,
+    pub max_duration: Option<f64>,
+}
+
+/// Sort order of playlist entries, `date`, `duration` or `title`
+///
+/// A `-` prefix reverses the order, e.g. `-date` for the newest first.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+pub struct PlaylistSort {
+    pub key: SortKey,
+    pub reverse: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SortKey {
+    Date,
+    Duration,
+    Title,
+}
+
+impl std::str::FromStr for PlaylistSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (reverse, key) = match s.strip_prefix('-') {
+            Some(key) => (true, key),
+            None => (false, s),
+        };
+        let key = match key {
+            "date" => SortKey::Date,
+            "duration" => SortKey::Duration,
+            "title" => SortKey::Title,
+            _ => return Err(format!("unknown sort order: {s}")),
+        };
+        Ok(PlaylistSort { key, reverse })
+    }
+}
+
+impl TryFrom<String> for PlaylistSort {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl Config {
+    /// Load config file and retruns `Config`
+    ///
+    /// If config file doesn't exists, returns default value
+    pub fn load() -> Result<Config, Error> {
+        if let Some(mut path) = get_config_dir() {
+            path.push("config.toml");
+
+            if path.exists() {
+                let data: String = std::fs::read_to_string(&path)?;
+                let mut config: Config = toml::from_str(&data)?;
+
+                if let Some(mpv) = config.mpv {
+                    config.mpv = Some(realpath(mpv)?);
+                }
+                if let Some(ytdl) = config.ytdl {
+                    config.ytdl = Some(realpath(ytdl)?);
+                }
+
+                config.socket = Some(match config.socket {
+                    Some(v) => expand_socket(&v),
+                    None => default_socket(),
+                });
+                for site in config.sites.iter_mut().flat_map(|v| v.values_mut()) {
+                    site.socket = site.socket.as_deref().map(expand_socket);
+                }
+                for instance in config.instances.iter_mut().flat_map(|v| v.values_mut()) {
+                    instance.socket = expand_socket(&instance.socket);
+                }
+
+                return Ok(config);
+            }
+        }
+
+        Ok(default_config())
+    }
+
+    /// Returns the config of the given mpv profile
+    pub fn profile(&self, name: Option<&str>) -> Option<&Profile> {
+        self.profiles.as_ref()?.get(name?)
+    }
+
+    /// Returns the named mpv instance of `Config.instances`
+    pub fn instance(&self, name: &str) -> Option<&Instance> {
+        self.instances.as_ref()?.get(name)
+    }
+
+    /// Returns the socket path of the given mpv profile
+    ///
+    /// `{profile}` of `Config.socket` is replaced by the profile name,
+    /// or `default` without a profile.
+    pub fn socket(&self, profile: Option<&str>) -> Option<String> {
+        let socket = self.socket.as_ref()?;
+        Some(socket.replace("{profile}", profile.unwrap_or("default")))
+    }
+
+    /// Returns the site rules of the given URL
+    pub fn site(&self, url: &str) -> Option<&Site> {
+        let (host, _) = crate::url::split_url(url)?;
+        self.sites
+            .as_ref()?
+            .iter()
+            .filter(|(k, _)| host == *k || host.ends_with(&format!(".{k}")))
+            .max_by_key(|(k, _)| k.len())
+            .map(|(_, v)| v)
+    }
+}
+
+/// Returns config directory path of mpv-handler
+pub fn get_config_dir() -> Option<PathBuf> {
+    // Linux config directory location: $XDG_CONFIG_HOME/mpv-handler/
+    #[cfg(unix)]
+    {
+        if let Some(mut v) = dirs::config_dir() {
+            v.push("mpv-handler");
+            return Some(v);
+        }
+    }
+
+    // Windows config directory location: %WORKING_DIR%\
+    #[cfg(windows)]
+    {
+        if let Ok(mut v) = std::env::current_exe() {
+            v.pop();
+            return Some(v);
+        }
+    }
+
+    log::warn!("Failed to get config directory");
+    None
+}
+
+/// Returns state directory path of mpv-handler
+pub fn get_state_dir() -> Option<PathBuf> {
+    // Linux state directory location: $XDG_STATE_HOME/mpv-handler/
+    #[cfg(unix)]
+    {
+        if let Some(mut v) = dirs::state_dir() {
+            v.push("mpv-handler");
+            return Some(v);
+        }
+    }
+
+    // Windows state directory location: %WORKING_DIR%\
+    #[cfg(windows)]
+    {
+        if let Some(v) = get_config_dir() {
+            return Some(v);
+        }
+    }
+
+    log::warn!("Failed to get state directory");
+    None
+}
+
+/// Returns data directory path of mpv-handler
+pub fn get_data_dir() -> Option<PathBuf> {
+    // Linux data directory location: $XDG_DATA_HOME/mpv-handler/
+    #[cfg(unix)]
+    {
+        if let Some(mut v) = dirs::data_dir() {
+            v.push("mpv-handler");
+            return Some(v);
+        }
+    }
+
+    // Windows data directory location: %WORKING_DIR%\
+    #[cfg(windows)]
+    {
+        if let Some(v) = get_config_dir() {
+            return Some(v);
+        }
+    }
+
+    log::warn!("Failed to get data directory");
+    None
+}
+
+/// Returns cache directory path of mpv-handler
+pub fn get_cache_dir() -> Option<PathBuf> {
+    // Linux cache directory location: $XDG_CACHE_HOME/mpv-handler/
+    #[cfg(unix)]
+    {
+        if let Some(mut v) = dirs::cache_dir() {
+            v.push("mpv-handler");
+            return Some(v);
+        }
+    }
+
+    // Windows cache directory location: %WORKING_DIR%\cache\
+    #[cfg(windows)]
+    {
+        if let Some(mut v) = get_config_dir() {
+            v.push("cache");
+            return Some(v);
+        }
+    }
+
+    log::warn!("Failed to get cache directory");
+    None
+}
+
+/// The default value of `Config.mpv`
+pub fn default_mpv() -> Result<String, Error> {
+    #[cfg(unix)]
+    return realpath("mpv");
+    #[cfg(windows)]
+    return realpath("mpv.com");
+}
+
+/// The default value of `Config.socket`
+pub fn default_socket() -> String {
+    #[cfg(unix)]
+    return "/tmp/mpvsocket".to_string();
+    #[cfg(windows)]
+    return r"\\.\pipe\mpvsocket".to_string();
+}
+
+/// Expand the `{uid}`, `{user}` and `{display}` variables of `Config.socket`
+///
+/// `{profile}` is kept, it's expanded by `Config::socket` per link.
+fn expand_socket(socket: &str) -> String {
+    #[cfg(unix)]
+    let uid = crate::socket::current_uid().to_string();
+    #[cfg(windows)]
+    let uid = String::new();
+
+    let env = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|v| std::env::var(v).ok().filter(|v| !v.is_empty()))
+            .unwrap_or_default()
+    };
+    let user = env(&["USER", "LOGNAME", "USERNAME"]);
+    let display = env(&["WAYLAND_DISPLAY", "DISPLAY"]);
+
+    expand_variables(
+        socket,
+        &[
+            ("uid", &uid),
+            ("user", &user),
+            ("display", display.trim_start_matches(':')),
+        ],
+    )
+}
+
+/// Replace `{name}` of the template by the value of each variable
+fn expand_variables(template: &str, variables: &[(&str, &str)]) -> String {
+    variables.iter().fold(template.to_string(), |acc, (k, v)| {
+        acc.replace(&format!("{{{k}}}"), v)
+    })
+}
+
+/// The default value of `Scrobble.profiles`
+fn default_scrobble_profiles() -> Vec<String> {
+    vec!["music".to_string()]
+}
+
+/// The default value of `SponsorBlock.categories`
+fn default_sponsorblock_categories() -> Vec<String> {
+    vec!["sponsor".to_string()]
+}
+
+/// The defalut value of `Config`
+fn default_config() -> Config {
+    Config {
+        socket: Some(default_socket()),
+        ..Default::default()
+    }
+}
+
+/// Find and read `ytdl-format` from `mpv.conf`
+pub fn get_ytdl_format_from_mpv_conf() -> Option<String> {
+    // Get mpv config directory
+    let mut path = dirs::config_dir()?;
+    path.push("mpv/mpv.conf");
+
+    if !path.exists() {
+        return None;
+    }
+
+    let content = std::fs::read_to_string(path).ok()?;
+
+    // Find `ytdl-format` option
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=')
+            && key.trim() == "ytdl-format"
+        {
+            return Some(value.trim().to_string());
+        }
+    }
+
+    None
+}
+
+fn realpath<T: AsRef<std::ffi::OsStr>>(path: T) -> Result<String, Error> {
+    let path = std::path::PathBuf::from(&path);
+
+    if path.is_relative() {
+        #[cfg(windows)]
+        {
+            if let Some(mut p) = crate::config::get_config_dir() {
+                p.push(&path);
+                if let Ok(rp) = p.canonicalize() {
+                    return Ok(rp.display().to_string());
+                };
+            }
+        }
+
+        if let Some(paths) = std::env::var_os("PATH") {
+            for mut p in std::env::split_paths(&paths) {
+                p.push(&path);
+                if let Ok(rp) = p.canonicalize() {
+                    return Ok(rp.display().to_string());
+                };
+            }
+        }
+    }
+
+    Ok(path.display().to_string())
+}
+
+#[test]
+fn test_config_parse() {
+    // Custom values
+    let config: Config = toml::from_str(
+        r##"
+            mpv = "/usr/bin/mpv"
+            ytdl = "/usr/bin/yt-dlp"
+            proxy = "http://example.com:8080"
+            socket = "/tmp/mpv"
+            channel_order = "oldest"
+            subscriptions = ["https://www.youtube.com/@example"]
+            dearrow = true
+            notify = true
+            impersonate = "chrome"
+            pickformat = true
+            max_bitrate = 2500
+            bandwidth_probe = "https://example.com/100MB.bin"
+            metered = false
+            audio_lang = "en"
+            detach = true
+            now_playing = true
+            history = false
+            sort = "-date"
+            sidecars = true
+            title_from = "ytdlp:%(artist)s - %(track)s"
+            trust_domains = true
+            playlist_end = 200
+            concurrency = 8
+            background = true
+            log_dir = "/tmp/mpv-logs"
+            dedupe_profiles = true
+            dialog = "kdialog"
+            screen = 1
+            workspace = "2"
+            ytdl_format = "bestvideo[height<=?1080]+bestaudio/best"
+            content_filter = "/usr/local/bin/mpv-filter"
+            max_queue_length = 200
+            max_queue_hours = 12.5
+            idle_priority = true
+            osd = false
+            osd_duration = 5000
+            streamlink = "/usr/bin/streamlink"
+            pickitems = true
+            resume = false
+            ytdl_timeout = 60
+            ytdl_retries = 1
+            url_cache_ttl = 30
+            refresh_after = 240
+            downgrade_on_buffering = true
+            title_lang = "de"
+            dedupe = "ask"
+            download_dir = "/tmp/downloads"
+            keep_ld_env = true
+            log_level = "warn"
+            log_file = true
+
+            [logs]
+            keep = 5
+            max_age = 30
+            max_size = 1024
+
+            [sponsorblock]
+            categories = ["sponsor", "selfpromo"]
+
+            [profiles.music]
+            loudnorm = true
+            gapless = true
+            audio_device = "pulse/speakers"
+
+            [profiles.radio.queue]
+            type = "mpd"
+            address = "localhost:6600"
+
+            [discord]
+            client_id = "1234"
+            hidden_domains = ["example.com"]
+
+            [scrobble]
+            listenbrainz_token = "token"
+
+            [party]
+            follow = "192.168.1.2:7700"
+
+            [env]
+            LIBVA_DRIVER_NAME = "radeonsi"
+
+            [filter]
+            exclude = ["#shorts"]
+            min_duration = 60
+
+            [sites."twitch.tv"]
+            quality = "720p"
+            live_resolver = "streamlink"
+            profile = "live"
+            proxy = "http://example.com:3128"
+
+            [sites."music.youtube.com"]
+            format = "bestaudio/best"
+            format_sort = ["abr"]
+
+            [sites."youtube.com"]
+            v_codec = "vp9"
+
+            [sites."soundcloud.com"]
+            instance = "music"
+
+            [instances.music]
+            socket = "/tmp/mpv-music"
+            profile = "music"
+
+            [[auto_profiles]]
+            profile = "night"
+            after = "22:00"
+            before = "07:00"
+        "##,
+    )
+    .unwrap();
+
+    assert_eq!(config.mpv, Some("/usr/bin/mpv".to_string()));
+    assert_eq!(config.ytdl, Some("/usr/bin/yt-dlp".to_string()));
+    assert_eq!(config.proxy, Some("http://example.com:8080".to_string()));
+    assert_eq!(config.socket, Some("/tmp/mpv".to_string()));
+    assert_eq!(config.channel_order, Some(ChannelOrder::Oldest));
+    assert_eq!(
+        config.subscriptions,
+        Some(vec!["https://www.youtube.com/@example".to_string()])
+    );
+    assert_eq!(config.dearrow, Some(true));
+    assert_eq!(
+        config.profile(Some("music")),
+        Some(&Profile {
+            loudnorm: Some(true),
+            gapless: Some(true),
+            audio_device: Some("pulse/speakers".to_string()),
+            queue: None,
+        })
+    );
+    assert_eq!(
+        config.profile(Some("radio")).unwrap().queue,
+        Some(QueueBackend::Mpd {
+            address: "localhost:6600".to_string()
+        })
+    );
+    assert_eq!(config.profile(Some("video")), None);
+    assert_eq!(config.notify, Some(true));
+    assert_eq!(config.impersonate, Some("chrome".to_string()));
+    assert_eq!(config.pickformat, Some(true));
+    assert_eq!(config.max_bitrate, Some(2500));
+    assert_eq!(
+        config.bandwidth_probe,
+        Some("https://example.com/100MB.bin".to_string())
+    );
+    assert_eq!(config.metered, Some(false));
+    assert_eq!(config.audio_lang, Some("en".to_string()));
+    assert_eq!(config.detach, Some(true));
+    assert_eq!(config.now_playing, Some(true));
+    assert_eq!(
+        config.party,
+        Some(Party {
+            listen: None,
+            follow: Some("192.168.1.2:7700".to_string()),
+            token: None,
+        })
+    );
+    assert_eq!(
+        config.env,
+        Some(HashMap::from([(
+            "LIBVA_DRIVER_NAME".to_string(),
+            "radeonsi".to_string()
+        )]))
+    );
+    assert_eq!(
+        config.discord,
+        Some(Discord {
+            client_id: "1234".to_string(),
+            hidden_domains: vec!["example.com".to_string()],
+        })
+    );
+    assert_eq!(
+        config.scrobble,
+        Some(Scrobble {
+            profiles: vec!["music".to_string()],
+            listenbrainz_token: Some("token".to_string()),
+            lastfm_api_key: None,
+            lastfm_api_secret: None,
+            lastfm_session_key: None,
+        })
+    );
+    assert_eq!(config.history, Some(false));
+    assert_eq!(
+        config.filter,
+        Some(EntryFilter {
+            include: Vec::new(),
+            exclude: vec!["#shorts".to_string()],
+            min_duration: Some(60.0),
+            max_duration: None,
+        })
+    );
+    assert_eq!(
+        config.site("https://www.twitch.tv/example"),
+        Some(&Site {
+            quality: Some("720p".to_string()),
+            profile: Some("live".to_string()),
+            proxy: Some("http://example.com:3128".to_string()),
+            live_resolver: Some(LiveResolver::Streamlink),
+            ..Default::default()
+        })
+    );
+    assert_eq!(
+        config.site("https://music.youtube.com/watch?v=Ggkn2f5e-IU"),
+        Some(&Site {
+            format: Some("bestaudio/best".to_string()),
+            format_sort: vec!["abr".to_string()],
+            ..Default::default()
+        })
+    );
+    assert_eq!(
+        config.site("https://www.youtube.com/watch?v=Ggkn2f5e-IU"),
+        config.sites.as_ref().unwrap().get("youtube.com")
+    );
+    assert_eq!(config.site("https://notyoutube.com/"), None);
+    assert_eq!(
+        config
+            .site("https://soundcloud.com/example")
+            .unwrap()
+            .instance,
+        Some("music".to_string())
+    );
+    assert_eq!(
+        config.instance("music"),
+        Some(&Instance {
+            socket: "/tmp/mpv-music".to_string(),
+            profile: Some("music".to_string()),
+        })
+    );
+    assert_eq!(config.instance("video"), None);
+    assert_eq!(
+        config.sort,
+        Some(PlaylistSort {
+            key: SortKey::Date,
+            reverse: true
+        })
+    );
+    assert_eq!(config.sidecars, Some(true));
+    assert_eq!(
+        config.title_from,
+        Some("ytdlp:%(artist)s - %(track)s".to_string())
+    );
+    assert_eq!(config.trust_domains, Some(true));
+    assert_eq!(config.playlist_end, Some(200));
+    assert_eq!(config.concurrency, Some(8));
+    assert_eq!(config.background, Some(true));
+    assert_eq!(config.log_dir, Some("/tmp/mpv-logs".to_string()));
+    assert_eq!(
+        config.logs,
+        Some(LogRetention {
+            keep: Some(5),
+            max_age: Some(30),
+            max_size: Some(1024),
+        })
+    );
+    assert_eq!(config.dedupe_profiles, Some(true));
+    assert_eq!(config.dialog, Some(DialogBackend::Kdialog));
+    assert_eq!(config.screen, Some(1));
+    assert_eq!(config.workspace, Some("2".to_string()));
+    assert_eq!(
+        config.auto_profiles,
+        Some(vec![ProfileRule {
+            profile: "night".to_string(),
+            after: Some("22:00".to_string()),
+            before: Some("07:00".to_string()),
+            on_battery: None,
+        }])
+    );
+    assert_eq!(
+        config.ytdl_format,
+        Some("bestvideo[height<=?1080]+bestaudio/best".to_string())
+    );
+    assert_eq!(
+        config.content_filter,
+        Some("/usr/local/bin/mpv-filter".to_string())
+    );
+    assert_eq!(config.max_queue_length, Some(200));
+    assert_eq!(config.max_queue_hours, Some(12.5));
+    assert_eq!(config.idle_priority, Some(true));
+    assert_eq!(config.osd, Some(false));
+    assert_eq!(config.osd_duration, Some(5000));
+    assert_eq!(config.streamlink, Some("/usr/bin/streamlink".to_string()));
+    assert_eq!(config.pickitems, Some(true));
+    assert_eq!(config.resume, Some(false));
+    assert_eq!(config.ytdl_timeout, Some(60));
+    assert_eq!(config.ytdl_retries, Some(1));
+    assert_eq!(config.url_cache_ttl, Some(30));
+    assert_eq!(config.refresh_after, Some(240));
+    assert_eq!(config.downgrade_on_buffering, Some(true));
+    assert_eq!(config.title_lang, Some("de".to_string()));
+    assert_eq!(config.dedupe, Some(Dedupe::Ask));
+    assert_eq!(config.download_dir, Some("/tmp/downloads".to_string()));
+    assert_eq!(config.keep_ld_env, Some(true));
+    assert_eq!(config.log_level, Some(LogLevel::Warn));
+    assert_eq!(config.log_file, Some(true));
+    assert_eq!(
+        config.sponsorblock,
+        Some(SponsorBlock {
+            categories: vec!["sponsor".to_string(), "selfpromo".to_string()]
+        })
+    );
+
+    // Unexpected values
+    let config: Config = toml::from_str(
+        r#"
+            key1 = "value1"
+            key2 = "value2"
+            key3 = "value3"
+        "#,
+    )
+    .unwrap();
+
+    #[cfg(unix)]
+    assert_eq!(config.mpv, None);
+    assert_eq!(config.ytdl, None);
+    assert_eq!(config.proxy, None);
+    assert_eq!(config.socket, None);
+    assert_eq!(config.channel_order, None);
+    assert_eq!(config.subscriptions, None);
+    assert_eq!(config.sponsorblock, None);
+    assert_eq!(config.dearrow, None);
+    assert_eq!(config.notify, None);
+    assert_eq!(config.profiles, None);
+    assert_eq!(config.impersonate, None);
+    assert_eq!(config.pickformat, None);
+    assert_eq!(config.max_bitrate, None);
+    assert_eq!(config.bandwidth_probe, None);
+    assert_eq!(config.metered, None);
+    assert_eq!(config.audio_lang, None);
+    assert_eq!(config.detach, None);
+    assert_eq!(config.now_playing, None);
+    assert_eq!(config.discord, None);
+    assert_eq!(config.scrobble, None);
+    assert_eq!(config.history, None);
+    assert_eq!(config.sites, None);
+    assert_eq!(config.sort, None);
+    assert_eq!(config.filter, None);
+    assert_eq!(config.sidecars, None);
+    assert_eq!(config.title_from, None);
+    assert_eq!(config.trust_domains, None);
+    assert_eq!(config.playlist_end, None);
+    assert_eq!(config.concurrency, None);
+    assert_eq!(config.background, None);
+    assert_eq!(config.log_dir, None);
+    assert_eq!(config.logs, None);
+    assert_eq!(config.dedupe_profiles, None);
+    assert_eq!(config.dialog, None);
+    assert_eq!(config.screen, None);
+    assert_eq!(config.workspace, None);
+    assert_eq!(config.auto_profiles, None);
+    assert_eq!(config.ytdl_format, None);
+    assert_eq!(config.content_filter, None);
+    assert_eq!(config.max_queue_length, None);
+    assert_eq!(config.max_queue_hours, None);
+    assert_eq!(config.idle_priority, None);
+    assert_eq!(config.osd, None);
+    assert_eq!(config.osd_duration, None);
+    assert_eq!(config.streamlink, None);
+    assert_eq!(config.pickitems, None);
+    assert_eq!(config.party, None);
+    assert_eq!(config.resume, None);
+    assert_eq!(config.ytdl_timeout, None);
+    assert_eq!(config.ytdl_retries, None);
+    assert_eq!(config.url_cache_ttl, None);
+    assert_eq!(config.refresh_after, None);
+    assert_eq!(config.downgrade_on_buffering, None);
+    assert_eq!(config.title_lang, None);
+    assert_eq!(config.dedupe, None);
+    assert_eq!(config.env, None);
+    assert_eq!(config.download_dir, None);
+    assert_eq!(config.keep_ld_env, None);
+    assert_eq!(config.log_level, None);
+    assert_eq!(config.log_file, None);
+    assert_eq!(config.instances, None);
+}
+
+#[test]
+fn test_socket_template() {
+    let socket = expand_variables(
+        "/run/user/{uid}/mpv-{display}-{profile}.sock",
+        &[("uid", "1000"), ("user", "alice"), ("display", "0")],
+    );
+    assert_eq!(socket, "/run/user/1000/mpv-0-{profile}.sock");
+
+    let config = Config {
+        socket: Some(socket),
+        ..Default::default()
+    };
+    assert_eq!(
+        config.socket(Some("music")),
+        Some("/run/user/1000/mpv-0-music.sock".to_string())
+    );
+    assert_eq!(
+        config.socket(None),
+        Some("/run/user/1000/mpv-0-default.sock".to_string())
+    );
+}
@@ -0,0 +1,112 @@
+use crate::config::Config;
+use crate::plugins::play::Entry;
+use crate::protocol::Protocol;
+use serde::Serialize;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Item piped as JSON to the command of `Config.content_filter`
+#[derive(Serialize)]
+struct FilteredItem<'a> {
+    url: &'a str,
+    title: &'a str,
+    duration: Option<f64>,
+    upload_date: Option<&'a str>,
+    profile: Option<&'a str>,
+    tags: &'a [String],
+}
+
+/// Drop the entries blocked by `Config.content_filter`, and explain the
+/// blocks in a dialog
+///
+/// The command exits with `0` to allow an item, and prints the reason of a
+/// block. Items are blocked if the command can't be run.
+pub fn retain(config: &Config, proto: &Protocol, entries: &mut Vec<Entry>) {
+    let Some(command) = &config.content_filter else {
+        return;
+    };
+
+    let mut blocked = Vec::new();
+    entries.retain(|entry| match check(command, proto, entry) {
+        Ok(()) => true,
+        Err(reason) => {
+            log::info!("Blocked by content filter: {} ({})", entry.title, reason);
+            blocked.push(format!("• {} ({})", entry.title, reason));
+            false
+        }
+    });
+
+    if !blocked.is_empty() {
+        let text = format!(
+            "{} items were blocked by the content filter:\n\n{}",
+            blocked.len(),
+            blocked.join("\n")
+        );
+        crate::dialog::message(config, &text);
+    }
+}
+
+/// Returns the reason if the command blocks the entry
+fn check(command: &str, proto: &Protocol, entry: &Entry) -> Result<(), String> {
+    let item = FilteredItem {
+        url: &entry.url,
+        title: &entry.title,
+        duration: entry.duration,
+        upload_date: entry.upload_date.as_deref(),
+        profile: proto.profile,
+        tags: proto.tags.as_deref().unwrap_or_default(),
+    };
+    let input = serde_json::to_string(&item).map_err(|e| e.to_string())?;
+
+    let mut child = Command::new(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("filter failed: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(input.as_bytes());
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("filter failed: {e}"))?;
+
+    if output.status.success() {
+        return Ok(());
+    }
+    let reason = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Err(match reason.is_empty() {
+        true => "blocked".to_string(),
+        false => reason,
+    })
+}
+
+#[cfg(unix)]
+#[test]
+fn test_content_filter() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path =
+        std::env::temp_dir().join(format!("mpv-handler-test-filter-{}.sh", std::process::id()));
+    let script =
+        "#!/bin/sh\ngrep -q '\"title\":\"Blocked' && { echo 'Not for kids'; exit 1; }\nexit 0\n";
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let proto = Protocol::from_url("https://example.com/".to_string());
+    let entry = |title: &str| Entry {
+        title: title.to_string(),
+        url: "https://example.com/v".to_string(),
+        duration: None,
+        upload_date: None,
+        range: None,
+    };
+    let command = path.to_str().unwrap();
+    assert_eq!(check(command, &proto, &entry("Allowed video")), Ok(()));
+    assert_eq!(
+        check(command, &proto, &entry("Blocked video")),
+        Err("Not for kids".to_string())
+    );
+    assert!(check("/nonexistent/filter", &proto, &entry("Allowed video")).is_err());
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,56 @@
+use crate::config::Config;
+
+const DEARROW_API: &str = "https://sponsor.ajay.app/api/branding";
+
+/// Returns the DeArrow community title of a YouTube URL
+///
+/// Returns `None` if DeArrow is disabled, the URL isn't a YouTube video or
+/// no title was submitted.
+pub fn title(config: &Config, url: &str) -> Option<String> {
+    if config.dearrow != Some(true) {
+        return None;
+    }
+
+    let id = crate::url::youtube_id(url)?;
+    let branding = crate::http::get_json(config, DEARROW_API, &[("videoID", id)])?;
+    let title = find_title(&branding)?;
+
+    log::debug!("DeArrow title: {}", title);
+    Some(title)
+}
+
+/// Find the first trusted, non-original title in the branding response
+fn find_title(branding: &serde_json::Value) -> Option<String> {
+    branding["titles"]
+        .as_array()?
+        .iter()
+        .filter(|v| v["original"] != true)
+        .find(|v| v["locked"] == true || v["votes"].as_i64().unwrap_or(0) >= 0)
+        .and_then(|v| v["title"].as_str())
+        .map(format_title)
+}
+
+/// Remove `>` markers, which prevent a word from being auto-formatted
+fn format_title(title: &str) -> String {
+    title
+        .split(' ')
+        .map(|v| v.strip_prefix('>').unwrap_or(v))
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[test]
+fn test_dearrow_title() {
+    let branding = serde_json::json!({
+        "titles": [
+            { "title": "Original", "original": true, "votes": 5, "locked": false },
+            { "title": "Downvoted", "original": false, "votes": -1, "locked": false },
+            { "title": "How >iPhone works", "original": false, "votes": 0, "locked": false }
+        ],
+        "thumbnails": []
+    });
+    assert_eq!(find_title(&branding), Some("How iPhone works".to_string()));
+
+    let branding = serde_json::json!({ "titles": [], "thumbnails": [] });
+    assert_eq!(find_title(&branding), None);
+}
@@ -0,0 +1,417 @@
+use crate::config::{Config, DialogBackend};
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Exit code of `zenity` and `yad` when the dialog timed out
+const ZENITY_TIMEOUT: i32 = 5;
+const YAD_TIMEOUT: i32 = 70;
+
+/// Answer of an entry dialog
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    Text(String),
+    /// Timed out, or no dialog backend is used, so the default applies
+    Default,
+    Cancelled,
+}
+
+/// Returns the dialog backend of `Config.dialog`, `zenity` by default
+fn backend(config: &Config) -> DialogBackend {
+    config.dialog.clone().unwrap_or(DialogBackend::Zenity)
+}
+
+/// Ask a question with the buttons `labels`, at least two
+///
+/// Returns the index of the chosen button, or `None` if the dialog is
+/// dismissed or can't be shown.
+pub fn choose(config: &Config, text: &str, labels: &[&str]) -> Option<usize> {
+    let output = match backend(config) {
+        DialogBackend::Zenity => {
+            let mut command = Command::new("zenity");
+            command
+                .arg("--question")
+                .arg("--title=mpv-handler")
+                .arg("--text")
+                .arg(text)
+                .arg(format!("--ok-label={}", labels[0]))
+                .arg(format!("--cancel-label={}", labels[1]));
+            for label in &labels[2..] {
+                command.arg(format!("--extra-button={label}"));
+            }
+            let output = run(&mut command)?;
+            return zenity_choice(output.status.code(), &stdout(&output), labels);
+        }
+        DialogBackend::Kdialog => {
+            let mut command = Command::new("kdialog");
+            command
+                .arg("--title=mpv-handler")
+                .arg(if labels.len() > 2 {
+                    "--yesnocancel"
+                } else {
+                    "--yesno"
+                })
+                .arg(text)
+                .arg(format!("--yes-label={}", labels[0]))
+                .arg(format!("--no-label={}", labels[1]));
+            if let Some(label) = labels.get(2) {
+                command.arg(format!("--cancel-label={label}"));
+            }
+            run(&mut command)?
+        }
+        DialogBackend::Yad => {
+            let mut command = Command::new("yad");
+            command.arg("--title=mpv-handler").arg("--text").arg(text);
+            for (i, label) in labels.iter().enumerate() {
+                command.arg(format!("--button={label}:{i}"));
+            }
+            run(&mut command)?
+        }
+        DialogBackend::Rofi | DialogBackend::Dmenu => {
+            let choice = menu(config, text, labels.iter().map(|v| v.to_string()))?;
+            return labels.iter().position(|v| *v == choice);
+        }
+        DialogBackend::Disabled => return None,
+    };
+
+    // The exit code of kdialog and yad is the index of the button
+    let code = usize::try_from(output.status.code()?).ok()?;
+    (code < labels.len()).then_some(code)
+}
+
+/// Show an information dialog, e.g. the reason a link was refused
+pub fn message(config: &Config, text: &str) {
+    let mut command = match backend(config) {
+        DialogBackend::Zenity => {
+            let mut command = Command::new("zenity");
+            command
+                .arg("--info")
+                .arg("--title=mpv-handler")
+                .arg("--text")
+                .arg(text);
+            command
+        }
+        DialogBackend::Kdialog => {
+            let mut command = Command::new("kdialog");
+            command.arg("--title=mpv-handler").arg("--msgbox").arg(text);
+            command
+        }
+        DialogBackend::Yad => {
+            let mut command = Command::new("yad");
+            command
+                .arg("--title=mpv-handler")
+                .arg("--text")
+                .arg(text)
+                .arg("--button=OK:0");
+            command
+        }
+        DialogBackend::Rofi => {
+            let mut command = Command::new("rofi");
+            command.arg("-e").arg(text);
+            command
+        }
+        DialogBackend::Dmenu => {
+            menu(config, text, std::iter::once("OK".to_string()));
+            return;
+        }
+        DialogBackend::Disabled => return,
+    };
+    run(&mut command);
+}
+
+/// Ask for a text, prefilled with `default`
+///
+/// Dialogs which support it time out after `timeout` seconds.
+pub fn entry(config: &Config, text: &str, default: &str, cancel: &str, timeout: u32) -> Input {
+    let mut command = match backend(config) {
+        DialogBackend::Zenity => {
+            let mut command = Command::new("zenity");
+            command
+                .arg("--entry")
+                .arg("--text")
+                .arg(text)
+                .arg("--entry-text")
+                .arg(default)
+                .arg(format!("--cancel-label={cancel}"))
+                .arg(format!("--timeout={timeout}"));
+            command
+        }
+        DialogBackend::Kdialog => {
+            let mut command = Command::new("kdialog");
+            command
+                .arg("--title=mpv-handler")
+                .arg("--inputbox")
+                .arg(text)
+                .arg(default);
+            command
+        }
+        DialogBackend::Yad => {
+            let mut command = Command::new("yad");
+            command
+                .arg("--entry")
+                .arg("--title=mpv-handler")
+                .arg("--text")
+                .arg(text)
+                .arg("--entry-text")
+                .arg(default)
+                .arg(format!("--button={cancel}:1"))
+                .arg("--button=OK:0")
+                .arg(format!("--timeout={timeout}"));
+            command
+        }
+        DialogBackend::Rofi | DialogBackend::Dmenu => {
+            return match menu(config, text, std::iter::once(default.to_string())) {
+                Some(v) => Input::Text(v),
+                None => Input::Cancelled,
+            };
+        }
+        DialogBackend::Disabled => return Input::Default,
+    };
+
+    match run(&mut command) {
+        Some(output) => match output.status.code() {
+            Some(0) => Input::Text(stdout(&output)),
+            Some(ZENITY_TIMEOUT) if backend(config) == DialogBackend::Zenity => Input::Default,
+            Some(YAD_TIMEOUT) if backend(config) == DialogBackend::Yad => Input::Default,
+            _ => Input::Cancelled,
+        },
+        None => Input::Cancelled,
+    }
+}
+
+/// Ask to pick a row of a table, returns the first column of the picked row
+///
+/// Returns `None` if the dialog is cancelled or can't be shown.
+pub fn list(
+    config: &Config,
+    title: &str,
+    text: &str,
+    columns: &[&str],
+    rows: &[Vec<String>],
+) -> Option<String> {
+    let mut command = match backend(config) {
+        DialogBackend::Zenity | DialogBackend::Yad => {
+            let mut command = Command::new(match backend(config) {
+                DialogBackend::Yad => "yad",
+                _ => "zenity",
+            });
+            command
+                .arg("--list")
+                .arg(format!("--title={title}"))
+                .arg("--text")
+                .arg(text)
+                .arg("--width=900")
+                .arg("--height=600");
+            if backend(config) == DialogBackend::Yad {
+                command.arg("--print-column=1").arg("--separator=");
+            }
+            for column in columns {
+                command.arg(format!("--column={column}"));
+            }
+            command.args(rows.iter().flatten());
+            command
+        }
+        DialogBackend::Kdialog => {
+            let mut command = Command::new("kdialog");
+            command
+                .arg(format!("--title={title}"))
+                .arg("--menu")
+                .arg(text);
+            for row in rows {
+                command.arg(&row[0]).arg(row.join("  "));
+            }
+            command
+        }
+        DialogBackend::Rofi | DialogBackend::Dmenu => {
+            let lines: Vec<String> = rows.iter().map(|v| v.join("  ")).collect();
+            let choice = menu(config, title, lines.iter().cloned())?;
+            let row = lines.iter().position(|v| *v == choice)?;
+            return Some(rows[row][0].clone());
+        }
+        DialogBackend::Disabled => return None,
+    };
+
+    let output = run(&mut command)?;
+    output.status.success().then(|| stdout(&output))
+}
+
+/// Ask to check rows of a table, all checked at first
+///
+/// Returns the indices of the checked rows, all of them if no dialog backend
+/// is used, or `None` if the dialog is cancelled or can't be shown. `dmenu`
+/// can't check several rows, so it picks one.
+pub fn checklist(
+    config: &Config,
+    title: &str,
+    text: &str,
+    columns: &[&str],
+    rows: &[Vec<String>],
+) -> Option<Vec<usize>> {
+    // Rows are identified by their number, which the dialogs print
+    let numbered = rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| (i + 1).to_string() + "  " + &row.join("  "));
+    let mut command = match backend(config) {
+        DialogBackend::Zenity | DialogBackend::Yad => {
+            let mut command = Command::new(match backend(config) {
+                DialogBackend::Yad => "yad",
+                _ => "zenity",
+            });
+            command
+                .arg("--list")
+                .arg("--checklist")
+                .arg(format!("--title={title}"))
+                .arg("--text")
+                .arg(text)
+                .arg("--width=900")
+                .arg("--height=600")
+                .arg("--print-column=2")
+                .arg("--column=")
+                .arg("--column=#");
+            command.arg(match backend(config) {
+                DialogBackend::Yad => "--separator=",
+                _ => "--separator=\n",
+            });
+            for column in columns {
+                command.arg(format!("--column={column}"));
+            }
+            for (i, row) in rows.iter().enumerate() {
+                command.arg("TRUE").arg((i + 1).to_string()).args(row);
+            }
+            command
+        }
+        DialogBackend::Kdialog => {
+            let mut command = Command::new("kdialog");
+            command
+                .arg(format!("--title={title}"))
+                .arg("--separate-output")
+                .arg("--checklist")
+                .arg(text);
+            for (i, line) in numbered.enumerate() {
+                command.arg((i + 1).to_string()).arg(line).arg("on");
+            }
+            command
+        }
+        DialogBackend::Rofi | DialogBackend::Dmenu => {
+            let choice = pick(config, title, numbered, true)?;
+            return Some(checked_rows(&choice, rows.len()));
+        }
+        DialogBackend::Disabled => return Some((0..rows.len()).collect()),
+    };
+
+    let output = run(&mut command)?;
+    output
+        .status
+        .success()
+        .then(|| checked_rows(&stdout(&output), rows.len()))
+}
+
+/// Returns the indices of the rows numbered at the start of the output lines
+///
+/// kdialog quotes the numbers, menus print the whole row.
+fn checked_rows(stdout: &str, count: usize) -> Vec<usize> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let number = line.trim().trim_matches('"').split_whitespace().next()?;
+            let number: usize = number.trim_end_matches('|').parse().ok()?;
+            (1..=count).contains(&number).then(|| number - 1)
+        })
+        .collect()
+}
+
+/// Pick a line with `rofi -dmenu` or `dmenu`, which also accept typed text
+fn menu(config: &Config, prompt: &str, lines: impl Iterator<Item = String>) -> Option<String> {
+    pick(config, prompt, lines, false)
+}
+
+/// Pick lines with `rofi -dmenu` or `dmenu`, `multiple` lines with rofi only
+///
+/// Returns the picked lines, one per line.
+fn pick(
+    config: &Config,
+    prompt: &str,
+    lines: impl Iterator<Item = String>,
+    multiple: bool,
+) -> Option<String> {
+    // Single line prompts only
+    let prompt = prompt.replace('\n', " ");
+    let mut command = match backend(config) {
+        DialogBackend::Rofi => {
+            let mut command = Command::new("rofi");
+            command.arg("-dmenu").arg("-p").arg(&prompt);
+            if multiple {
+                command.arg("-multi-select");
+            }
+            command
+        }
+        _ => {
+            let mut command = Command::new("dmenu");
+            command.arg("-p").arg(&prompt);
+            command
+        }
+    };
+
+    let child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn();
+    let mut child = match child {
+        Ok(v) => v,
+        Err(e) => {
+            log::warn!("Dialog command failed: {}", e);
+            return None;
+        }
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        let input: String = lines.map(|v| v + "\n").collect();
+        let _ = stdin.write_all(input.as_bytes());
+    }
+
+    let output = child.wait_with_output().ok()?;
+    let choice = stdout(&output);
+    (output.status.success() && !choice.is_empty()).then_some(choice)
+}
+
+fn run(command: &mut Command) -> Option<Output> {
+    match command.output() {
+        Ok(v) => Some(v),
+        Err(e) => {
+            log::warn!("Dialog command failed: {}", e);
+            None
+        }
+    }
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Returns the chosen button of the `zenity` exit code and output
+///
+/// Extra buttons exit like cancel, but print their label.
+fn zenity_choice(code: Option<i32>, stdout: &str, labels: &[&str]) -> Option<usize> {
+    match (code, stdout.trim()) {
+        (Some(0), _) => Some(0),
+        (Some(1), "") => Some(1),
+        (Some(1), label) => labels[2..].iter().position(|v| *v == label).map(|v| v + 2),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_checked_rows() {
+    assert_eq!(checked_rows("1\n3\n", 3), vec![0, 2]);
+    assert_eq!(checked_rows("\"2\"\n\"3\"", 3), vec![1, 2]);
+    assert_eq!(checked_rows("2|\n", 3), vec![1]);
+    assert_eq!(checked_rows("3  Title  4:00\n", 3), vec![2]);
+    assert_eq!(checked_rows("0\n4\nTitle", 3), Vec::<usize>::new());
+}
+
+#[test]
+fn test_zenity_choice() {
+    let labels = ["Always", "Never", "Once"];
+    assert_eq!(zenity_choice(Some(0), "", &labels), Some(0));
+    assert_eq!(zenity_choice(Some(1), "Once\n", &labels), Some(2));
+    assert_eq!(zenity_choice(Some(1), "", &labels), Some(1));
+    // Timeout or closed by the window manager
+    assert_eq!(zenity_choice(Some(5), "", &labels), None);
+    assert_eq!(zenity_choice(Some(-1), "", &labels), None);
+}
@@ -0,0 +1,208 @@
+use crate::config::Discord;
+use crate::observer::{Listener, Playback};
+use serde_json::json;
+use std::io::Write;
+
+/// Opcodes of Discord's IPC frames
+const OP_HANDSHAKE: u32 = 0;
+const OP_FRAME: u32 = 1;
+/// Activity type `Watching`
+const ACTIVITY_WATCHING: u8 = 3;
+
+/// Publishes the playing item as Discord Rich Presence, enabled by
+/// `Config.discord`
+pub struct Presence {
+    config: Discord,
+    pipe: Option<Box<dyn Write + Send>>,
+}
+
+impl Presence {
+    pub fn new(config: Discord) -> Presence {
+        Presence { config, pipe: None }
+    }
+
+    /// Send the frame, connecting to Discord first if needed
+    ///
+    /// Discord may be started or restarted after the daemon, so failures
+    /// drop the connection and the next item connects again.
+    fn send(&mut self, payload: serde_json::Value) {
+        if self.pipe.is_none() {
+            self.pipe = connect(&self.config.client_id);
+        }
+        if let Some(pipe) = &mut self.pipe
+            && pipe.write_all(&frame(OP_FRAME, &payload)).is_err()
+        {
+            self.pipe = None;
+        }
+    }
+}
+
+impl Listener for Presence {
+    fn now_playing(&mut self, playback: &Playback) {
+        let activity = activity(
+            &self.config,
+            playback,
+            chrono::Utc::now().timestamp_millis(),
+        );
+        self.send(json!({
+            "cmd": "SET_ACTIVITY",
+            "args": { "pid": std::process::id(), "activity": activity },
+            "nonce": format!("{}", chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()),
+        }));
+    }
+}
+
+/// Returns the activity of the playing item, started at `start` (ms)
+///
+/// Items of `hidden_domains` only show that something is being watched.
+fn activity(config: &Discord, playback: &Playback, start: i64) -> serde_json::Value {
+    let domain = playback_url(playback)
+        .and_then(|v| crate::url::split_url(&v).map(|(host, _)| host.to_string()));
+    let hidden = domain.as_deref().is_some_and(|host| {
+        config
+            .hidden_domains
+            .iter()
+            .any(|v| host == v || host.ends_with(&format!(".{v}")))
+    });
+
+    if hidden {
+        return json!({
+            "type": ACTIVITY_WATCHING,
+            "details": "Watching something",
+            "timestamps": { "start": start },
+        });
+    }
+
+    let mut activity = json!({
+        "type": ACTIVITY_WATCHING,
+        "details": playback.title.as_deref().unwrap_or("mpv"),
+        "timestamps": { "start": start },
+    });
+    if let Some(state) = playback.uploader.as_deref().or(domain.as_deref()) {
+        activity["state"] = json!(state);
+    }
+    activity
+}
+
+/// Returns the original URL of the playing item, prefetched direct URLs are
+/// looked up in the queue state
+fn playback_url(playback: &Playback) -> Option<String> {
+    let path = playback.path.as_deref()?;
+    match crate::queue::QueueState::load().find(path) {
+        Some(item) => Some(item.url.clone()),
+        None => Some(path.to_string()),
+    }
+}
+
+/// Encode an IPC frame: opcode and length (little-endian), then the JSON
+fn frame(op: u32, payload: &serde_json::Value) -> Vec<u8> {
+    let data = payload.to_string();
+    let mut frame = Vec::with_capacity(8 + data.len());
+    frame.extend_from_slice(&op.to_le_bytes());
+    frame.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    frame.extend_from_slice(data.as_bytes());
+    frame
+}
+
+/// Connect to the IPC socket of a running Discord client and handshake
+fn connect(client_id: &str) -> Option<Box<dyn Write + Send>> {
+    let handshake = frame(OP_HANDSHAKE, &json!({ "v": 1, "client_id": client_id }));
+
+    for path in ipc_paths() {
+        #[cfg(unix)]
+        let pipe = std::os::unix::net::UnixStream::connect(&path);
+        #[cfg(windows)]
+        let pipe = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path);
+
+        if let Ok(mut pipe) = pipe
+            && pipe.write_all(&handshake).is_ok()
+        {
+            log::info!("Connected to Discord: {}", path.display());
+            return Some(Box::new(pipe));
+        }
+    }
+    None
+}
+
+/// Returns the candidate paths of Discord's IPC socket
+fn ipc_paths() -> Vec<std::path::PathBuf> {
+    #[cfg(unix)]
+    {
+        let mut dirs: Vec<std::path::PathBuf> = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+            .iter()
+            .filter_map(|v| std::env::var_os(v).map(Into::into))
+            .collect();
+        dirs.push("/tmp".into());
+
+        let mut paths = Vec::new();
+        for dir in dirs {
+            // Flatpak and Snap builds of Discord use sub directories
+            for sub in ["", "app/com.discordapp.Discord", "snap.discord"] {
+                for i in 0..10 {
+                    paths.push(dir.join(sub).join(format!("discord-ipc-{i}")));
+                }
+            }
+        }
+        paths
+    }
+
+    #[cfg(windows)]
+    {
+        (0..10)
+            .map(|i| format!(r"\\.\pipe\discord-ipc-{i}").into())
+            .collect()
+    }
+}
+
+#[test]
+fn test_discord_frame() {
+    let frame = frame(OP_FRAME, &json!({ "v": 1 }));
+    assert_eq!(&frame[..4], &[1, 0, 0, 0]);
+    assert_eq!(&frame[4..8], &[7, 0, 0, 0]);
+    assert_eq!(&frame[8..], br#"{"v":1}"#);
+}
+
+#[test]
+fn test_discord_activity() {
+    let config = Discord {
+        client_id: "1234".to_string(),
+        hidden_domains: vec!["example.com".to_string()],
+    };
+    let mut playback = Playback {
+        pos: Some(0),
+        count: Some(1),
+        title: Some("Title".to_string()),
+        path: Some("https://www.youtube.com/watch?v=Ggkn2f5e-IU".to_string()),
+        uploader: Some("Uploader".to_string()),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        activity(&config, &playback, 1000),
+        json!({
+            "type": 3,
+            "details": "Title",
+            "state": "Uploader",
+            "timestamps": { "start": 1000 },
+        })
+    );
+
+    playback.uploader = None;
+    assert_eq!(
+        activity(&config, &playback, 1000)["state"],
+        "www.youtube.com"
+    );
+
+    playback.path = Some("https://videos.example.com/watch/1".to_string());
+    assert_eq!(
+        activity(&config, &playback, 1000),
+        json!({
+            "type": 3,
+            "details": "Watching something",
+            "timestamps": { "start": 1000 },
+        })
+    );
+}
@@ -0,0 +1,60 @@
+use thiserror::Error;
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Too many arguments")]
+    TooManyArgs,
+    #[error("Unknown command \"{0}\"")]
+    UnknownCommand(String),
+    #[error("Incorrect protocol \"{0}\"")]
+    IncorrectProtocol(String),
+    #[error("Incorrect video URL \"{0}\"")]
+    IncorrectVideoURL(String),
+    #[error("Dangerous video protocol \"{0}\"")]
+    DangerousVideoProtocol(String),
+    #[error("Player exited by error")]
+    PlayerExited(u8),
+    #[error("Player rejected option \"{0}\"")]
+    OptionRejected(String),
+    #[error("Failed to run player ({0})")]
+    PlayerRunFailed(std::io::Error),
+    #[error("Failed to decode ({0})")]
+    FromBase64Error(#[from] base64::DecodeError),
+    #[error("Failed to decode ({0})")]
+    FromStringError(#[from] std::string::FromUtf8Error),
+    #[error("Failed to decode ({0})")]
+    FromTomlError(#[from] toml::de::Error),
+    #[error("Failed to decode ({0})")]
+    FromIoError(#[from] std::io::Error),
+    #[error("Failed to decode ({0})")]
+    FromXmlError(#[from] roxmltree::Error),
+    #[error("Failed to decode ({0})")]
+    FromJsonError(#[from] serde_json::Error),
+    #[error("Interrupted")]
+    Interrupted,
+    #[error("Insecure mpv socket \"{0}\" ({1})")]
+    InsecureSocket(String, String),
+    #[error("Socket \"{0}\" is not an mpv IPC socket")]
+    NotMpvSocket(String),
+    #[error("Domain \"{0}\" is not allowed to open mpv")]
+    DomainNotAllowed(String),
+    #[error("mpv failed to run command {0}")]
+    CommandFailed(String),
+    #[error("Socket connection failed")]
+    SocketConnectionFailed,
+    #[error("Failed to fetch feed \"{0}\"")]
+    FeedFetchFailed(String),
+    #[error("No playable episodes in feed \"{0}\"")]
+    EmptyFeed(String),
+    #[error("yt-dlp timed out after {0} seconds")]
+    YtdlTimeout(u64),
+    #[error("Failed to download \"{0}\"")]
+    DownloadFailed(String),
+    #[error("Unknown mpv instance \"{0}\"")]
+    UnknownInstance(String),
+    #[error("Queue backend failed ({0})")]
+    SinkFailed(String),
+    #[error("Remote mpv \"{0}\" isn't reachable")]
+    RemoteUnreachable(String),
+}
@@ -0,0 +1,126 @@
+use crate::config::Config;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// TTL of the cached format list of a video
+const FORMATS_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Format of a video listed by `yt-dlp -F`
+///
+/// - `id`: format ID passed to `-f`
+/// - `ext`: file extension
+/// - `info`: the remaining columns (resolution, codecs, bitrate, etc.)
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Format {
+    pub id: String,
+    pub ext: String,
+    pub info: String,
+}
+
+impl Format {
+    /// Returns `true` if the format has no audio, so it should be merged
+    pub fn is_video_only(&self) -> bool {
+        self.info.contains("video only")
+    }
+
+    /// Returns format selector of this format, merged with the best audio
+    /// if the format is video only
+    pub fn selector(&self) -> String {
+        match self.is_video_only() {
+            true => format!("{}+bestaudio", self.id),
+            false => self.id.clone(),
+        }
+    }
+}
+
+/// List formats of the URL by `yt-dlp -F`
+///
+/// The list is cached per video for a short time, so picking a format
+/// again doesn't hit the extractor twice.
+pub fn list_formats(config: &Config, url: &str) -> Vec<Format> {
+    let key = crate::url::youtube_id(url).unwrap_or(url);
+    if let Some(formats) = crate::cache::get("formats", key, FORMATS_CACHE_TTL) {
+        return formats;
+    }
+
+    let formats = fetch_formats(config, url);
+    if !formats.is_empty() {
+        crate::cache::put("formats", key, &formats, FORMATS_CACHE_TTL);
+    }
+    formats
+}
+
+fn fetch_formats(config: &Config, url: &str) -> Vec<Format> {
+    let output = crate::plugins::play::ytdl_output(
+        config,
+        crate::plugins::play::ytdl_command(config)
+            .arg("-F")
+            .arg(url),
+    );
+
+    match output {
+        Ok(Some(output)) if output.status.success() => {
+            parse_format_table(&String::from_utf8_lossy(&output.stdout))
+        }
+        _ => {
+            log::warn!("Failed to list formats: {}", url);
+            Vec::new()
+        }
+    }
+}
+
+/// Parse the format table printed by `yt-dlp -F`
+///
+/// Storyboards (`mhtml` images) are skipped, since they are not playable.
+fn parse_format_table(table: &str) -> Vec<Format> {
+    let mut formats = Vec::new();
+
+    // Rows start after the dashed line below the header
+    for line in table.lines().skip_while(|v| !v.starts_with("---")).skip(1) {
+        let mut columns = line.split_whitespace();
+        let (Some(id), Some(ext)) = (columns.next(), columns.next()) else {
+            continue;
+        };
+        if ext == "mhtml" {
+            continue;
+        }
+
+        let info = columns
+            .filter(|v| *v != "|")
+            .collect::<Vec<&str>>()
+            .join(" ");
+
+        formats.push(Format {
+            id: id.to_string(),
+            ext: ext.to_string(),
+            info,
+        });
+    }
+
+    formats
+}
+
+#[test]
+fn test_format_table_parse() {
+    let formats = parse_format_table(
+        "[youtube] Extracting URL: https://www.youtube.com/watch?v=Ggkn2f5e-IU
+[info] Available formats for Ggkn2f5e-IU:
+ID  EXT   RESOLUTION FPS CH |   FILESIZE   TBR PROTO | VCODEC        VBR ACODEC      ABR ASR MORE INFO
+----------------------------------------------------------------------------------------------------------
+sb0 mhtml 48x27        0    |                  mhtml | images                                storyboard
+140 m4a   audio only      2 |    3.05MiB  129k https | audio only        mp4a.40.2  129k 44k medium, m4a_dash
+137 mp4   1920x1080   30    |   40.12MiB 1693k https | avc1.640028 1693k video only          1080p, mp4_dash
+18  mp4   640x360     30  2 |    8.63MiB  364k https | avc1.42001E       mp4a.40.2       44k 360p",
+    );
+
+    assert_eq!(formats.len(), 3);
+    assert_eq!(formats[0].id, "140");
+    assert_eq!(formats[0].ext, "m4a");
+    assert_eq!(formats[0].selector(), "140");
+    assert!(formats[1].is_video_only());
+    assert_eq!(formats[1].selector(), "137+bestaudio");
+    assert_eq!(
+        formats[2].info,
+        "640x360 30 2 8.63MiB 364k https avc1.42001E mp4a.40.2 44k 360p"
+    );
+}
@@ -0,0 +1,184 @@
+use crate::observer::{Listener, Playback};
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Items watched for at least this share of their duration are completed
+const COMPLETED_RATIO: f64 = 0.9;
+/// Items played shorter than this aren't recorded
+const MIN_WATCHED_SECONDS: f64 = 5.0;
+/// Items stopped before this position start from the beginning again
+const MIN_RESUME_SECONDS: f64 = 30.0;
+
+/// Watched item, stored as one JSON line in `history.jsonl`
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct HistoryEntry {
+    /// Start time (Unix timestamp)
+    pub time: i64,
+    pub title: String,
+    /// Original URL of the item
+    pub url: String,
+    pub domain: Option<String>,
+    pub uploader: Option<String>,
+    /// Duration in seconds, if known
+    pub duration: Option<f64>,
+    /// Unpaused playback time in seconds
+    pub watched: f64,
+    pub completed: bool,
+    /// Tags of the link which enqueued the item
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Playback position in seconds when it stopped, if known
+    #[serde(default)]
+    pub position: Option<f64>,
+}
+
+impl HistoryEntry {
+    /// Returns the start time in RFC 3339 format of the local time zone
+    pub fn local_time(&self) -> String {
+        match Local.timestamp_opt(self.time, 0).single() {
+            Some(v) => v.to_rfc3339(),
+            None => self.time.to_string(),
+        }
+    }
+}
+
+/// Records watched items, enabled unless `Config.history = false`
+#[derive(Default)]
+pub struct Recorder {
+    started: i64,
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder::default()
+    }
+}
+
+impl Listener for Recorder {
+    fn now_playing(&mut self, _playback: &Playback) {
+        self.started = Local::now().timestamp();
+    }
+
+    fn finished(&mut self, playback: &Playback, played: f64) {
+        if played < MIN_WATCHED_SECONDS {
+            return;
+        }
+        let (Some(title), Some(path)) = (&playback.title, &playback.path) else {
+            return;
+        };
+
+        // Prefetched direct URLs are looked up in the queue state
+        let (url, tags) = match crate::queue::QueueState::load().find(path) {
+            Some(item) => (item.url.clone(), item.tags.clone()),
+            None => (path.clone(), Vec::new()),
+        };
+        let domain = crate::url::split_url(&url).map(|(host, _)| host.to_string());
+
+        let entry = HistoryEntry {
+            time: self.started,
+            title: title.clone(),
+            url,
+            domain,
+            uploader: playback.uploader.clone(),
+            duration: playback.duration,
+            watched: played.round(),
+            completed: playback
+                .duration
+                .is_some_and(|v| played >= v * COMPLETED_RATIO),
+            tags,
+            position: playback.position.map(f64::round),
+        };
+        if let Err(e) = append(&entry) {
+            log::warn!("Failed to record history: {}", e);
+        }
+    }
+}
+
+/// Returns the history entries, oldest first
+///
+/// Broken lines are skipped.
+pub fn load() -> Vec<HistoryEntry> {
+    history_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|data| {
+            data.lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the positions to resume items at by their URL, disabled by
+/// `Config.resume = false`
+pub fn resume_positions(config: &crate::config::Config) -> HashMap<String, f64> {
+    if config.resume == Some(false) {
+        return HashMap::new();
+    }
+    positions(&load())
+}
+
+/// Returns the last positions of the items which weren't completed
+fn positions(entries: &[HistoryEntry]) -> HashMap<String, f64> {
+    let mut positions = HashMap::new();
+    for entry in entries {
+        match entry.position {
+            Some(v) if !entry.completed && v >= MIN_RESUME_SECONDS => {
+                positions.insert(entry.url.clone(), v);
+            }
+            _ => {
+                positions.remove(&entry.url);
+            }
+        }
+    }
+    positions
+}
+
+fn append(entry: &HistoryEntry) -> Result<(), crate::error::Error> {
+    if let Some(path) = history_path() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    }
+    Ok(())
+}
+
+fn history_path() -> Option<PathBuf> {
+    let mut path = crate::config::get_state_dir()?;
+    path.push("history.jsonl");
+    Some(path)
+}
+
+#[test]
+fn test_resume_positions() {
+    let entry = |url: &str, position: Option<f64>, completed: bool| HistoryEntry {
+        time: 0,
+        title: "Title".to_string(),
+        url: url.to_string(),
+        domain: None,
+        uploader: None,
+        duration: Some(7200.0),
+        watched: 600.0,
+        completed,
+        tags: Vec::new(),
+        position,
+    };
+    let positions = positions(&[
+        entry("https://example.com/1", Some(1800.0), false),
+        entry("https://example.com/1", Some(2400.0), false),
+        entry("https://example.com/2", Some(3000.0), false),
+        entry("https://example.com/2", Some(7100.0), true),
+        entry("https://example.com/3", Some(10.0), false),
+        entry("https://example.com/4", None, false),
+    ]);
+
+    assert_eq!(positions.len(), 1);
+    assert_eq!(positions.get("https://example.com/1"), Some(&2400.0));
+}
@@ -0,0 +1,104 @@
+use crate::config::Config;
+use std::process::Command;
+
+/// Returns `curl` command using the configured proxy
+pub fn curl(config: &Config) -> Command {
+    let mut command = Command::new("curl");
+    command.arg("--silent").arg("--location");
+    if let Some(proxy) = &config.proxy {
+        command.arg("--proxy").arg(proxy);
+    }
+    command
+}
+
+/// Measure the download speed of the URL in kbit/s, for at most `seconds`
+pub fn download_speed(config: &Config, url: &str, seconds: u32) -> Option<u32> {
+    let null = if cfg!(windows) { "NUL" } else { "/dev/null" };
+    // curl exits with an error on timeout, but still writes out the speed
+    let output = curl(config)
+        .arg("--max-time")
+        .arg(seconds.to_string())
+        .arg("--output")
+        .arg(null)
+        .arg("--write-out")
+        .arg("%{speed_download}")
+        .arg(url)
+        .output()
+        .ok()?;
+
+    let bytes: f64 = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .ok()?;
+    if bytes > 0.0 {
+        Some((bytes * 8.0 / 1000.0) as u32)
+    } else {
+        None
+    }
+}
+
+/// Send a `POST` request with a JSON body and `Authorization` header
+///
+/// Returns `true` on 2xx responses.
+pub fn post_json(
+    config: &Config,
+    url: &str,
+    authorization: &str,
+    body: &serde_json::Value,
+) -> bool {
+    let status = curl(config)
+        .arg("--fail")
+        .arg("--max-time")
+        .arg("10")
+        .arg("--output")
+        .arg(if cfg!(windows) { "NUL" } else { "/dev/null" })
+        .arg("--header")
+        .arg("Content-Type: application/json")
+        .arg("--header")
+        .arg(format!("Authorization: {authorization}"))
+        .arg("--data-binary")
+        .arg(body.to_string())
+        .arg(url)
+        .status();
+    matches!(status, Ok(v) if v.success())
+}
+
+/// Send a `POST` request with an URL-encoded form
+///
+/// Returns `true` on 2xx responses.
+pub fn post_form(config: &Config, url: &str, form: &[(&str, &str)]) -> bool {
+    let mut command = curl(config);
+    command
+        .arg("--fail")
+        .arg("--max-time")
+        .arg("10")
+        .arg("--output")
+        .arg(if cfg!(windows) { "NUL" } else { "/dev/null" });
+    for (k, v) in form {
+        command.arg("--data-urlencode").arg(format!("{k}={v}"));
+    }
+    let status = command.arg(url).status();
+    matches!(status, Ok(v) if v.success())
+}
+
+/// Send a `GET` request with URL-encoded query and returns the parsed JSON
+///
+/// Returns `None` on network errors, non-2xx responses and invalid JSON.
+pub fn get_json(config: &Config, url: &str, query: &[(&str, &str)]) -> Option<serde_json::Value> {
+    let mut command = curl(config);
+    command
+        .arg("--fail")
+        .arg("--max-time")
+        .arg("10")
+        .arg("--get");
+    for (k, v) in query {
+        command.arg("--data-urlencode").arg(format!("{k}={v}"));
+    }
+
+    let output = command.arg(url).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    serde_json::from_slice(&output.stdout).ok()
+}
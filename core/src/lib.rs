@@ -0,0 +1,90 @@
+//! Core of mpv-handler: protocol parsing, config, extraction by yt-dlp, the
+//! mpv IPC client and the queue
+//!
+//! The `mpv-handler` binary is a thin frontend of this crate. Other tools,
+//! e.g. browser extension hosts, GUIs or bots, can enqueue links with it
+//! without running the binary:
+//!
+//! ```no_run
+//! use mpv_handler_core::{Config, MpvController, Protocol};
+//!
+//! let config = Config::load()?;
+//!
+//! // Play or enqueue a link like the handler does
+//! let proto = Protocol::from_url("https://www.youtube.com/watch?v=Ggkn2f5e-IU".to_string());
+//! mpv_handler_core::plugins::play::exec(&proto, &config)?;
+//!
+//! // Or control a running mpv instance directly
+//! let mut mpv = MpvController::connect("/tmp/mpvsocket")?;
+//! mpv.send_all(&[serde_json::json!(["cycle", "pause"])])?;
+//! # Ok::<(), mpv_handler_core::Error>(())
+//! ```
+
+mod auto_profile;
+/// Handling of links in a detached background worker
+pub mod background;
+/// Cache of yt-dlp results with expiry, in the cache directory
+pub mod cache;
+/// Cancellation by `mpv-handler cancel` and signals
+pub mod cancel;
+/// Config of `config.toml`
+pub mod config;
+mod content_filter;
+mod dearrow;
+mod dialog;
+/// Discord Rich Presence of the playing item
+pub mod discord;
+pub mod error;
+mod formats;
+/// Watch history
+pub mod history;
+mod http;
+/// Logger of the handler, messages of the crate use the `log` facade
+pub mod logger;
+/// Logs of the mpv sessions
+pub mod logs;
+/// Client of the mpv JSON IPC
+pub mod mpv_ipc;
+mod network;
+/// Desktop notifications
+pub mod notify;
+/// Observer of the playback of a running mpv instance
+pub mod observer;
+/// Devices paired for remote control
+pub mod pairing;
+/// Watch party mirroring playback between daemons, experimental
+pub mod party;
+/// Plugins handling the links, by the `PLUGINS` part of the protocol
+pub mod plugins;
+pub mod protocol;
+/// Queue of the items enqueued to mpv
+pub mod queue;
+/// Refresh of expiring direct URLs in the queue
+pub mod refresh;
+/// Items whose extraction failed
+pub mod retry;
+/// Execution of yt-dlp and mpv, replaceable for tests
+pub mod runner;
+/// Upcoming live streams enqueued once they start
+pub mod schedule;
+/// Scrobbling of played tracks
+pub mod scrobble;
+mod sidecar;
+/// Queues of enqueued items other than the playlist of mpv
+pub mod sink;
+/// Checks of the mpv IPC socket
+pub mod socket;
+mod streamlink;
+mod subtitles;
+/// Temporary files of the handler and its mpv instances
+pub mod temp;
+/// Trust-on-first-use prompt of the link domains
+pub mod trust;
+mod url;
+mod warnings;
+mod window;
+
+pub use crate::config::Config;
+pub use crate::error::Error;
+pub use crate::mpv_ipc::Connection as MpvController;
+pub use crate::protocol::Protocol;
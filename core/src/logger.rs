@@ -0,0 +1,102 @@
+use crate::config::{Config, LogLevel};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Log file of `Config.log_file`, in the config directory
+const LOG_FILE: &str = "mpv-handler.log";
+/// Size in bytes after which the log file is moved to `LOG_FILE.old`
+const LOG_FILE_MAX_SIZE: u64 = 1024 * 1024;
+
+/// Logger of the handler, writing to stderr and the optional log file
+///
+/// Links opened by browsers have no visible stderr, so the log file keeps
+/// their messages.
+struct Logger {
+    file: Mutex<Option<File>>,
+}
+
+static LOGGER: Logger = Logger {
+    file: Mutex::new(None),
+};
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            Level::Debug | Level::Trace => eprintln!("[{}] {}", record.level(), record.args()),
+            _ => eprintln!("{}", record.args()),
+        }
+
+        if let Ok(mut file) = self.file.lock()
+            && let Some(file) = file.as_mut()
+        {
+            let time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+            let _ = writeln!(file, "{} {:<5} {}", time, record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock()
+            && let Some(file) = file.as_mut()
+        {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Install the logger of the handler, logging info messages to stderr
+///
+/// Does nothing if another logger is installed, e.g. by a library user.
+pub fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
+
+/// Apply `Config.log_level` and `Config.log_file`
+///
+/// `verbose` (`--verbose` and `mpv-debug` links) logs debug messages.
+pub fn configure(config: &Config, verbose: bool) {
+    let level = match config.log_level.as_ref() {
+        _ if verbose => LevelFilter::Debug,
+        Some(LogLevel::Error) => LevelFilter::Error,
+        Some(LogLevel::Warn) => LevelFilter::Warn,
+        Some(LogLevel::Info) | None => LevelFilter::Info,
+        Some(LogLevel::Debug) => LevelFilter::Debug,
+    };
+    log::set_max_level(level);
+
+    if config.log_file != Some(true) {
+        return;
+    }
+    let Some(path) = log_file_path() else {
+        return;
+    };
+    if std::fs::metadata(&path).is_ok_and(|v| v.len() > LOG_FILE_MAX_SIZE) {
+        let _ = std::fs::rename(&path, path.with_extension("log.old"));
+    }
+    match OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(file) => {
+            if let Ok(mut v) = LOGGER.file.lock() {
+                *v = Some(file);
+            }
+        }
+        Err(e) => log::warn!("Failed to open log file {}: {}", path.display(), e),
+    }
+}
+
+fn log_file_path() -> Option<PathBuf> {
+    let mut path = crate::config::get_config_dir()?;
+    std::fs::create_dir_all(&path).ok()?;
+    path.push(LOG_FILE);
+    Some(path)
+}
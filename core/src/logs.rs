@@ -0,0 +1,168 @@
+use crate::config::{Config, LogRetention};
+use chrono::Local;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Default number of session logs kept, older ones are removed
+const DEFAULT_KEPT_LOGS: usize = 10;
+const LOG_PREFIX: &str = "mpv-";
+const LOG_EXTENSION: &str = ".log";
+
+/// Log of an mpv session, e.g. `mpv-20240301-203000.000.log`
+///
+/// Once it reaches `LogRetention.max_size`, it continues in a new file.
+pub struct SessionLog {
+    dir: PathBuf,
+    retention: LogRetention,
+    file: File,
+    written: u64,
+}
+
+impl SessionLog {
+    /// Create the log of a new mpv session and rotate the older ones
+    ///
+    /// Failures are only reported, logs are best-effort.
+    pub fn create(config: &Config) -> Option<SessionLog> {
+        let dir = log_dir(config)?;
+        let retention = config.logs.clone().unwrap_or_default();
+        let file = create_file(&dir, &retention)?;
+        Some(SessionLog {
+            dir,
+            retention,
+            file,
+            written: 0,
+        })
+    }
+
+    /// Returns the file of the log, which isn't split anymore
+    pub fn into_file(self) -> File {
+        self.file
+    }
+
+    /// Append a line, continuing in a new file if the log is full
+    pub fn write_line(&mut self, line: &[u8]) {
+        let max_size = self.retention.max_size.map(|v| v * 1024);
+        if max_size.is_some_and(|v| self.written >= v)
+            && let Some(file) = create_file(&self.dir, &self.retention)
+        {
+            self.file = file;
+            self.written = 0;
+        }
+
+        if self
+            .file
+            .write_all(line)
+            .and_then(|_| self.file.write_all(b"\n"))
+            .is_ok()
+        {
+            self.written += line.len() as u64 + 1;
+        }
+    }
+}
+
+/// Returns the session logs, oldest first
+pub fn list(config: &Config) -> Vec<PathBuf> {
+    log_dir(config).map_or_else(Vec::new, |dir| list_dir(&dir))
+}
+
+fn list_dir(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut logs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|v| v.path())
+        .filter(|v| v.file_name().and_then(|v| v.to_str()).is_some_and(is_log))
+        .collect();
+    // Names start with the time, so they sort by age
+    logs.sort();
+    logs
+}
+
+fn create_file(dir: &Path, retention: &LogRetention) -> Option<File> {
+    let name = format!(
+        "{LOG_PREFIX}{}{LOG_EXTENSION}",
+        Local::now().format("%Y%m%d-%H%M%S%.3f")
+    );
+    let path = dir.join(name);
+
+    let result = std::fs::create_dir_all(dir).and_then(|_| File::create(&path));
+    match result {
+        Ok(file) => {
+            rotate(dir, retention);
+            Some(file)
+        }
+        Err(e) => {
+            log::warn!("Failed to create log {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Remove the logs older than `max_age` days, then the oldest logs beyond `keep`
+fn rotate(dir: &Path, retention: &LogRetention) {
+    let mut logs = list_dir(dir);
+
+    if let Some(days) = retention.max_age {
+        let max_age = Duration::from_secs(days * 24 * 60 * 60);
+        logs.retain(|path| {
+            let expired = std::fs::metadata(path)
+                .and_then(|v| v.modified())
+                .ok()
+                .and_then(|v| SystemTime::now().duration_since(v).ok())
+                .is_some_and(|v| v > max_age);
+            if expired {
+                let _ = std::fs::remove_file(path);
+            }
+            !expired
+        });
+    }
+
+    let keep = retention.keep.unwrap_or(DEFAULT_KEPT_LOGS);
+    for path in logs.iter().take(logs.len().saturating_sub(keep)) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+fn is_log(name: &str) -> bool {
+    name.starts_with(LOG_PREFIX) && name.ends_with(LOG_EXTENSION)
+}
+
+/// Returns `Config.log_dir`, or the `logs` directory in the state directory
+fn log_dir(config: &Config) -> Option<PathBuf> {
+    if let Some(dir) = &config.log_dir {
+        return Some(PathBuf::from(dir));
+    }
+    let mut path = crate::config::get_state_dir()?;
+    path.push("logs");
+    Some(path)
+}
+
+#[test]
+fn test_log_rotation() {
+    let mut dir = std::env::temp_dir();
+    dir.push(format!("mpv-handler-test-logs-{}", std::process::id()));
+
+    std::fs::create_dir_all(&dir).unwrap();
+    for i in 0..DEFAULT_KEPT_LOGS + 2 {
+        std::fs::write(dir.join(format!("mpv-20240301-2000{i:02}.000.log")), "").unwrap();
+    }
+    std::fs::write(dir.join("notes.txt"), "").unwrap();
+
+    rotate(&dir, &LogRetention::default());
+    let logs = list_dir(&dir);
+    assert_eq!(logs.len(), DEFAULT_KEPT_LOGS);
+    assert!(logs[0].ends_with("mpv-20240301-200002.000.log"));
+
+    let retention = LogRetention {
+        keep: Some(3),
+        ..Default::default()
+    };
+    rotate(&dir, &retention);
+    assert_eq!(list_dir(&dir).len(), 3);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
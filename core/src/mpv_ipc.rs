@@ -0,0 +1,452 @@
+use crate::error::Error;
+use serde_json::json;
+use std::io::prelude::*;
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// Delays between reconnect attempts when the IPC connection drops
+const RECONNECT_DELAYS_MS: [u64; 5] = [250, 500, 1000, 2000, 4000];
+/// Interval of connect attempts while waiting for a new socket
+const SOCKET_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Prefix of sockets connected over TCP, e.g. `tcp://htpc:7531`
+pub const TCP_PREFIX: &str = "tcp://";
+
+#[cfg(unix)]
+type LocalStream = std::os::unix::net::UnixStream;
+#[cfg(windows)]
+type LocalStream = pipe::NamedPipe;
+
+/// Stream of the mpv IPC socket
+///
+/// A Unix domain socket or a Windows named pipe of a local mpv, or a TCP
+/// connection to a JSON IPC bridge of a remote mpv, e.g. by `socat`.
+pub enum Stream {
+    Local(LocalStream),
+    Tcp(TcpStream),
+}
+
+impl Stream {
+    pub fn try_clone(&self) -> std::io::Result<Stream> {
+        match self {
+            Stream::Local(v) => v.try_clone().map(Stream::Local),
+            Stream::Tcp(v) => v.try_clone().map(Stream::Tcp),
+        }
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        match self {
+            Stream::Local(v) => v.set_read_timeout(timeout),
+            Stream::Tcp(v) => v.set_read_timeout(timeout),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Local(v) => v.read(buf),
+            Stream::Tcp(v) => v.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Local(v) => v.write(buf),
+            Stream::Tcp(v) => v.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Stream::Local(v) => v.flush(),
+            Stream::Tcp(v) => v.flush(),
+        }
+    }
+}
+
+/// Returns `true` if the socket is a remote mpv connected over TCP
+///
+/// Remote instances can't be launched by the handler, only enqueued to.
+pub fn is_tcp(path: &str) -> bool {
+    path.starts_with(TCP_PREFIX)
+}
+
+/// Open the mpv IPC socket, e.g. `/tmp/mpvsocket`, `\\.\pipe\mpvsocket`
+/// or `tcp://htpc:7531`
+pub fn open(path: &str) -> std::io::Result<Stream> {
+    if let Some(address) = path.strip_prefix(TCP_PREFIX) {
+        return TcpStream::connect(address).map(Stream::Tcp);
+    }
+    #[cfg(unix)]
+    return LocalStream::connect(path).map(Stream::Local);
+    #[cfg(windows)]
+    return pipe::NamedPipe::open(path).map(Stream::Local);
+}
+
+/// Get property of mpv by IPC and returns its value
+pub fn get_property(stream: &Stream, name: &str) -> Option<serde_json::Value> {
+    command(stream, json!(["get_property", name]))
+}
+
+/// IPC connection to mpv, which reconnects when it drops
+///
+/// The connection may drop during long enqueues, e.g. when mpv is restarted
+/// by the user or after suspend and resume.
+pub struct Connection {
+    path: String,
+    pub stream: Stream,
+}
+
+impl Connection {
+    /// Use the connected stream after checking the peer is mpv
+    pub fn new(path: &str, stream: Stream) -> Result<Connection, Error> {
+        handshake(&stream, path)?;
+        Ok(Connection {
+            path: path.to_string(),
+            stream,
+        })
+    }
+
+    pub fn connect(path: &str) -> Result<Connection, Error> {
+        let stream = open(path).map_err(|_| Error::SocketConnectionFailed)?;
+        Connection::new(path, stream)
+    }
+
+    /// Connect to the socket of a just launched mpv, once it is created
+    ///
+    /// Returns `SocketConnectionFailed` error if it isn't created in `timeout`.
+    pub fn wait(path: &str, timeout: Duration) -> Result<Connection, Error> {
+        let start = std::time::Instant::now();
+        loop {
+            if let Ok(stream) = open(path) {
+                log::info!(
+                    "Connected to new mpv socket after {}ms.",
+                    start.elapsed().as_millis()
+                );
+                return Connection::new(path, stream);
+            }
+            if start.elapsed() >= timeout {
+                return Err(Error::SocketConnectionFailed);
+            }
+            std::thread::sleep(SOCKET_POLL_INTERVAL);
+        }
+    }
+
+    /// Append the URL to the playlist and set its playlist title
+    ///
+    /// The title is set by the index of the appended entry, found by the
+    /// `playlist_entry_id` of the reply, since `playlist/-1` may be another
+    /// entry when something else appends at the same time.
+    ///
+    /// Returns the playlist index of the entry, `-1` if unknown, or `None`
+    /// if mpv refused to load the URL.
+    pub fn append(
+        &mut self,
+        url: &str,
+        options: serde_json::Map<String, serde_json::Value>,
+        title: &str,
+    ) -> Result<Option<i64>, Error> {
+        self.load(url, "append", options, title)
+    }
+
+    /// Load the URL by the `loadfile` flag, e.g. `insert-next`, and set the
+    /// title of its entry
+    ///
+    /// Returns the index of the entry like `append`.
+    pub fn load(
+        &mut self,
+        url: &str,
+        flag: &str,
+        options: serde_json::Map<String, serde_json::Value>,
+        title: &str,
+    ) -> Result<Option<i64>, Error> {
+        let replies = self.send_all(&[json!(["loadfile", url, flag, loadfile_arg(options)])])?;
+        let Some(reply) = &replies[0] else {
+            return Ok(None);
+        };
+
+        // mpv before 0.38 doesn't reply with the entry ID, so the title is
+        // set on the last entry
+        let index = reply["playlist_entry_id"]
+            .as_u64()
+            .and_then(|id| {
+                get_property(&self.stream, "playlist").and_then(|v| playlist_index(&v, id))
+            })
+            .map_or(-1, |v| v as i64);
+
+        self.send_all(&[json!([
+            "set_property",
+            format!("playlist/{index}/title"),
+            title
+        ])])?;
+        Ok(Some(index))
+    }
+
+    /// Send the commands in one write and returns the `data` of their replies
+    ///
+    /// Replies are matched by `request_id`, failed commands return `None`.
+    pub fn send_all(
+        &mut self,
+        commands: &[serde_json::Value],
+    ) -> Result<Vec<Option<serde_json::Value>>, Error> {
+        let ids: Vec<u64> = commands.iter().map(|_| next_request_id()).collect();
+        let buffer: String = commands
+            .iter()
+            .zip(&ids)
+            .map(|(args, id)| json!({ "command": args, "request_id": id }).to_string() + "\n")
+            .collect();
+
+        self.write(&buffer)?;
+        Ok(read_replies(&self.stream, &ids))
+    }
+
+    /// Write to the socket, reconnecting with backoff on write errors
+    ///
+    /// The data is written again after reconnecting, so a batch resumes from
+    /// the first unsent item.
+    fn write(&mut self, data: &str) -> Result<(), Error> {
+        let Err(e) = self.stream.write_all(data.as_bytes()) else {
+            return Ok(());
+        };
+
+        log::warn!("IPC connection lost ({}), reconnecting", e);
+        for delay in RECONNECT_DELAYS_MS {
+            std::thread::sleep(Duration::from_millis(delay));
+            if let Ok(conn) = Connection::connect(&self.path) {
+                log::debug!("Reconnected to mpv socket: {}", self.path);
+                *self = conn;
+                self.stream.write_all(data.as_bytes())?;
+                return Ok(());
+            }
+        }
+        Err(Error::SocketConnectionFailed)
+    }
+}
+
+/// Returns the index of the entry with `id` in the `playlist` property
+fn playlist_index(playlist: &serde_json::Value, id: u64) -> Option<usize> {
+    playlist.as_array()?.iter().position(|v| v["id"] == id)
+}
+
+/// Check the socket peer is mpv by its `get_version` reply
+///
+/// Avoids sending commands into an unrelated socket at the configured path.
+fn handshake(stream: &Stream, socket_path: &str) -> Result<(), Error> {
+    match command(stream, json!(["get_version"])) {
+        Some(v) if v.is_u64() => Ok(()),
+        _ => Err(Error::NotMpvSocket(socket_path.to_string())),
+    }
+}
+
+/// Send a command over the IPC socket and returns the `data` of its reply
+///
+/// Events and replies of previous commands are skipped. Returns `None` on
+/// errors or when no reply arrives within a second.
+pub fn command(stream: &Stream, args: serde_json::Value) -> Option<serde_json::Value> {
+    let request_id = next_request_id();
+    let command = json!({ "command": args, "request_id": request_id });
+
+    let mut writer = stream.try_clone().ok()?;
+    writer
+        .write_all((command.to_string() + "\n").as_bytes())
+        .ok()?;
+
+    read_replies(stream, &[request_id]).pop()?
+}
+
+/// Read replies of the given requests, skipping events and other replies
+///
+/// Stops after a second without any reply.
+fn read_replies(stream: &Stream, ids: &[u64]) -> Vec<Option<serde_json::Value>> {
+    let mut replies = vec![None; ids.len()];
+    let Ok(reader) = stream.try_clone() else {
+        return replies;
+    };
+    if reader
+        .set_read_timeout(Some(std::time::Duration::from_secs(1)))
+        .is_err()
+    {
+        return replies;
+    }
+
+    let mut remaining = ids.len();
+    for line in std::io::BufReader::new(reader).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let Ok(reply) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+        if let Some(i) = ids.iter().position(|v| reply["request_id"] == *v) {
+            if reply["error"] == "success" {
+                replies[i] = Some(reply["data"].clone());
+            }
+            remaining -= 1;
+            if remaining == 0 {
+                break;
+            }
+        }
+    }
+    replies
+}
+
+fn next_request_id() -> u64 {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static REQUEST_ID: AtomicU64 = AtomicU64::new(1000);
+
+    REQUEST_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Returns the per-file options argument of a `loadfile` command
+///
+/// Options with a list of values (e.g. multiple audio tracks) can't be
+/// repeated in a JSON map, so such options are passed as key-value list
+/// string with `%LENGTH%` quoted values instead.
+fn loadfile_arg(options: serde_json::Map<String, serde_json::Value>) -> serde_json::Value {
+    if !options.values().any(|v| v.is_array()) {
+        return serde_json::Value::Object(options);
+    }
+
+    let quote = |key: &str, value: &serde_json::Value| {
+        let value = match value {
+            serde_json::Value::String(v) => v.clone(),
+            v => v.to_string(),
+        };
+        format!("{key}=%{}%{value}", value.len())
+    };
+    let mut pairs: Vec<String> = Vec::new();
+    for (key, value) in &options {
+        match value {
+            serde_json::Value::Array(values) => {
+                pairs.extend(values.iter().map(|v| quote(key, v)));
+            }
+            v => pairs.push(quote(key, v)),
+        }
+    }
+    json!(pairs.join(","))
+}
+
+#[cfg(windows)]
+mod pipe {
+    use std::fs::{File, OpenOptions};
+    use std::io::{Read, Result, Write};
+    use std::time::Duration;
+
+    /// Named pipe of mpv, with the subset of the `UnixStream` API used by the handler
+    pub struct NamedPipe(File);
+
+    impl NamedPipe {
+        pub fn open(path: &str) -> Result<NamedPipe> {
+            let file = OpenOptions::new().read(true).write(true).open(path)?;
+            Ok(NamedPipe(file))
+        }
+
+        pub fn try_clone(&self) -> Result<NamedPipe> {
+            Ok(NamedPipe(self.0.try_clone()?))
+        }
+
+        /// Pipes opened for synchronous I/O have no read timeouts, so reads
+        /// block until mpv writes. mpv replies to every command, so only the
+        /// idle events of the observer are affected.
+        pub fn set_read_timeout(&self, _timeout: Option<Duration>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Read for NamedPipe {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Write for NamedPipe {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            self.0.flush()
+        }
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ipc() {
+    // Replies to each command with `data`, like mpv
+    let serve = |peer: Stream, data: serde_json::Value| {
+        std::thread::spawn(move || {
+            let mut writer = peer.try_clone().unwrap();
+            for line in std::io::BufReader::new(peer).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+                let reply = json!({ "data": data, "error": "success", "request_id": request["request_id"] });
+                writer
+                    .write_all((reply.to_string() + "\n").as_bytes())
+                    .unwrap();
+            }
+        });
+    };
+    let peer = |data: serde_json::Value| {
+        let (stream, peer) = LocalStream::pair().unwrap();
+        serve(Stream::Local(peer), data);
+        Stream::Local(stream)
+    };
+
+    assert!(handshake(&peer(json!(131077)), "/tmp/mpv").is_ok());
+    assert!(handshake(&peer(json!("hello")), "/tmp/mpv").is_err());
+
+    let mut conn = Connection::new("/tmp/mpv", peer(json!(131077))).unwrap();
+    let replies = conn
+        .send_all(&[json!(["get_version"]), json!(["get_version"])])
+        .unwrap();
+    assert_eq!(replies, vec![Some(json!(131077)), Some(json!(131077))]);
+
+    // Remote instances are connected by their JSON IPC bridge
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let socket = format!("{TCP_PREFIX}{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        let (peer, _) = listener.accept().unwrap();
+        serve(Stream::Tcp(peer), json!(131077));
+    });
+    assert!(is_tcp(&socket));
+    let conn = Connection::connect(&socket).unwrap();
+    assert_eq!(
+        get_property(&conn.stream, "mpv-version"),
+        Some(json!(131077))
+    );
+}
+
+#[test]
+fn test_playlist_index() {
+    let playlist = json!([
+        { "filename": "a", "id": 3 },
+        { "filename": "b", "id": 5, "current": true },
+        { "filename": "c", "id": 8 },
+    ]);
+    assert_eq!(playlist_index(&playlist, 8), Some(2));
+    assert_eq!(playlist_index(&playlist, 4), None);
+    assert_eq!(playlist_index(&json!(null), 3), None);
+}
+
+#[test]
+fn test_loadfile_arg() {
+    let mut opts = serde_json::Map::new();
+    opts.insert("title".to_string(), json!("T"));
+    opts.insert("audio-file".to_string(), json!("https://a.com/1?x=1,2"));
+    assert_eq!(loadfile_arg(opts.clone()), json!(opts));
+
+    opts.insert(
+        "audio-file".to_string(),
+        json!(["https://a.com/1?x=1,2", "https://a.com/2"]),
+    );
+    assert_eq!(
+        loadfile_arg(opts),
+        json!("audio-file=%21%https://a.com/1?x=1,2,audio-file=%15%https://a.com/2,title=%1%T")
+    );
+}
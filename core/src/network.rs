@@ -0,0 +1,56 @@
+use crate::config::Config;
+
+/// Returns `true` if NetworkManager reports the connection as metered
+///
+/// Disabled by `Config.metered = false`. Always `false` when NetworkManager
+/// can't be queried over D-Bus.
+pub fn is_metered(config: &Config) -> bool {
+    if config.metered == Some(false) {
+        return false;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let output = std::process::Command::new("busctl")
+            .arg("--system")
+            .arg("get-property")
+            .arg("org.freedesktop.NetworkManager")
+            .arg("/org/freedesktop/NetworkManager")
+            .arg("org.freedesktop.NetworkManager")
+            .arg("Metered")
+            .output();
+
+        match output {
+            Ok(output) if output.status.success() => {
+                parse_metered(&String::from_utf8_lossy(&output.stdout))
+            }
+            _ => false,
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        false
+    }
+}
+
+/// Parse the `NMMetered` property printed by `busctl`, e.g. `u 1`
+///
+/// Both `NM_METERED_YES` (1) and `NM_METERED_GUESS_YES` (3) are metered.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_metered(output: &str) -> bool {
+    matches!(
+        output.trim().strip_prefix("u ").map(str::trim),
+        Some("1") | Some("3")
+    )
+}
+
+#[test]
+fn test_parse_metered() {
+    assert!(parse_metered("u 1\n"));
+    assert!(parse_metered("u 3"));
+    assert!(!parse_metered("u 0"));
+    assert!(!parse_metered("u 2"));
+    assert!(!parse_metered("u 4"));
+    assert!(!parse_metered(""));
+}
@@ -0,0 +1,49 @@
+use crate::config::Config;
+
+/// Show a desktop notification if enabled by `Config.notify`
+///
+/// Failures are ignored, notifications are best-effort.
+pub fn send(config: &Config, summary: &str, body: &str) {
+    if config.notify == Some(true) {
+        show(summary, body);
+    }
+}
+
+/// Show the progress of a task in percent, replacing its previous
+/// notification where the notification server supports it
+pub fn progress(summary: &str, body: &str, percent: u32) {
+    #[cfg(unix)]
+    {
+        let _ = std::process::Command::new("notify-send")
+            .arg("--app-name=mpv-handler")
+            .arg("--icon=mpv")
+            .arg(format!("--hint=int:value:{percent}"))
+            .arg("--hint=string:x-canonical-private-synchronous:mpv-handler-progress")
+            .arg(summary)
+            .arg(body)
+            .status();
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = (summary, body, percent);
+    }
+}
+
+/// Show a desktop notification regardless of `Config.notify`
+pub fn show(summary: &str, body: &str) {
+    #[cfg(unix)]
+    {
+        let _ = std::process::Command::new("notify-send")
+            .arg("--app-name=mpv-handler")
+            .arg("--icon=mpv")
+            .arg(summary)
+            .arg(body)
+            .status();
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = (summary, body);
+    }
+}
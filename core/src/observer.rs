@@ -0,0 +1,262 @@
+use crate::config::Config;
+use crate::mpv_ipc::Connection;
+use serde_json::{Value, json};
+use std::io::BufRead;
+use std::time::{Duration, Instant};
+
+/// Delay before reconnecting when mpv isn't running
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Changes are reported once no event arrived for this long, since mpv
+/// sends the properties of a new item one by one
+const SETTLE_DELAY: Duration = Duration::from_millis(300);
+
+/// Properties observed by `observe_property`, identified by their index
+const PROPERTIES: [&str; 8] = [
+    "playlist-pos",
+    "playlist-count",
+    "media-title",
+    "path",
+    "metadata",
+    "duration",
+    "pause",
+    "paused-for-cache",
+];
+
+/// Playback state of mpv
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Playback {
+    /// Position in the playlist, starting at 0
+    pub pos: Option<u64>,
+    pub count: Option<u64>,
+    pub title: Option<String>,
+    pub path: Option<String>,
+    /// Uploader or artist from the metadata
+    pub uploader: Option<String>,
+    /// Duration in seconds, if known
+    pub duration: Option<f64>,
+    pub paused: bool,
+    /// Paused to wait for the cache to fill, e.g. on a slow connection
+    pub buffering: bool,
+    /// Playback position in seconds, polled while no events arrive
+    pub position: Option<f64>,
+}
+
+impl Playback {
+    /// Returns `true` if both are the same playlist item
+    fn same_item(&self, other: &Playback) -> bool {
+        self.pos == other.pos && self.path == other.path && self.title == other.title
+    }
+}
+
+/// Receives playback changes observed by `mpv-handler daemon`
+pub trait Listener: Send {
+    /// Called when the playing item changed
+    fn now_playing(&mut self, _playback: &Playback) {}
+
+    /// Called when an item stopped playing, after `played` seconds of
+    /// unpaused playback
+    fn finished(&mut self, _playback: &Playback, _played: f64) {}
+
+    /// Called when playback paused to wait for the cache to fill
+    fn buffering(&mut self, _playback: &Playback) {}
+}
+
+/// Notifies the playing item, enabled by `Config.now_playing`
+pub struct NowPlaying;
+
+impl Listener for NowPlaying {
+    fn now_playing(&mut self, playback: &Playback) {
+        let Some(title) = &playback.title else {
+            return;
+        };
+        let summary = match (playback.pos, playback.count) {
+            (Some(pos), Some(count)) => format!("Now playing {}/{}", pos + 1, count),
+            _ => "Now playing".to_string(),
+        };
+        crate::notify::show(&summary, title);
+    }
+}
+
+/// Observe mpv over the IPC socket and report changes to the listeners
+///
+/// Reconnects whenever mpv is restarted, until the process is interrupted.
+pub fn run(config: &Config, listeners: &mut [Box<dyn Listener>]) {
+    let Some(socket_path) = &config.socket(None) else {
+        return;
+    };
+
+    while !crate::cancel::interrupted() {
+        if crate::socket::check(socket_path).is_ok()
+            && let Ok(conn) = Connection::connect(socket_path)
+        {
+            log::debug!("Observing mpv socket: {}", socket_path);
+            observe(conn, socket_path, listeners);
+        }
+        std::thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+/// Handle the events of a connection until it is closed
+fn observe(mut conn: Connection, socket_path: &str, listeners: &mut [Box<dyn Listener>]) {
+    let commands: Vec<Value> = PROPERTIES
+        .iter()
+        .enumerate()
+        .map(|(id, name)| json!(["observe_property", id, name]))
+        .collect();
+    if conn.send_all(&commands).is_err()
+        || conn.stream.set_read_timeout(Some(SETTLE_DELAY)).is_err()
+    {
+        return;
+    }
+    let Ok(reader) = conn.stream.try_clone() else {
+        return;
+    };
+    // The position changes every frame, so it is polled on another stream
+    // instead of observed
+    let query = crate::mpv_ipc::open(socket_path).ok();
+
+    let mut playback = Playback::default();
+    let mut reported = Playback::default();
+    let mut played = 0.0;
+    let mut tick = Instant::now();
+    let mut reader = std::io::BufReader::new(reader);
+    let mut line = String::new();
+
+    while !crate::cancel::interrupted() {
+        // A line read partially before a timeout is kept and completed
+        let result = reader.read_line(&mut line);
+
+        if reported.title.is_some() && !reported.paused {
+            played += tick.elapsed().as_secs_f64();
+        }
+        tick = Instant::now();
+
+        match result {
+            // Closed by mpv
+            Ok(0) => break,
+            Ok(_) => {
+                if let Ok(event) = serde_json::from_str::<Value>(&line) {
+                    let buffering = playback.buffering;
+                    update(&mut playback, &event);
+                    if playback.buffering && !buffering {
+                        for listener in listeners.iter_mut() {
+                            listener.buffering(&playback);
+                        }
+                    }
+                }
+                line.clear();
+            }
+            Err(e) if is_timeout(&e) => {
+                if let Some(query) = &query
+                    && playback.same_item(&reported)
+                    && playback.title.is_some()
+                {
+                    playback.position = crate::mpv_ipc::get_property(query, "time-pos")
+                        .and_then(|v| v.as_f64())
+                        .or(playback.position);
+                }
+                if !playback.same_item(&reported) {
+                    if reported.title.is_some() {
+                        for listener in listeners.iter_mut() {
+                            listener.finished(&reported, played);
+                        }
+                    }
+                    if playback.title.is_some() {
+                        for listener in listeners.iter_mut() {
+                            listener.now_playing(&playback);
+                        }
+                    }
+                    played = 0.0;
+                }
+                reported = playback.clone();
+            }
+            Err(_) => break,
+        }
+    }
+
+    if reported.title.is_some() {
+        for listener in listeners.iter_mut() {
+            listener.finished(&reported, played);
+        }
+    }
+}
+
+/// Update the playback state by a `property-change` event
+fn update(playback: &mut Playback, event: &Value) {
+    if event["event"] != "property-change" {
+        return;
+    }
+
+    let data = &event["data"];
+    match event["name"].as_str() {
+        Some("playlist-pos") => playback.pos = data.as_u64(),
+        Some("playlist-count") => playback.count = data.as_u64(),
+        Some("media-title") => playback.title = data.as_str().map(String::from),
+        Some("path") => {
+            playback.path = data.as_str().map(String::from);
+            playback.position = None;
+        }
+        Some("metadata") => playback.uploader = uploader(data),
+        Some("duration") => playback.duration = data.as_f64(),
+        Some("pause") => playback.paused = data.as_bool().unwrap_or_default(),
+        Some("paused-for-cache") => playback.buffering = data.as_bool().unwrap_or_default(),
+        _ => {}
+    }
+}
+
+/// Returns the uploader or artist of the `metadata` property, whose keys
+/// keep the case of the source tags
+fn uploader(metadata: &Value) -> Option<String> {
+    let metadata = metadata.as_object()?;
+    ["uploader", "artist"].iter().find_map(|key| {
+        metadata
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .and_then(|(_, v)| v.as_str())
+            .map(String::from)
+    })
+}
+
+fn is_timeout(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+    )
+}
+
+#[test]
+fn test_playback_update() {
+    let mut playback = Playback::default();
+    let events = [
+        json!({ "event": "property-change", "id": 0, "name": "playlist-pos", "data": 3 }),
+        json!({ "event": "property-change", "id": 1, "name": "playlist-count", "data": 12 }),
+        json!({ "event": "property-change", "id": 2, "name": "media-title", "data": "Title" }),
+        json!({ "event": "property-change", "id": 4, "name": "metadata", "data": { "ARTIST": "Artist" } }),
+        json!({ "event": "playback-restart" }),
+    ];
+    for event in &events {
+        update(&mut playback, event);
+    }
+
+    assert_eq!(
+        playback,
+        Playback {
+            pos: Some(3),
+            count: Some(12),
+            title: Some("Title".to_string()),
+            path: None,
+            uploader: Some("Artist".to_string()),
+            duration: None,
+            paused: false,
+            buffering: false,
+            position: None,
+        }
+    );
+
+    // Idle mpv has no position
+    update(
+        &mut playback,
+        &json!({ "event": "property-change", "id": 0, "name": "playlist-pos", "data": -1 }),
+    );
+    assert_eq!(playback.pos, None);
+}
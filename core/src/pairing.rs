@@ -0,0 +1,137 @@
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+/// Devices paired for remote control, managed by `mpv-handler pair`
+///
+/// Only SHA-256 digests of the tokens are stored, the token is shown once
+/// when the device is paired. The file isn't encrypted, it's only readable
+/// by the user.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Pairings {
+    pub devices: Vec<Device>,
+}
+
+/// Paired device, `paired` and `last_seen` are Unix timestamps
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Device {
+    pub name: String,
+    pub token_hash: String,
+    pub paired: i64,
+    pub last_seen: Option<i64>,
+}
+
+impl Pairings {
+    /// Load pairings, returns no devices if they don't exist or are broken
+    pub fn load() -> Pairings {
+        pairings_path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let Some(path) = pairings_path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+            options.mode(0o600);
+            // Files of older versions may be readable by others
+            if path.exists() {
+                std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+            }
+        }
+        let mut file = options.open(&path)?;
+        std::io::Write::write_all(&mut file, serde_json::to_string(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Pair a device, replacing a device of the same name
+    ///
+    /// Returns the new token of the device.
+    pub fn pair(&mut self, name: &str) -> Result<String, Error> {
+        let token = new_token()?;
+        self.devices.retain(|v| v.name != name);
+        self.devices.push(Device {
+            name: name.to_string(),
+            token_hash: hash(&token),
+            paired: chrono::Local::now().timestamp(),
+            last_seen: None,
+        });
+        Ok(token)
+    }
+
+    /// Returns the device of the token, updating when it was last seen
+    pub fn authorize(&mut self, token: &str) -> Option<&Device> {
+        let token_hash = hash(token);
+        let device = self
+            .devices
+            .iter_mut()
+            .find(|v| v.token_hash == token_hash)?;
+        device.last_seen = Some(chrono::Local::now().timestamp());
+        Some(device)
+    }
+
+    /// Revoke the token of the device, returns `false` if it isn't paired
+    pub fn revoke(&mut self, name: &str) -> bool {
+        let count = self.devices.len();
+        self.devices.retain(|v| v.name != name);
+        self.devices.len() != count
+    }
+}
+
+/// Returns a random token of 32 hex digits, read from the RNG of the OS
+fn new_token() -> Result<String, Error> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| std::io::Error::other(e.to_string()))?;
+    Ok(hex(&bytes))
+}
+
+/// Returns the SHA-256 digest of the token, tokens are random so no salt
+/// is needed
+fn hash(token: &str) -> String {
+    hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|v| format!("{v:02x}")).collect()
+}
+
+fn pairings_path() -> Option<PathBuf> {
+    let mut path = crate::config::get_data_dir()?;
+    path.push("pairings.json");
+    Some(path)
+}
+
+#[test]
+fn test_pairing() {
+    let mut pairings = Pairings::default();
+    let token = pairings.pair("phone").unwrap();
+    assert_eq!(token.len(), 32);
+    assert_eq!(pairings.devices[0].token_hash.len(), 64);
+    assert_ne!(pairings.devices[0].token_hash, token);
+
+    assert!(pairings.authorize("wrong").is_none());
+    assert_eq!(pairings.authorize(&token).unwrap().name, "phone");
+    assert!(pairings.devices[0].last_seen.is_some());
+
+    // Pairing again replaces the old token
+    let new = pairings.pair("phone").unwrap();
+    assert_eq!(pairings.devices.len(), 1);
+    assert!(pairings.authorize(&token).is_none());
+    assert!(pairings.authorize(&new).is_some());
+
+    assert!(pairings.revoke("phone"));
+    assert!(!pairings.revoke("phone"));
+    assert!(pairings.authorize(&new).is_none());
+}
@@ -0,0 +1,354 @@
+use crate::config::Config;
+use crate::mpv_ipc::Connection;
+use crate::pairing::Pairings;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Delay before reconnecting to mpv or the host
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Time a follower has to send its `Hello` after connecting
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// First line sent by a follower, authorizing it by its paired token
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct Hello {
+    token: String,
+}
+
+/// Playback event mirrored from the host to its followers, sent as JSON lines
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum PartyEvent {
+    /// Item appended to the playlist, by its original URL rather than a
+    /// prefetched direct URL
+    Enqueue {
+        url: String,
+        title: Option<String>,
+    },
+    /// Playback position in seconds after a seek
+    Seek {
+        time: f64,
+    },
+    Pause {
+        paused: bool,
+    },
+}
+
+/// Start the watch party of `Config.party` in the background (experimental)
+///
+/// The host mirrors the enqueues, seeks and pauses of its mpv to the
+/// followers connected to `listen`. A follower connected to the host at
+/// `follow` applies them to its own mpv.
+///
+/// The host only accepts followers sending the token of a device paired by
+/// `mpv-handler pair add`.
+pub fn start(config: &Arc<Config>) {
+    let Some(party) = &config.party else {
+        return;
+    };
+    if let Some(address) = party.listen.clone() {
+        let config = Arc::clone(config);
+        std::thread::spawn(move || host(&config, &address));
+    }
+    if let Some(address) = party.follow.clone() {
+        let config = Arc::clone(config);
+        std::thread::spawn(move || follow(&config, &address));
+    }
+}
+
+/// Accept followers and mirror the events of mpv to them
+fn host(config: &Config, address: &str) {
+    let listener = match TcpListener::bind(address) {
+        Ok(v) => v,
+        Err(e) => {
+            log::warn!("Failed to host watch party on {}: {}", address, e);
+            return;
+        }
+    };
+    log::info!("Hosting watch party on {}", address);
+
+    if Pairings::load().devices.is_empty() {
+        log::warn!("No devices paired for the watch party, pair them by `mpv-handler pair add`");
+    }
+
+    let followers: Arc<Mutex<Vec<TcpStream>>> = Arc::default();
+    let joined = Arc::clone(&followers);
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // Followers have a while to send their hello, which doesn't
+            // hold up the other connections
+            let joined = Arc::clone(&joined);
+            std::thread::spawn(move || join(stream, &joined));
+        }
+    });
+
+    let Some(socket_path) = &config.socket(None) else {
+        return;
+    };
+    while !crate::cancel::interrupted() {
+        if crate::socket::check(socket_path).is_ok()
+            && let Ok(conn) = Connection::connect(socket_path)
+        {
+            mirror(conn, socket_path, &followers);
+        }
+        std::thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+/// Add the follower to `followers` if it is paired
+fn join(stream: TcpStream, followers: &Mutex<Vec<TcpStream>>) {
+    let peer = stream
+        .peer_addr()
+        .map_or_else(|_| "unknown".to_string(), |v| v.to_string());
+    match admit(&stream) {
+        Some(name) => log::info!("Watch party follower joined: {} ({})", peer, name),
+        None => {
+            log::warn!("Refused watch party follower without pairing: {}", peer);
+            return;
+        }
+    }
+    if let Ok(mut followers) = followers.lock() {
+        followers.push(stream);
+    }
+}
+
+/// Returns the name of the paired device of the follower, or `None` if it
+/// isn't paired
+fn admit(stream: &TcpStream) -> Option<String> {
+    let mut pairings = Pairings::load();
+    if pairings.devices.is_empty() {
+        return None;
+    }
+
+    stream.set_read_timeout(Some(HELLO_TIMEOUT)).ok()?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).ok()?;
+    stream.set_read_timeout(None).ok()?;
+
+    let hello: Hello = serde_json::from_str(&line).ok()?;
+    let name = pairings.authorize(&hello.token)?.name.clone();
+    if let Err(e) = pairings.save() {
+        log::warn!("Failed to save pairings: {}", e);
+    }
+    Some(name)
+}
+
+/// Broadcast the events of a connection until it is closed
+fn mirror(mut conn: Connection, socket_path: &str, followers: &Mutex<Vec<TcpStream>>) {
+    let commands = [
+        json!(["observe_property", 1, "pause"]),
+        json!(["observe_property", 2, "playlist-count"]),
+    ];
+    // Properties are queried on another stream, which doesn't carry events
+    let Ok(query) = crate::mpv_ipc::open(socket_path) else {
+        return;
+    };
+    if conn.send_all(&commands).is_err() || conn.stream.set_read_timeout(None).is_err() {
+        return;
+    }
+    let Ok(reader) = conn.stream.try_clone() else {
+        return;
+    };
+
+    // Items of the playlist when the host connected aren't mirrored
+    let mut known: Option<Vec<String>> = None;
+    let mut seeking = false;
+    for line in BufReader::new(reader).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let Ok(event) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+
+        let events = match (event["event"].as_str(), event["name"].as_str()) {
+            (Some("seek"), _) => {
+                seeking = true;
+                Vec::new()
+            }
+            (Some("playback-restart"), _) if seeking => {
+                seeking = false;
+                crate::mpv_ipc::get_property(&query, "time-pos")
+                    .and_then(|v| v.as_f64())
+                    .map(|time| PartyEvent::Seek { time })
+                    .into_iter()
+                    .collect()
+            }
+            (Some("property-change"), Some("pause")) => event["data"]
+                .as_bool()
+                .map(|paused| PartyEvent::Pause { paused })
+                .into_iter()
+                .collect(),
+            (Some("property-change"), Some("playlist-count")) => {
+                let playlist = crate::mpv_ipc::get_property(&query, "playlist").unwrap_or_default();
+                let events = match &known {
+                    Some(known) => original_urls(new_items(known, &playlist)),
+                    None => Vec::new(),
+                };
+                known = Some(filenames(&playlist));
+                events
+            }
+            _ => Vec::new(),
+        };
+        for event in &events {
+            broadcast(followers, event);
+        }
+    }
+}
+
+/// Send the event to the followers, dropping disconnected ones
+fn broadcast(followers: &Mutex<Vec<TcpStream>>, event: &PartyEvent) {
+    let Ok(line) = serde_json::to_string(event) else {
+        return;
+    };
+    if let Ok(mut followers) = followers.lock() {
+        followers.retain_mut(|v| v.write_all((line.clone() + "\n").as_bytes()).is_ok());
+    }
+}
+
+/// Returns the filenames of the `playlist` property
+fn filenames(playlist: &Value) -> Vec<String> {
+    playlist
+        .as_array()
+        .map(|v| {
+            v.iter()
+                .filter_map(|v| v["filename"].as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the enqueue events of the playlist entries not in `known`
+fn new_items(known: &[String], playlist: &Value) -> Vec<PartyEvent> {
+    let Some(entries) = playlist.as_array() else {
+        return Vec::new();
+    };
+    entries
+        .iter()
+        .filter_map(|v| {
+            let url = v["filename"].as_str()?;
+            (!known.iter().any(|v| v == url)).then(|| PartyEvent::Enqueue {
+                url: url.to_string(),
+                title: v["title"].as_str().map(String::from),
+            })
+        })
+        .collect()
+}
+
+/// Replace the prefetched direct URLs of enqueue events by the original
+/// URLs of their items, which followers can resolve themselves
+fn original_urls(events: Vec<PartyEvent>) -> Vec<PartyEvent> {
+    if events.is_empty() {
+        return events;
+    }
+    let state = crate::queue::QueueState::load();
+    events
+        .into_iter()
+        .map(|event| match event {
+            PartyEvent::Enqueue { url, title } => PartyEvent::Enqueue {
+                url: state.find(&url).map_or(url, |item| item.url.clone()),
+                title,
+            },
+            event => event,
+        })
+        .collect()
+}
+
+/// Connect to the host and apply its events, reconnecting when it drops
+fn follow(config: &Config, address: &str) {
+    while !crate::cancel::interrupted() {
+        if let Ok(mut stream) = TcpStream::connect(address) {
+            log::info!("Following watch party of {}", address);
+            // A failed write drops the connection, which ends the reads
+            if let Some(token) = config.party.as_ref().and_then(|v| v.token.clone())
+                && let Ok(hello) = serde_json::to_string(&Hello { token })
+            {
+                let _ = stream.write_all((hello + "\n").as_bytes());
+            }
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                match serde_json::from_str(&line) {
+                    Ok(event) => apply(config, event),
+                    Err(e) => log::warn!("Invalid watch party event ({}): {}", e, line),
+                }
+            }
+            log::info!("Watch party host disconnected: {}", address);
+        }
+        std::thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+/// Apply an event of the host to mpv
+///
+/// Enqueued URLs must use the protocols allowed for clicked links, and are
+/// checked by `Config.trust_domains` like them.
+fn apply(config: &Config, event: PartyEvent) {
+    let Some(socket_path) = &config.socket(None) else {
+        return;
+    };
+    if let Err(e) = crate::socket::check(socket_path) {
+        log::warn!("Skipped watch party event: {}", e);
+        return;
+    }
+    let Ok(mut conn) = Connection::connect(socket_path) else {
+        log::warn!("mpv isn't running, skipped watch party event: {:?}", event);
+        return;
+    };
+
+    let result = match event {
+        PartyEvent::Enqueue { url, title } => {
+            if let Err(e) =
+                crate::protocol::check_url(&url).and_then(|_| crate::trust::check(config, &url))
+            {
+                log::warn!("Skipped watch party item: {}", e);
+                return;
+            }
+            let title = title.unwrap_or_else(|| url.clone());
+            let mut options = serde_json::Map::new();
+            options.insert("title".to_string(), json!(title));
+            conn.append(&url, options, &title).map(|_| ())
+        }
+        PartyEvent::Seek { time } => conn
+            .send_all(&[json!(["seek", time, "absolute"])])
+            .map(|_| ()),
+        PartyEvent::Pause { paused } => conn
+            .send_all(&[json!(["set_property", "pause", paused])])
+            .map(|_| ()),
+    };
+    if let Err(e) = result {
+        log::warn!("Failed to apply watch party event: {}", e);
+    }
+}
+
+#[test]
+fn test_party_events() {
+    let event: PartyEvent = serde_json::from_str(r#"{"event":"seek","time":61.5}"#).unwrap();
+    assert_eq!(event, PartyEvent::Seek { time: 61.5 });
+    assert_eq!(
+        serde_json::to_string(&PartyEvent::Pause { paused: true }).unwrap(),
+        r#"{"event":"pause","paused":true}"#
+    );
+
+    let playlist = json!([
+        { "filename": "https://example.com/1", "title": "One" },
+        { "filename": "https://example.com/2" },
+    ]);
+    let known = vec!["https://example.com/1".to_string()];
+    assert_eq!(
+        new_items(&known, &playlist),
+        vec![PartyEvent::Enqueue {
+            url: "https://example.com/2".to_string(),
+            title: None,
+        }]
+    );
+    assert_eq!(filenames(&playlist).len(), 2);
+
+    let hello: Hello = serde_json::from_str(r#"{"token":"0123abcd"}"#).unwrap();
+    assert_eq!(hello.token, "0123abcd");
+}
@@ -0,0 +1,127 @@
+use crate::config::Config;
+use crate::error::Error;
+use crate::protocol::Protocol;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Progress lines of yt-dlp, the percentage followed by the title
+const PROGRESS_TEMPLATE: &str =
+    "download:mpv-handler-progress:%(progress._percent_str)s %(info.title)s";
+const PREFIX_PROGRESS: &str = "mpv-handler-progress:";
+/// Percent between the progress notifications
+const PROGRESS_STEP: f64 = 10.0;
+
+/// Save the media of the URL with yt-dlp instead of playing it
+///
+/// Cookies, proxy and format of the link and config apply like for `play`.
+/// Files are saved to `Config.download_dir`, the progress is shown by
+/// notifications.
+pub fn exec(proto: &Protocol, config: &Config) -> Result<(), Error> {
+    let Some(dir) = download_dir(config) else {
+        return Err(Error::DownloadFailed(proto.url.clone()));
+    };
+    std::fs::create_dir_all(&dir)?;
+
+    let mut command = download_command(proto, config, &dir);
+    command.stdout(Stdio::piped());
+    let mut child = config.runner.spawn(&mut command)?;
+    log::info!("Downloading to {}: {}", dir.display(), proto.url);
+    crate::notify::progress("Downloading", &proto.url, 0);
+
+    let mut title = proto.url.clone();
+    let mut shown = 0.0;
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let Some((percent, name)) = progress(&line) else {
+                println!("{line}");
+                continue;
+            };
+            if percent - shown >= PROGRESS_STEP {
+                shown = percent;
+                crate::notify::progress("Downloading", name, percent as u32);
+            }
+            title = name.to_string();
+        }
+    }
+
+    if !child.wait()?.success() {
+        crate::notify::show("Download failed", &title);
+        return Err(Error::DownloadFailed(proto.url.clone()));
+    }
+    println!("Downloaded: {}", title);
+    crate::notify::show("Downloaded", &format!("{}\n{}", title, dir.display()));
+    Ok(())
+}
+
+/// Returns `Config.download_dir`, the download directory of the user by default
+fn download_dir(config: &Config) -> Option<PathBuf> {
+    match &config.download_dir {
+        Some(v) => Some(PathBuf::from(v)),
+        None => dirs::download_dir(),
+    }
+}
+
+/// Build the yt-dlp `Command` saving the media of the link to `dir`
+fn download_command(proto: &Protocol, config: &Config, dir: &Path) -> Command {
+    let site = config.site(&proto.url).cloned().unwrap_or_default();
+    let mut command = crate::plugins::play::ytdl_command(config);
+
+    if let Some(proxy) = site.proxy.as_ref().or(config.proxy.as_ref()) {
+        command.arg("--proxy").arg(proxy);
+    }
+    if let Some(path) = proto
+        .cookies
+        .or(site.cookies.as_deref())
+        .and_then(crate::plugins::play::cookies_path)
+    {
+        command.arg("--cookies").arg(path);
+    }
+    if let Some(format) = site.format.as_ref().or(config.ytdl_format.as_ref()) {
+        command.arg("--format").arg(format);
+    }
+    let sort: Vec<String> = crate::plugins::play::format_sort(
+        proto.quality.or(site.quality.as_deref()),
+        proto.v_codec.or(site.v_codec.as_deref()),
+        None,
+    )
+    .into_iter()
+    .chain(site.format_sort)
+    .collect();
+    if !sort.is_empty() {
+        command.arg("--format-sort").arg(sort.join(","));
+    }
+
+    command
+        .arg("--paths")
+        .arg(dir)
+        .arg("--newline")
+        .arg("--progress-template")
+        .arg(PROGRESS_TEMPLATE)
+        .arg(&proto.url);
+    command
+}
+
+/// Returns the percentage and title of a progress line
+fn progress(line: &str) -> Option<(f64, &str)> {
+    let (percent, title) = line.strip_prefix(PREFIX_PROGRESS)?.trim().split_once(' ')?;
+    let percent = percent.trim_end_matches('%').parse().ok()?;
+    Some((percent, title))
+}
+
+#[test]
+fn test_download_progress() {
+    assert_eq!(
+        progress("mpv-handler-progress: 42.5% Title of video"),
+        Some((42.5, "Title of video"))
+    );
+    assert_eq!(progress("[download] Destination: video.mp4"), None);
+
+    let mut proto = Protocol::from_url("https://example.com/video".to_string());
+    proto.quality = Some("720p");
+    let command = download_command(&proto, &Config::default(), Path::new("/tmp"));
+    let args: Vec<_> = command.get_args().collect();
+    assert!(args.windows(2).any(|v| v == ["--format-sort", "res:720"]));
+    assert!(args.windows(2).any(|v| v == ["--paths", "/tmp"]));
+    assert_eq!(args.last().unwrap(), &"https://example.com/video");
+}
@@ -0,0 +1,225 @@
+use crate::config::{ChannelOrder, Config};
+use crate::error::Error;
+use crate::http::curl;
+use crate::plugins::play::{Entry, ask_item_count};
+use crate::protocol::Protocol;
+use crate::url::split_url;
+
+const DEFAULT_FEED_ITEMS: usize = 5;
+
+const FEED_CONTENT_TYPES: [&str; 4] = [
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/xml",
+    "text/xml",
+];
+
+/// Fetch a podcast/RSS/Atom feed and enqueue its newest episodes
+pub fn exec(proto: &Protocol, config: &Config) -> Result<(), Error> {
+    let data = fetch_feed(&proto.url, config)?;
+    let mut entries = parse_feed(&data)?;
+
+    if entries.is_empty() {
+        return Err(Error::EmptyFeed(proto.url.clone()));
+    }
+
+    let count = match proto.items {
+        Some(n) => n,
+        // Play only the newest episode when the dialog is cancelled
+        None => ask_item_count(
+            config,
+            &format!(
+                "Feed detected with {} episodes.\nHow many of the newest episodes do you want to fetch? (0 for all)",
+                entries.len()
+            ),
+            DEFAULT_FEED_ITEMS,
+        )
+        .unwrap_or(1),
+    };
+
+    // Feeds are listed newest-first
+    if count > 0 {
+        entries.truncate(count);
+    }
+    if config.channel_order == Some(ChannelOrder::Oldest) {
+        entries.reverse();
+    }
+
+    log::info!(
+        "Fetching {} episodes from feed: {}",
+        entries.len(),
+        proto.url
+    );
+    crate::plugins::play::play(proto, config, entries)
+}
+
+/// Returns `true` if the URL looks like a feed and is served as one
+///
+/// Only URLs with a feed-like path are checked by content type, to avoid
+/// an extra request for every played video.
+pub fn is_feed_url(url: &str, config: &Config) -> bool {
+    let path = match split_url(url) {
+        Some((_, path)) => path.to_ascii_lowercase(),
+        None => return false,
+    };
+
+    let candidate = [".rss", ".xml", ".atom"].iter().any(|v| path.ends_with(v))
+        || path
+            .split('/')
+            .any(|v| v == "feed" || v == "rss" || v == "atom");
+
+    candidate
+        && content_type(url, config)
+            .is_some_and(|t| FEED_CONTENT_TYPES.iter().any(|v| t.starts_with(v)))
+}
+
+/// Parse RSS/Atom feed and returns its episodes
+///
+/// The media URL is taken from `<enclosure>`, `<media:content>` or an Atom
+/// `<link rel="enclosure">`. Entries without media are skipped.
+fn parse_feed(data: &str) -> Result<Vec<Entry>, Error> {
+    let doc = roxmltree::Document::parse(data)?;
+    let mut entries = Vec::new();
+
+    for item in doc
+        .descendants()
+        .filter(|n| matches!(n.tag_name().name(), "item" | "entry"))
+    {
+        let mut title = None;
+        let mut url = None;
+        let mut duration = None;
+
+        for child in item.children().filter(|n| n.is_element()) {
+            match child.tag_name().name() {
+                "title" if title.is_none() => title = child.text().map(|v| v.trim().to_string()),
+                "enclosure" | "content" if url.is_none() => {
+                    url = child.attribute("url").map(|v| v.to_string())
+                }
+                // <itunes:duration>
+                "duration" => duration = child.text().and_then(crate::queue::parse_duration),
+                "link" if url.is_none() && child.attribute("rel") == Some("enclosure") => {
+                    url = child.attribute("href").map(|v| v.to_string())
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(url) = url {
+            entries.push(Entry {
+                title: title.unwrap_or(url.clone()),
+                url,
+                duration,
+                upload_date: None,
+                range: None,
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Download feed document with `curl`
+fn fetch_feed(url: &str, config: &Config) -> Result<String, Error> {
+    let output = curl(config)
+        .arg("--max-time")
+        .arg("30")
+        .arg(url)
+        .output()
+        .map_err(|_| Error::FeedFetchFailed(url.to_string()))?;
+
+    if !output.status.success() {
+        return Err(Error::FeedFetchFailed(url.to_string()));
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Query content type of the URL with a `HEAD` request
+fn content_type(url: &str, config: &Config) -> Option<String> {
+    let output = curl(config)
+        .arg("--head")
+        .arg("--max-time")
+        .arg("10")
+        .arg("--output")
+        .arg(if cfg!(windows) { "NUL" } else { "/dev/null" })
+        .arg("--write-out")
+        .arg("%{content_type}")
+        .arg(url)
+        .output()
+        .ok()?;
+
+    let content_type = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .to_lowercase();
+    (output.status.success() && !content_type.is_empty()).then_some(content_type)
+}
+
+#[test]
+fn test_feed_parse() {
+    // RSS with enclosures
+    let entries = parse_feed(
+        r#"<?xml version="1.0"?>
+        <rss version="2.0" xmlns:media="http://search.yahoo.com/mrss/"
+          xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+          <channel>
+            <title>Podcast</title>
+            <item>
+              <title>Episode 2</title>
+              <enclosure url="https://example.com/2.mp3" type="audio/mpeg"/>
+              <itunes:duration>1:02:03</itunes:duration>
+            </item>
+            <item>
+              <title>Episode 1</title>
+              <media:content url="https://example.com/1.mp3"/>
+            </item>
+            <item>
+              <title>No media</title>
+            </item>
+          </channel>
+        </rss>"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        entries,
+        vec![
+            Entry {
+                title: "Episode 2".to_string(),
+                url: "https://example.com/2.mp3".to_string(),
+                duration: Some(3723.0),
+                upload_date: None,
+                range: None,
+            },
+            Entry {
+                title: "Episode 1".to_string(),
+                url: "https://example.com/1.mp3".to_string(),
+                duration: None,
+                upload_date: None,
+                range: None,
+            },
+        ]
+    );
+
+    // Atom with enclosure links
+    let entries = parse_feed(
+        r#"<feed xmlns="http://www.w3.org/2005/Atom">
+          <entry>
+            <title>Episode</title>
+            <link rel="alternate" href="https://example.com/episode"/>
+            <link rel="enclosure" href="https://example.com/episode.ogg"/>
+          </entry>
+        </feed>"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        entries,
+        vec![Entry {
+            title: "Episode".to_string(),
+            url: "https://example.com/episode.ogg".to_string(),
+            duration: None,
+            upload_date: None,
+            range: None,
+        }]
+    );
+}
@@ -0,0 +1,86 @@
+use crate::config::Config;
+use crate::error::Error;
+use crate::protocol::Protocol;
+use std::process::Command;
+
+/// Link of a sample video, parsed like a clicked link
+const SAMPLE_LINK: &str =
+    "mpv://play/aHR0cHM6Ly93d3cueW91dHViZS5jb20vd2F0Y2g_dj1HZ2tuMmY1ZS1JVQ/?enqueue=true";
+
+/// Verify the installation for `mpv://handler-test` and show the result
+///
+/// Parses a sample link and looks for mpv, yt-dlp and a running mpv on the
+/// socket, without playing anything.
+pub fn exec(_proto: &Protocol, config: &Config) -> Result<(), Error> {
+    let text = report(config).join("\n");
+    println!("{text}");
+    crate::dialog::message(config, &text);
+    Ok(())
+}
+
+/// Returns a line per checked part of the pipeline
+fn report(config: &Config) -> Vec<String> {
+    let handler = option_env!("MPV_HANDLER_VERSION").unwrap_or(env!("CARGO_PKG_VERSION"));
+    let mut lines = vec![format!("mpv-handler v{handler} working")];
+
+    lines.push(match Protocol::parse(SAMPLE_LINK) {
+        Ok(_) => "Sample link parsed".to_string(),
+        Err(e) => format!("Sample link failed: {e}"),
+    });
+
+    let mpv = match &config.mpv {
+        Some(v) => Some(v.clone()),
+        None => crate::config::default_mpv().ok(),
+    };
+    let mpv = mpv.and_then(|path| version(config, Command::new(path).arg("--version")));
+    lines.push(match mpv {
+        Some(v) => format!("{v} found"),
+        None => "mpv not found".to_string(),
+    });
+
+    let mut ytdl = crate::plugins::play::ytdl_command(config);
+    ytdl.arg("--version");
+    lines.push(match version(config, &mut ytdl) {
+        Some(v) => format!("yt-dlp {v} found"),
+        None => "yt-dlp not found".to_string(),
+    });
+
+    lines.push(match config.socket(None) {
+        Some(path) => match crate::socket::check(&path) {
+            Err(e) => format!("Socket insecure: {e}"),
+            Ok(_) if crate::mpv_ipc::open(&path).is_ok() => format!("Socket OK: {path}"),
+            Ok(_) => format!("Socket OK, no mpv running: {path}"),
+        },
+        None => "Socket disabled".to_string(),
+    });
+    lines
+}
+
+/// Returns the version printed by the command, e.g. `mpv v0.38.0`
+fn version(config: &Config, command: &mut Command) -> Option<String> {
+    match config.runner.output(command) {
+        Ok(Some(v)) if v.status.success() => version_of(&String::from_utf8_lossy(&v.stdout)),
+        _ => None,
+    }
+}
+
+/// Returns the version of the first line of `--version` output, without
+/// the copyright of mpv
+fn version_of(stdout: &str) -> Option<String> {
+    let line = stdout.lines().next()?.trim();
+    let version = match line.split_once(" Copyright") {
+        Some((v, _)) => v,
+        None => line,
+    };
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+#[test]
+fn test_handler_test_version() {
+    assert_eq!(
+        version_of("mpv v0.38.0 Copyright © 2000-2024 mpv/MPlayer/mplayer2 projects\n built on"),
+        Some("mpv v0.38.0".to_string())
+    );
+    assert_eq!(version_of("2024.08.06\n"), Some("2024.08.06".to_string()));
+    assert_eq!(version_of(""), None);
+}
@@ -0,0 +1,12 @@
+pub mod download;
+pub mod feed;
+pub mod handler_test;
+pub mod play;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Plugins {
+    Play,
+    Feed,
+    Download,
+    HandlerTest,
+}
@@ -0,0 +1,3039 @@
+use crate::config::{
+    ChannelOrder, Config, Dedupe, LiveResolver, PlaylistSort, Profile, Site, SortKey,
+};
+use crate::dialog::Input;
+use crate::error::Error;
+use crate::mpv_ipc::{Connection, Stream};
+use crate::protocol::{Protocol, Split};
+use crate::queue::QueueItem;
+use crate::retry::Batch;
+use crate::warnings::Warning;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Output, Stdio};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+const PREFIX_COOKIES: &str = "--ytdl-raw-options-append=cookies=";
+const PREFIX_PROFILE: &str = "--profile=";
+const PREFIX_FORMATS: &str = "--ytdl-raw-options-append=format-sort=";
+const PREFIX_V_TITLE: &str = "--title=";
+const PREFIX_SUBFILE: &str = "--sub-file=";
+const PREFIX_AUDIO_DEVICE: &str = "--audio-device=";
+const PREFIX_STARTAT: &str = "--start=";
+const PREFIX_STARTAT_CHAPTER: &str = "chapter:";
+const PREFIX_TITLE_FROM_YTDL: &str = "ytdlp:";
+/// Marks the `--print` line of the templated title in the yt-dlp output
+const PREFIX_TEMPLATE_TITLE: &str = "mpv-handler-title:";
+const PREFIX_AB_LOOP_A: &str = "--ab-loop-a=";
+const PREFIX_AB_LOOP_B: &str = "--ab-loop-b=";
+const PREFIX_YTDL_FORMAT: &str = "--ytdl-format=";
+const PREFIX_RAW_OPTIONS: &str = "--ytdl-raw-options-append=";
+const PREFIX_IMPERSONATE: &str = "--ytdl-raw-options-append=impersonate=";
+/// Extractor argument of the language of translated YouTube titles
+const PREFIX_TITLE_LANG: &str = "youtube:lang=";
+const PREFIX_YT_PATH: &str = "--script-opts=ytdl_hook-ytdl_path=";
+const PREFIX_SCRIPT_OPTS: &str = "--script-opts-append=";
+const PREFIX_SB_CATEGORIES: &str = "mpv_handler-sponsorblock=";
+const PREFIX_SB_VIDEO_ID: &str = "mpv_handler-video_id=";
+
+/// mpv's message on an option it can't set, e.g. an option of a newer version
+const PREFIX_REJECTED_OPTION: &str = "Setting commandline option ";
+const SUFFIX_REJECTED_OPTION: &str = " failed.";
+
+const OPTIONS_LOUDNORM: [&str; 2] = [
+    "--af-append=lavfi=[loudnorm=I=-16:TP=-1.5:LRA=11]",
+    "--replaygain=track",
+];
+const OPTION_LIVE_FROM_START: &str = "live-from-start=";
+const OPTION_AUDIO_MULTISTREAMS: &str = "audio-multistreams=";
+const OPTIONS_GAPLESS: [&str; 2] = ["--gapless-audio=yes", "--prefetch-playlist=yes"];
+const OPTIONS_AUDIO_ONLY: [&str; 2] = ["--force-window=no", "--no-video"];
+/// Suffix of the socket of audio-only instances, so their queue is separate
+const SUFFIX_AUDIO_SOCKET: &str = "-audio";
+
+const DEFAULT_YTDL_FORMAT: &str = "bestvideo[height<=?1920][fps<=?30][vcodec^=avc]+bestaudio/best";
+const DATASAVER_YTDL_FORMAT: &str =
+    "bestvideo[height<=?480][fps<=?30]+bestaudio/best[height<=?480]";
+const AUDIO_ONLY_YTDL_FORMAT: &str = "bestaudio/best";
+const DEFAULT_CHANNEL_ITEMS: usize = 10;
+/// Default number of items resolved by yt-dlp at once
+const DEFAULT_CONCURRENCY: usize = 4;
+/// Default seconds before a hung yt-dlp is killed, and its retries
+const DEFAULT_YTDL_TIMEOUT: u64 = 300;
+const DEFAULT_YTDL_RETRIES: u32 = 2;
+/// Default minutes resolved URLs are cached, direct URLs of YouTube expire
+/// after about 6 hours
+const DEFAULT_URL_CACHE_TTL: u64 = 60;
+/// Delay before retrying a timed out yt-dlp, doubled on each retry
+const YTDL_RETRY_DELAY: Duration = Duration::from_secs(2);
+/// Time the impersonate targets check may take, it isn't retried
+const IMPERSONATE_CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Priority of yt-dlp with `Config.idle_priority`
+#[cfg(unix)]
+const IDLE_NICENESS: libc::c_int = 19;
+#[cfg(target_os = "linux")]
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+#[cfg(target_os = "linux")]
+const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+#[cfg(target_os = "linux")]
+const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+#[cfg(windows)]
+const IDLE_PRIORITY_CLASS: u32 = 0x40;
+
+/// Milliseconds enqueued items are shown on the OSD
+const DEFAULT_OSD_DURATION: u64 = 3000;
+
+/// Time mpv has to create its socket after launching
+const NEW_SOCKET_TIMEOUT: Duration = Duration::from_secs(3);
+
+const BANDWIDTH_PROBE_SECONDS: u32 = 3;
+const BANDWIDTH_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Entry of a playlist, channel or feed
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub title: String,
+    pub url: String,
+    /// Duration in seconds, if known
+    pub duration: Option<f64>,
+    /// Upload date in `YYYYMMDD` format, if known
+    pub upload_date: Option<String>,
+    /// Start and end in seconds of the part to play, e.g. of a chapter
+    pub range: Option<(f64, f64)>,
+}
+
+/// Metadata of an item in the `--dump-json` output of yt-dlp
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct ResolvedMedia {
+    pub title: String,
+    /// Direct URL of a single format
+    pub url: Option<String>,
+    /// Formats merged by the format selector, e.g. video and audio
+    pub requested_formats: Option<Vec<ResolvedFormat>>,
+    /// Duration in seconds, if known
+    pub duration: Option<f64>,
+    pub uploader: Option<String>,
+    pub is_live: Option<bool>,
+}
+
+/// Format of `ResolvedMedia.requested_formats`
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct ResolvedFormat {
+    pub url: String,
+    /// `none` for audio-only formats
+    pub vcodec: Option<String>,
+}
+
+impl ResolvedMedia {
+    /// Returns the video URL and the separate audio URLs
+    pub fn urls(&self) -> Option<(String, Vec<String>)> {
+        let Some(formats) = &self.requested_formats else {
+            return Some((self.url.clone()?, Vec::new()));
+        };
+        let video = formats
+            .iter()
+            .position(|v| v.vcodec.as_deref() != Some("none"))
+            .unwrap_or(0);
+        let audio = formats
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != video)
+            .map(|(_, v)| v.url.clone())
+            .collect();
+        Some((formats.get(video)?.url.clone(), audio))
+    }
+}
+
+/// Direct URLs and metadata of an item resolved by yt-dlp
+#[derive(Deserialize, Serialize)]
+struct DirectUrls {
+    title: String,
+    video_url: String,
+    /// Separate audio URLs, more than one for multiple audio tracks
+    audio_urls: Vec<String>,
+    duration: Option<f64>,
+    /// Error output of yt-dlp, if it failed
+    #[serde(skip)]
+    error: Option<String>,
+    /// Unix time the URLs were resolved, kept by the cache
+    #[serde(default)]
+    resolved: i64,
+    /// Significant warnings of yt-dlp while resolving
+    #[serde(skip)]
+    warnings: Vec<Warning>,
+}
+
+/// Format selection passed to yt-dlp when resolving direct URLs
+#[derive(Clone)]
+struct FormatSelection {
+    /// `--format` of yt-dlp
+    format: String,
+    /// `--format-sort` fields of yt-dlp
+    sort: Vec<String>,
+    /// Probe formats with `--check-formats`, disabled by data-saver mode
+    check_formats: bool,
+    /// Resolve direct URLs before enqueueing, disabled for live-from-start
+    prefetch: bool,
+    /// yt-dlp output template of the titles, from `title_from`
+    title_template: Option<String>,
+    /// streamlink qualities of live streams, if the site resolves them by
+    /// streamlink
+    streamlink: Option<String>,
+}
+
+/// Execute player with given options
+pub fn exec(proto: &Protocol, config: &Config) -> Result<(), Error> {
+    log::debug!("Using yt-dlp path: {}", ytdl_path(config));
+
+    // --- Playlist Detection ---
+    let mut playlist_entries: Vec<Entry> = if is_channel_url(&proto.url) {
+        fetch_channel(proto, config)
+    } else if proto.url.contains("&list=") {
+        fetch_playlist(proto, config)
+    } else if crate::plugins::feed::is_feed_url(&proto.url, config) {
+        return crate::plugins::feed::exec(proto, config);
+    } else if proto.split == Some(Split::Chapters) {
+        fetch_chapters(config, &proto.url)
+    } else {
+        Vec::new()
+    };
+
+    // Sorted before the entries are resolved
+    if let Some(order) = proto.sort.as_ref().or(config.sort.as_ref()) {
+        sort_entries(&mut playlist_entries, order);
+    }
+
+    play(proto, config, playlist_entries)
+}
+
+/// Play or enqueue the given playlist entries
+///
+/// If `playlist_entries` is empty, `proto.url` is played as a single video.
+pub fn play(
+    proto: &Protocol,
+    config: &Config,
+    mut playlist_entries: Vec<Entry>,
+) -> Result<(), Error> {
+    let is_playlist = !playlist_entries.is_empty();
+    // Links without a target of their own may go to any running instance
+    let any_instance = proto.profile.is_none()
+        && proto.instance.is_none()
+        && proto.new_instance != Some(true)
+        && proto.audio_only != Some(true);
+
+    // Rules of the site replace the config, the link parameters take precedence
+    let site = config.site(&proto.url).cloned().unwrap_or_default();
+    let site_proto;
+    let proto = if site.profile.is_some() || site.cookies.is_some() {
+        site_proto = Protocol {
+            profile: proto.profile.or(site.profile.as_deref()),
+            cookies: proto.cookies.or(site.cookies.as_deref()),
+            ..proto.clone()
+        };
+        &site_proto
+    } else {
+        proto
+    };
+    let site_config;
+    let config = if site.proxy.is_some() || site.socket.is_some() {
+        site_config = Config {
+            proxy: site.proxy.clone().or_else(|| config.proxy.clone()),
+            socket: site.socket.clone().or_else(|| config.socket.clone()),
+            ..config.clone()
+        };
+        &site_config
+    } else {
+        config
+    };
+
+    // A named instance replaces the socket, the site rules included. Its
+    // links are enqueued, launching the instance if it isn't running
+    let instance_proto;
+    let instance_config;
+    let (proto, config) = match proto.instance.or(site.instance.as_deref()) {
+        Some(name) => {
+            let instance = config
+                .instance(name)
+                .ok_or_else(|| Error::UnknownInstance(name.to_string()))?;
+            instance_proto = Protocol {
+                profile: proto.profile.or(instance.profile.as_deref()),
+                enqueue: proto.enqueue.or(Some(true)),
+                ..proto.clone()
+            };
+            instance_config = Config {
+                socket: Some(instance.socket.clone()),
+                ..config.clone()
+            };
+            (&instance_proto, &instance_config)
+        }
+        None => (proto, config),
+    };
+
+    // A forced new instance gets its own socket, so the queue socket stays
+    // with the running instance
+    let new_instance = proto.new_instance == Some(true);
+    let forced_config;
+    let config = match config.socket(proto.profile) {
+        Some(socket) if new_instance && !crate::mpv_ipc::is_tcp(&socket) => {
+            crate::socket::remove_stale_instances(&socket);
+            forced_config = Config {
+                socket: Some(unique_socket(&socket)),
+                ..config.clone()
+            };
+            &forced_config
+        }
+        _ => config,
+    };
+    // Audio-only items are queued by their own instance
+    let audio_only = proto.audio_only == Some(true);
+    let audio_config;
+    let config = match config.socket(proto.profile) {
+        Some(socket) if audio_only && !crate::mpv_ipc::is_tcp(&socket) => {
+            audio_config = Config {
+                socket: Some(format!("{socket}{SUFFIX_AUDIO_SOCKET}")),
+                ..config.clone()
+            };
+            &audio_config
+        }
+        _ => config,
+    };
+    // Another running instance than the one of the socket may be picked
+    let picked_config;
+    let config = match any_instance.then(|| pick_instance(config, &site, proto)) {
+        Some(Some(socket)) => {
+            picked_config = Config {
+                socket: Some(socket),
+                ..config.clone()
+            };
+            &picked_config
+        }
+        _ => config,
+    };
+    let socket = config.socket(proto.profile);
+
+    // --- Queues of Other Profiles ---
+    if config.dedupe_profiles == Some(true) {
+        let others = crate::queue::other_queues(config, proto.profile);
+        let queued_in = |url: &str| {
+            others
+                .iter()
+                .find(|(_, urls)| urls.iter().any(|v| v == url))
+                .map(|(name, _)| name.clone())
+        };
+
+        if !is_playlist && let Some(name) = queued_in(&proto.url) {
+            log::info!("Already queued in '{}': {}", name, proto.url);
+            crate::notify::show(&format!("Already queued in '{name}'"), &proto.url);
+            return Ok(());
+        }
+        playlist_entries.retain(|entry| match queued_in(&entry.url) {
+            Some(name) => {
+                log::info!("Already queued in '{}', skipping: {}", name, entry.title);
+                false
+            }
+            None => true,
+        });
+        if is_playlist && playlist_entries.is_empty() {
+            crate::notify::show("Already queued in other profiles", &proto.url);
+            return Ok(());
+        }
+    }
+
+    // --- Content Filter ---
+    if config.content_filter.is_some() {
+        if is_playlist {
+            crate::content_filter::retain(config, proto, &mut playlist_entries);
+            if playlist_entries.is_empty() {
+                return Ok(());
+            }
+        } else {
+            let mut entries = vec![Entry {
+                title: proto.v_title.clone().unwrap_or(proto.url.clone()),
+                url: proto.url.clone(),
+                duration: None,
+                upload_date: None,
+                range: None,
+            }];
+            crate::content_filter::retain(config, proto, &mut entries);
+            if entries.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    // --- Queue Backends ---
+    if let Some(backend) = config.profile(proto.profile).and_then(|v| v.queue.as_ref())
+        && let Some(mut sink) = crate::sink::open(config, backend)
+    {
+        if !is_playlist {
+            playlist_entries.push(Entry {
+                title: proto.v_title.clone().unwrap_or(proto.url.clone()),
+                url: proto.url.clone(),
+                duration: None,
+                upload_date: None,
+                range: None,
+            });
+        }
+        sink.push(&playlist_entries)?;
+        let summary = format!("Enqueued {} items", playlist_entries.len());
+        log::info!(
+            "{} to the queue of profile '{}'",
+            summary,
+            proto.profile.unwrap_or_default()
+        );
+        crate::notify::show(&summary, &proto.url);
+        return Ok(());
+    }
+
+    // --- Socket Check ---
+    // Remote instances are always enqueued to, they can't be launched
+    let remote = socket.as_deref().is_some_and(crate::mpv_ipc::is_tcp);
+    let mut use_existing_socket = false;
+    if (proto.enqueue == Some(true) || remote)
+        && !new_instance
+        && let Some(socket_path) = &socket
+    {
+        crate::socket::check(socket_path)?;
+        if crate::mpv_ipc::open(socket_path).is_ok() {
+            use_existing_socket = true;
+            log::debug!("Connected to existing mpv socket: {}", socket_path);
+        } else {
+            log::info!(
+                "No existing mpv socket found or connection failed. Launching new instance."
+            );
+        }
+    }
+    if remote && !use_existing_socket {
+        return Err(Error::RemoteUnreachable(socket.unwrap_or_default()));
+    }
+
+    let datasaver = proto
+        .datasaver
+        .unwrap_or_else(|| crate::network::is_metered(config));
+
+    // Picked format applies to the linked video only, audio-only and
+    // data-saver mode force a format instead
+    let forced_format = match proto.pickformat.or(config.pickformat) {
+        _ if audio_only => Some(AUDIO_ONLY_YTDL_FORMAT.to_string()),
+        _ if datasaver => Some(DATASAVER_YTDL_FORMAT.to_string()),
+        Some(true) if !is_playlist => pick_format(config, &proto.url),
+        _ => None,
+    };
+
+    let mut format = forced_format
+        .clone()
+        .or(site.format.clone())
+        .or_else(|| config.ytdl_format.clone())
+        .unwrap_or_else(|| {
+            crate::config::get_ytdl_format_from_mpv_conf()
+                .unwrap_or_else(|| DEFAULT_YTDL_FORMAT.to_string())
+        });
+    if let Some(lang) = &config.audio_lang {
+        format = prefer_audio_lang(&format, lang);
+    }
+
+    let selection = FormatSelection {
+        format,
+        sort: format_sort(
+            proto.quality.or(site.quality.as_deref()),
+            proto.v_codec.or(site.v_codec.as_deref()),
+            max_bitrate(config),
+        )
+        .into_iter()
+        .chain(site.format_sort)
+        .collect(),
+        check_formats: !datasaver,
+        prefetch: proto.dvr != Some(true),
+        title_template: title_template(proto, config),
+        streamlink: (site.live_resolver == Some(LiveResolver::Streamlink))
+            .then(|| crate::streamlink::quality(proto.quality)),
+    };
+
+    // --- Enqueue to Existing Instance ---
+    if use_existing_socket
+        && let Some(socket_path) = &socket
+        && let Ok(stream) = crate::mpv_ipc::open(socket_path)
+    {
+        let mut conn = Connection::new(socket_path, stream)?;
+        set_audio_device(proto, config, &mut conn);
+
+        let mut entries = if is_playlist {
+            playlist_entries
+        } else {
+            // Use proto.v_title or URL as title for single video
+            let title = proto.v_title.clone().unwrap_or(proto.url.clone());
+            vec![Entry {
+                title,
+                url: proto.url.clone(),
+                duration: None,
+                upload_date: None,
+                range: None,
+            }]
+        };
+        if !dedupe(config, &conn.stream, &mut entries) {
+            return Ok(());
+        }
+        if (config.max_queue_length.is_some() || config.max_queue_hours.is_some())
+            && !limit_queue(config, crate::queue::pending(&conn.stream), &mut entries)
+        {
+            return Ok(());
+        }
+        let mut batch = Batch::new(entries);
+        let positions = match is_playlist {
+            true => crate::history::resume_positions(config),
+            false => HashMap::new(),
+        };
+
+        log::info!("Enqueuing to existing mpv instance.");
+        let mut added = Vec::new();
+        let mut warnings = Vec::new();
+        let mut next = None;
+        // Appending to an idle mpv doesn't start playback
+        let idle = crate::mpv_ipc::get_property(&conn.stream, "idle-active")
+            .and_then(|v| v.as_bool())
+            .unwrap_or_default();
+        if idle {
+            log::info!("mpv is idle, starting playback of the enqueued items.");
+        }
+        // Prefetch direct URLs for performance
+        let mut resolver = Resolver::new(config, &selection);
+        while let Some((index, entry, direct)) = resolver.next(&mut batch) {
+            log::info!(
+                "Enqueuing item [{}]: {} - {}",
+                index,
+                entry.title,
+                entry.url
+            );
+            crate::warnings::merge(&mut warnings, &direct.warnings);
+
+            if let Some(err) = &direct.error {
+                // Not yet live, offer to enqueue it later instead
+                if crate::schedule::is_upcoming_error(err)
+                    && let Some(item) = crate::schedule::probe(config, &entry.url)
+                {
+                    crate::schedule::offer(config, item);
+                    continue;
+                }
+                // Single videos fall back to the original URL
+                if is_playlist {
+                    batch.fail(entry, err);
+                    continue;
+                }
+            }
+
+            // For playlist items, use the pre-fetched title unless it's templated
+            let display_title = match entry.range {
+                // Chapters keep their own titles
+                Some(_) => entry.title.clone(),
+                None => match crate::dearrow::title(config, &entry.url) {
+                    Some(title) => title,
+                    None if is_playlist && selection.title_template.is_none() => {
+                        entry.title.clone()
+                    }
+                    None => direct.title,
+                },
+            };
+
+            let mut options_obj =
+                loadfile_options(config, &display_title, &entry.url, &direct.audio_urls);
+            resume(&mut options_obj, &positions, &entry.url);
+            clip(&mut options_obj, entry.range);
+
+            // A-B loop applies to the linked video only
+            if let Some((a, b)) = proto.abloop
+                && !is_playlist
+            {
+                options_obj.insert("ab-loop-a".to_string(), json!(a.to_string()));
+                options_obj.insert("ab-loop-b".to_string(), json!(b.to_string()));
+            }
+            // Subtitle tracks are fetched for the linked video only as well
+            if let Some(lang) = proto.subs
+                && !is_playlist
+            {
+                let paths = crate::subtitles::fetch(config, &entry.url, lang);
+                if !paths.is_empty() {
+                    options_obj.insert("sub-file".to_string(), json!(paths));
+                }
+            }
+            if proto.dvr == Some(true) {
+                options_obj.insert(
+                    "ytdl-raw-options-append".to_string(),
+                    json!(OPTION_LIVE_FROM_START),
+                );
+            }
+
+            let Some(index) = load_entry(
+                &mut conn,
+                idle,
+                (proto.play_next == Some(true)).then_some(&mut next),
+                &direct.video_url,
+                options_obj,
+                &display_title,
+            )?
+            else {
+                log::warn!("mpv refused to load '{}'", display_title);
+                continue;
+            };
+
+            println!("Enqueued: {}", display_title);
+            show_added(config, &mut conn, &display_title);
+            let item = QueueItem {
+                title: display_title,
+                url: entry.url.clone(),
+                resolved: prefetched(&direct.video_url, direct.resolved, &entry.url),
+                format: Some(selection.format.clone()),
+                filename: direct.video_url,
+                duration: direct.duration.or(entry.duration),
+                profile: proto.profile.map(String::from),
+                tags: proto.tags.clone().unwrap_or_default(),
+            };
+            write_sidecar(config, &conn.stream, &item, &direct.audio_urls, index);
+            added.push(item);
+        }
+
+        crate::retry::record(config, &batch.failed);
+        if !added.is_empty() {
+            finish_enqueue(config, &conn.stream, &added, &warnings);
+        }
+        return Ok(());
+    }
+    // Fallthrough to launch new instance if socket connection fails unexpectedly
+
+    // --- Launch New Instance ---
+    if is_playlist && !limit_queue(config, (0, 0.0), &mut playlist_entries) {
+        return Ok(());
+    }
+
+    let mut options: Vec<String> = build_mpv_options(proto, config, &selection);
+
+    if forced_format.is_some()
+        || site.format.is_some()
+        || config.ytdl_format.is_some()
+        || config.audio_lang.is_some()
+    {
+        options.push(format!("{PREFIX_YTDL_FORMAT}{}", selection.format));
+    }
+    if let Some(lang) = proto.subs
+        && !is_playlist
+    {
+        for path in crate::subtitles::fetch(config, &proto.url, lang) {
+            options.push(subfile(&path.to_string_lossy()));
+        }
+    }
+
+    if (proto.enqueue == Some(true) || new_instance)
+        && let Some(socket_path) = &socket
+    {
+        crate::socket::prepare(socket_path)?;
+        crate::socket::secure_when_created(socket_path);
+        options.push(format!("--input-ipc-server={}", socket_path));
+    }
+
+    // Retry once without an option the installed mpv doesn't support
+    match launch(proto, config, &playlist_entries, &selection, &options) {
+        Err(Error::OptionRejected(option)) => {
+            let len = options.len();
+            options.retain(|v| *v != option && v != option.trim_end_matches('='));
+            if options.len() == len {
+                return Err(Error::OptionRejected(option));
+            }
+            log::warn!("mpv rejected option {}, retrying without it", option);
+            crate::notify::show(
+                "mpv rejected an option",
+                &format!("Retrying without {option}"),
+            );
+            launch(proto, config, &playlist_entries, &selection, &options)
+        }
+        result => result,
+    }
+}
+
+/// Launch a new mpv instance playing the URL or playlist entries
+fn launch(
+    proto: &Protocol,
+    config: &Config,
+    playlist_entries: &[Entry],
+    selection: &FormatSelection,
+    options: &[String],
+) -> Result<(), Error> {
+    let detach = config.detach == Some(true);
+
+    if !playlist_entries.is_empty() {
+        // --- New Instance for Playlist ---
+        let mut options = options.to_vec();
+        options.push("--idle=yes".to_string());
+
+        let mut command = player_command(config, &options)?;
+        let (mut child, stderr) = spawn_player(config, &mut command, !detach)?;
+        let placing = crate::window::place(config, child.id());
+
+        let start = playlist_start(&proto.url, playlist_entries).unwrap_or(0);
+        let result = handle_playlist_in_new_instance(
+            &mut child,
+            config,
+            playlist_entries,
+            start,
+            selection,
+            proto.profile,
+            proto.tags.as_deref().unwrap_or_default(),
+        );
+        if let Err(e) = result {
+            // mpv may have exited before creating the socket
+            let exited = child.try_wait().is_ok_and(|v| v.is_some());
+            return Err(
+                match stderr.filter(|_| exited).and_then(|v| v.join().ok()?) {
+                    Some(option) => Error::OptionRejected(option),
+                    None => e,
+                },
+            );
+        }
+        if detach {
+            if let Some(v) = placing {
+                let _ = v.join();
+            }
+            reap(child);
+            return Ok(());
+        }
+        let status = child.wait().map_err(Error::PlayerRunFailed)?;
+        exit_status(status, stderr)
+    } else {
+        // --- New Instance for Single Video ---
+        let mut command = player_command(config, options)?;
+        let stream = selection.streamlink.as_ref().and_then(|quality| {
+            let title = crate::streamlink::probe_live(config, &proto.url)?;
+            let stream = crate::streamlink::stream_url(config, &proto.url, quality)?;
+            Some((title, stream))
+        });
+        match stream {
+            // Live streams of the site are played from streamlink's URL
+            Some((title, stream)) => {
+                command
+                    .arg(format!("--force-media-title={title}"))
+                    .arg("--")
+                    .arg(stream);
+            }
+            // Pass original URL directly to mpv
+            None => {
+                command.arg("--").arg(&proto.url);
+            }
+        }
+
+        let (mut child, stderr) = spawn_player(config, &mut command, !detach)?;
+        let placing = crate::window::place(config, child.id());
+        if detach {
+            if let Some(v) = placing {
+                let _ = v.join();
+            }
+            reap(child);
+            return Ok(());
+        }
+
+        let status = child.wait().map_err(Error::PlayerRunFailed)?;
+        match exit_status(status, stderr) {
+            Err(Error::PlayerExited(code)) => {
+                // Not yet live, offer to enqueue it later instead
+                if let Some(item) = crate::schedule::probe(config, &proto.url) {
+                    crate::schedule::offer(config, item);
+                    return Ok(());
+                }
+                Err(Error::PlayerExited(code))
+            }
+            result => result,
+        }
+    }
+}
+
+/// Thread printing the stderr of mpv, returns the option rejected by mpv
+/// once it exits
+type StderrWatcher = JoinHandle<Option<String>>;
+
+/// Spawn mpv, watching its stderr for a rejected option if `watch` is set
+///
+/// The stderr of mpv is still printed, and written to a session log. It's
+/// not watched for detached mpv, which outlives the handler, so it's only
+/// written into the log.
+fn spawn_player(
+    config: &Config,
+    command: &mut Command,
+    watch: bool,
+) -> Result<(std::process::Child, Option<StderrWatcher>), Error> {
+    let mut log = crate::logs::SessionLog::create(config);
+    if watch {
+        command.stderr(Stdio::piped());
+    } else if let Some(log) = log.take() {
+        command.stderr(log.into_file());
+    }
+    let mut child = config
+        .runner
+        .spawn(command)
+        .map_err(Error::PlayerRunFailed)?;
+
+    let stderr = child.stderr.take().map(|pipe| {
+        std::thread::spawn(move || {
+            let mut rejected = None;
+            for line in BufReader::new(pipe).split(b'\n') {
+                let Ok(line) = line else {
+                    break;
+                };
+                if let Some(log) = &mut log {
+                    log.write_line(&line);
+                }
+                let line = String::from_utf8_lossy(&line);
+                eprintln!("{}", line);
+                if rejected.is_none() {
+                    rejected = rejected_option(&line).map(String::from);
+                }
+            }
+            rejected
+        })
+    });
+    Ok((child, stderr))
+}
+
+/// Returns the error of a failed mpv exit, `OptionRejected` if mpv
+/// rejected an option at startup
+fn exit_status(
+    status: std::process::ExitStatus,
+    stderr: Option<StderrWatcher>,
+) -> Result<(), Error> {
+    if status.success() {
+        return Ok(());
+    }
+    match stderr.and_then(|v| v.join().ok()?) {
+        Some(option) => Err(Error::OptionRejected(option)),
+        None => Err(Error::PlayerExited(status.code().unwrap_or(1) as u8)),
+    }
+}
+
+/// Returns the option of mpv's message on an option it can't set, e.g.
+/// `Setting commandline option --foo=bar failed.`
+///
+/// Options without value are reported with a trailing `=`.
+fn rejected_option(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix(PREFIX_REJECTED_OPTION)?
+        .strip_suffix(SUFFIX_REJECTED_OPTION)
+}
+
+/// Ask which instance to enqueue to, if several known sockets respond
+///
+/// Returns the picked socket, or `None` to use the socket of the config,
+/// e.g. if the link isn't enqueued or the site has its own instance. The
+/// sockets of `crate::socket::known` are listed with their playing item.
+fn pick_instance(config: &Config, site: &Site, proto: &Protocol) -> Option<String> {
+    if proto.enqueue != Some(true)
+        || site.profile.is_some()
+        || site.instance.is_some()
+        || site.socket.is_some()
+    {
+        return None;
+    }
+    let rows: Vec<Vec<String>> = crate::socket::known(config)
+        .into_iter()
+        .filter(|v| crate::socket::check(v).is_ok())
+        .filter_map(|socket| {
+            let stream = crate::mpv_ipc::open(&socket).ok()?;
+            let count = crate::mpv_ipc::get_property(&stream, "playlist-count")?;
+            let title = crate::mpv_ipc::get_property(&stream, "media-title");
+            Some(vec![
+                socket,
+                title
+                    .as_ref()
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("Idle")
+                    .to_string(),
+                format!("{} items", count.as_u64().unwrap_or_default()),
+            ])
+        })
+        .collect();
+    if rows.len() < 2 {
+        return None;
+    }
+
+    let text = "Several mpv instances are running.\nPick the one to enqueue to.";
+    crate::dialog::list(
+        config,
+        "mpv instances",
+        text,
+        &["Socket", "Playing", "Queue"],
+        &rows,
+    )
+}
+
+/// Returns a socket path of a new instance, next to the queue socket
+fn unique_socket(socket: &str) -> String {
+    format!("{}-{}", socket, std::process::id())
+}
+
+/// Wait for a detached child in the background, so it doesn't become a
+/// zombie when the process keeps running (e.g. `mpv-handler daemon`)
+///
+/// If the process exits first, the child is reaped by init instead.
+fn reap(mut child: std::process::Child) {
+    std::thread::spawn(move || child.wait());
+}
+
+/// Build the player `Command` with given options and environment
+fn player_command(config: &Config, options: &[String]) -> Result<Command, Error> {
+    let mpv = match &config.mpv {
+        Some(v) => v.clone(),
+        None => crate::config::default_mpv()?,
+    };
+
+    let mut command = Command::new(mpv);
+    command.args(options);
+
+    // Keep detached mpv running when the browser's process group is signaled
+    #[cfg(unix)]
+    if config.detach == Some(true) {
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    }
+
+    if let Some(proxy) = &config.proxy {
+        command
+            .env("http_proxy", proxy)
+            .env("HTTP_PROXY", proxy)
+            .env("https_proxy", proxy)
+            .env("HTTPS_PROXY", proxy);
+    }
+
+    // Libraries of the browser's environment may break mpv
+    #[cfg(unix)]
+    if config.keep_ld_env != Some(true) {
+        command
+            .env_remove("LD_LIBRARY_PATH")
+            .env_remove("LD_PRELOAD");
+    }
+    command.envs(config.env.iter().flatten());
+
+    Ok(command)
+}
+
+/// Returns the configured yt-dlp binary path
+fn ytdl_path(config: &Config) -> &str {
+    config.ytdl.as_deref().unwrap_or("yt-dlp")
+}
+
+/// Build the yt-dlp `Command` with the options shared by all calls
+pub fn ytdl_command(config: &Config) -> Command {
+    let mut command = Command::new(ytdl_path(config));
+    command.envs(config.env.iter().flatten());
+    if let Some(target) = impersonate(config) {
+        command.arg("--impersonate").arg(target);
+    }
+    // YouTube falls back to the original title without a translation
+    if let Some(lang) = &config.title_lang {
+        command
+            .arg("--extractor-args")
+            .arg(format!("{PREFIX_TITLE_LANG}{lang}"));
+    }
+    command
+}
+
+/// Run a yt-dlp command through `Config.runner`, retrying it with backoff
+/// when it hangs longer than `Config.ytdl_timeout`
+///
+/// Returns `YtdlTimeout` error if every attempt timed out, and `None` if the
+/// command was cancelled.
+pub fn ytdl_output(config: &Config, command: &mut Command) -> Result<Option<Output>, Error> {
+    let seconds = config.ytdl_timeout.unwrap_or(DEFAULT_YTDL_TIMEOUT);
+    if seconds == 0 {
+        return Ok(config.runner.output(command)?);
+    }
+    let timeout = Duration::from_secs(seconds);
+    let retries = config.ytdl_retries.unwrap_or(DEFAULT_YTDL_RETRIES);
+
+    let mut delay = YTDL_RETRY_DELAY;
+    for attempt in 0..=retries {
+        match config.runner.output_timeout(command, timeout) {
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                log::warn!(
+                    "yt-dlp timed out after {}s (attempt {}/{})",
+                    seconds,
+                    attempt + 1,
+                    retries + 1
+                );
+            }
+            result => return Ok(result?),
+        }
+        if attempt < retries {
+            std::thread::sleep(delay);
+            delay *= 2;
+        }
+    }
+    Err(Error::YtdlTimeout(seconds))
+}
+
+/// Run the command at idle CPU and IO priority, enabled by
+/// `Config.idle_priority`
+///
+/// Failures to lower the priority are ignored.
+fn lower_priority(command: &mut Command) {
+    #[cfg(unix)]
+    unsafe {
+        // Only async-signal-safe calls between fork and exec
+        std::os::unix::process::CommandExt::pre_exec(command, || {
+            libc::setpriority(libc::PRIO_PROCESS, 0, IDLE_NICENESS);
+            #[cfg(target_os = "linux")]
+            libc::syscall(
+                libc::SYS_ioprio_set,
+                IOPRIO_WHO_PROCESS,
+                0,
+                IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+            );
+            Ok(())
+        });
+    }
+    #[cfg(windows)]
+    std::os::windows::process::CommandExt::creation_flags(command, IDLE_PRIORITY_CLASS);
+}
+
+/// Returns the configured impersonate target, if yt-dlp supports it
+///
+/// Impersonation requires yt-dlp with `curl_cffi`, so the target is checked
+/// once by `--list-impersonate-targets`.
+fn impersonate(config: &Config) -> Option<&str> {
+    static AVAILABLE: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+
+    let target = config.impersonate.as_deref()?;
+    let available = *AVAILABLE.get_or_init(|| {
+        let output = config.runner.output_timeout(
+            Command::new(ytdl_path(config)).arg("--list-impersonate-targets"),
+            IMPERSONATE_CHECK_TIMEOUT,
+        );
+        let available = match output {
+            Ok(Some(output)) => {
+                impersonate_available(&String::from_utf8_lossy(&output.stdout), target)
+            }
+            _ => false,
+        };
+        if !available {
+            log::warn!(
+                "Impersonate target unavailable, curl_cffi may be missing: {}",
+                target
+            );
+        }
+        available
+    });
+
+    available.then_some(target)
+}
+
+/// Check if the target is listed as available by `--list-impersonate-targets`
+fn impersonate_available(targets: &str, target: &str) -> bool {
+    let client = target.split(':').next().unwrap_or(target).to_lowercase();
+
+    targets.lines().any(|line| {
+        let line = line.to_lowercase();
+        line.split_whitespace()
+            .next()
+            .is_some_and(|v| v.starts_with(&client))
+            && !line.contains("unavailable")
+    })
+}
+
+/// Fetch entries of a playlist URL, asking the user how many to keep
+fn fetch_playlist(proto: &Protocol, config: &Config) -> Vec<Entry> {
+    let mut entries = probe_playlist(config, &proto.url, playlist_end(proto, config));
+    if entries.len() > 1 {
+        filter_entries(proto, config, &mut entries);
+    }
+
+    if entries.len() <= 1 {
+        return Vec::new();
+    }
+
+    if proto.items.is_none() && proto.pickitems.or(config.pickitems) == Some(true) {
+        return pick_items(config, entries);
+    }
+
+    let count = match proto.items {
+        Some(n) => Some(n),
+        None => ask_item_count(
+            config,
+            &format!(
+                "Playlist detected with {} entries.\nHow many items do you want to fetch? (0 for all)",
+                entries.len()
+            ),
+            0,
+        ),
+    };
+
+    match count {
+        Some(0) => {
+            log::info!("Fetching all {} playlist items.", entries.len());
+            entries
+        }
+        Some(n) => {
+            // Counted from the clicked video
+            let start = playlist_start(&proto.url, &entries).unwrap_or(0);
+            entries.drain(..start);
+            entries.truncate(n);
+            log::info!("Fetching the first {} playlist items.", entries.len());
+            entries
+        }
+        None => {
+            log::info!("Treating as a single video.");
+            Vec::new()
+        }
+    }
+}
+
+/// Ask which playlist entries to enqueue from a checklist of their titles
+fn pick_items(config: &Config, entries: Vec<Entry>) -> Vec<Entry> {
+    let rows: Vec<Vec<String>> = entries
+        .iter()
+        .map(|v| {
+            let duration = v.duration.map(crate::queue::format_duration);
+            vec![v.title.clone(), duration.unwrap_or_default()]
+        })
+        .collect();
+    let text = format!(
+        "Playlist detected with {} entries.\nCheck the items to enqueue.",
+        entries.len()
+    );
+    let checked = crate::dialog::checklist(
+        config,
+        "Pick playlist items",
+        &text,
+        &["Title", "Duration"],
+        &rows,
+    )
+    .unwrap_or_default();
+
+    if checked.is_empty() {
+        log::info!("Treating as a single video.");
+        return Vec::new();
+    }
+    log::info!("Fetching {} picked playlist items.", checked.len());
+    entries
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| checked.contains(i))
+        .map(|(_, v)| v)
+        .collect()
+}
+
+/// Returns the number of playlist entries worth probing
+///
+/// Limited by the `items` parameter if no entries are filtered out or
+/// skipped before the clicked video, and by `Config.playlist_end`.
+fn playlist_end(proto: &Protocol, config: &Config) -> Option<usize> {
+    let filtered = proto.filter.is_some()
+        || proto.min_duration.is_some()
+        || proto.max_duration.is_some()
+        || config.filter.is_some();
+    let items = match proto.items {
+        Some(n) if n > 0 && !filtered => match crate::url::query_value(&proto.url, "index") {
+            Some(v) => v.parse::<usize>().ok().map(|i| i.saturating_sub(1) + n),
+            None if crate::url::youtube_id(&proto.url).is_some() => None,
+            None => Some(n),
+        },
+        _ => None,
+    };
+    [items, config.playlist_end].into_iter().flatten().min()
+}
+
+/// Returns the index of the clicked video of a playlist URL
+///
+/// The video is found by its `v=` ID, or by the 1-based `index=` parameter.
+fn playlist_start(url: &str, entries: &[Entry]) -> Option<usize> {
+    crate::url::query_value(url, "list")?;
+
+    if let Some(id) = crate::url::youtube_id(url)
+        && let Some(i) = entries
+            .iter()
+            .position(|v| crate::url::youtube_id(&v.url) == Some(id))
+    {
+        return Some(i);
+    }
+
+    let index: usize = crate::url::query_value(url, "index")?.parse().ok()?;
+    (1..=entries.len()).contains(&index).then(|| index - 1)
+}
+
+/// Fetch the latest uploads of a channel URL in the configured order
+fn fetch_channel(proto: &Protocol, config: &Config) -> Vec<Entry> {
+    let count = match proto.items {
+        Some(n) => n,
+        None => match ask_item_count(
+            config,
+            "Channel detected.\nHow many of the latest uploads do you want to fetch? (0 for all)",
+            DEFAULT_CHANNEL_ITEMS,
+        ) {
+            Some(n) => n,
+            None => {
+                log::info!("Treating as a single video.");
+                return Vec::new();
+            }
+        },
+    };
+
+    let url = channel_uploads_url(&proto.url);
+    let end = [(count > 0).then_some(count), config.playlist_end]
+        .into_iter()
+        .flatten()
+        .min();
+    let mut entries = probe_playlist(config, &url, end);
+    filter_entries(proto, config, &mut entries);
+
+    // Channel uploads are listed newest-first
+    if config.channel_order == Some(ChannelOrder::Oldest) {
+        entries.reverse();
+    }
+
+    log::info!("Fetching {} uploads from channel: {}", entries.len(), url);
+    entries
+}
+
+/// Probe a playlist with `--flat-playlist` and returns its available entries
+///
+/// `end` limits the probe to the first N entries of the playlist.
+pub fn probe_playlist(config: &Config, url: &str, end: Option<usize>) -> Vec<Entry> {
+    let mut command = ytdl_command(config);
+    command.arg("--flat-playlist").arg("--dump-json");
+    if let Some(n) = end {
+        command.arg("--playlist-end").arg(n.to_string());
+    }
+
+    let output = match ytdl_output(config, command.arg(url)) {
+        Ok(Some(output)) if output.status.success() => output,
+        _ => return Vec::new(),
+    };
+
+    let mut entries = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(line)
+            && let (Some(title), Some(url)) =
+                (json_value["title"].as_str(), json_value["url"].as_str())
+        {
+            if title != "[Deleted video]" && title != "[Private video]" {
+                entries.push(Entry {
+                    title: title.to_string(),
+                    url: url.to_string(),
+                    duration: json_value["duration"].as_f64(),
+                    upload_date: upload_date(&json_value),
+                    range: None,
+                });
+            } else {
+                log::warn!("Skipping unavailable video: {}", title);
+            }
+        }
+    }
+    entries
+}
+
+/// Returns the upload date of a flat-playlist entry
+///
+/// Some extractors only list the upload timestamp.
+fn upload_date(entry: &serde_json::Value) -> Option<String> {
+    if let Some(v) = entry["upload_date"].as_str() {
+        return Some(v.to_string());
+    }
+    let time = chrono::DateTime::from_timestamp(entry["timestamp"].as_i64()?, 0)?;
+    Some(time.format("%Y%m%d").to_string())
+}
+
+/// Drop playlist entries by the `filter`, `min_duration` and `max_duration`
+/// parameters and config rules
+///
+/// Entries of unknown duration are kept.
+fn filter_entries(proto: &Protocol, config: &Config, entries: &mut Vec<Entry>) {
+    let mut rules = config.filter.clone().unwrap_or_default();
+    let min_duration = proto.min_duration.or(rules.min_duration);
+    let max_duration = proto.max_duration.or(rules.max_duration);
+    if let Some(v) = &proto.filter {
+        match v.strip_prefix('!') {
+            Some(v) => rules.exclude.push(v.to_string()),
+            None => rules.include.push(v.to_string()),
+        }
+    }
+    let compile = |patterns: &[String]| -> Vec<Regex> {
+        patterns
+            .iter()
+            .filter_map(|v| match Regex::new(v) {
+                Ok(re) => Some(re),
+                Err(e) => {
+                    log::warn!("Ignoring invalid filter {:?}: {}", v, e);
+                    None
+                }
+            })
+            .collect()
+    };
+    let include = compile(&rules.include);
+    let exclude = compile(&rules.exclude);
+
+    entries.retain(|entry| {
+        let keep = (include.is_empty() || include.iter().any(|re| re.is_match(&entry.title)))
+            && !exclude.iter().any(|re| re.is_match(&entry.title))
+            && entry.duration.is_none_or(|v| {
+                min_duration.is_none_or(|min| v >= min) && max_duration.is_none_or(|max| v <= max)
+            });
+        if !keep {
+            log::info!("Filtered out: {}", entry.title);
+        }
+        keep
+    });
+}
+
+/// Sort playlist entries, entries without the sort field are kept last
+fn sort_entries(entries: &mut [Entry], order: &PlaylistSort) {
+    entries.sort_by(|a, b| match order.key {
+        SortKey::Date => compare(&a.upload_date, &b.upload_date, order.reverse),
+        SortKey::Duration => compare(&a.duration, &b.duration, order.reverse),
+        SortKey::Title => compare(
+            &Some(a.title.to_lowercase()),
+            &Some(b.title.to_lowercase()),
+            order.reverse,
+        ),
+    });
+
+    fn compare<T: PartialOrd>(a: &Option<T>, b: &Option<T>, reverse: bool) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                let ordering = a.partial_cmp(b).unwrap_or(Ordering::Equal);
+                if reverse {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+/// Ask the user how many items to fetch with an entry dialog
+///
+/// Returns `default` when the dialog times out or no dialog backend is used,
+/// and `None` when it is cancelled or fails.
+pub fn ask_item_count(config: &Config, text: &str, default: usize) -> Option<usize> {
+    let input = crate::dialog::entry(
+        config,
+        text,
+        &default.to_string(),
+        "Play only the first video",
+        10,
+    );
+
+    match input {
+        Input::Text(num_str) => match num_str.parse::<usize>() {
+            Ok(num) => Some(num),
+            Err(_) => {
+                log::warn!("Invalid input.");
+                None
+            }
+        },
+        Input::Default => {
+            log::warn!("Dialog timed out. Using default value: {}", default);
+            Some(default)
+        }
+        Input::Cancelled => None,
+    }
+}
+
+/// Ask the user to pick a format of the URL with a list dialog
+///
+/// Returns the format selector, or `None` when the dialog is cancelled.
+fn pick_format(config: &Config, url: &str) -> Option<String> {
+    let formats = crate::formats::list_formats(config, url);
+    if formats.is_empty() {
+        return None;
+    }
+
+    let rows: Vec<Vec<String>> = formats
+        .iter()
+        .map(|v| vec![v.id.clone(), v.ext.clone(), v.info.clone()])
+        .collect();
+    let Some(id) = crate::dialog::list(config, "Pick a format", url, &["ID", "EXT", "INFO"], &rows)
+    else {
+        log::info!("Format dialog cancelled. Using default format.");
+        return None;
+    };
+
+    let format = formats.iter().find(|v| v.id == id)?;
+    log::debug!("Picked format: {}", format.selector());
+    Some(format.selector())
+}
+
+/// Returns `true` if the URL points to a channel or user page
+pub fn is_channel_url(url: &str) -> bool {
+    match crate::url::split_url(url) {
+        Some((host, path)) => {
+            let host = host.trim_start_matches("www.").trim_start_matches("m.");
+            (host == "youtube.com" || host == "music.youtube.com")
+                && (path.starts_with("/@")
+                    || path.starts_with("/channel/")
+                    || path.starts_with("/c/")
+                    || path.starts_with("/user/"))
+        }
+        None => false,
+    }
+}
+
+/// Returns the uploads tab URL of a channel URL
+///
+/// A bare channel URL is listed by tabs (videos, shorts, live), so it is
+/// redirected to the `videos` tab. URLs with an explicit tab are kept.
+pub fn channel_uploads_url(url: &str) -> String {
+    let base = url.split(['?', '#']).next().unwrap_or(url);
+    let base = base.trim_end_matches('/');
+
+    let depth = match crate::url::split_url(base) {
+        Some((_, path)) => {
+            let segments = path.split('/').filter(|s| !s.is_empty()).count();
+            if path.starts_with("/@") {
+                segments
+            } else {
+                segments.saturating_sub(1)
+            }
+        }
+        None => return url.to_string(),
+    };
+
+    match (depth, base.rsplit('/').next()) {
+        (1, _) => format!("{base}/videos"),
+        (2, Some("featured")) => format!("{}/videos", base.trim_end_matches("/featured")),
+        _ => url.to_string(),
+    }
+}
+
+/// Resolves the direct URLs of the upcoming entries of a batch in parallel
+///
+/// Up to `Config.concurrency` entries are resolved at once. Results are
+/// returned in batch order, so items are still enqueued in order.
+struct Resolver {
+    config: Arc<Config>,
+    selection: Arc<FormatSelection>,
+    concurrency: usize,
+    pending: VecDeque<(usize, Entry, JoinHandle<DirectUrls>)>,
+}
+
+impl Resolver {
+    fn new(config: &Config, selection: &FormatSelection) -> Resolver {
+        Resolver {
+            config: Arc::new(config.clone()),
+            selection: Arc::new(selection.clone()),
+            concurrency: config.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Returns the next entry of the batch and its position, with its direct URLs
+    fn next(&mut self, batch: &mut Batch) -> Option<(usize, Entry, DirectUrls)> {
+        while self.pending.len() < self.concurrency
+            && let Some((index, entry)) = batch.next()
+        {
+            let config = Arc::clone(&self.config);
+            let selection = Arc::clone(&self.selection);
+            let (url, title) = (entry.url.clone(), entry.title.clone());
+            let handle =
+                std::thread::spawn(move || fetch_direct_urls(&config, &selection, &url, &title));
+            self.pending.push_back((index, entry, handle));
+        }
+
+        let (index, entry, handle) = self.pending.pop_front()?;
+        let direct = handle.join().unwrap_or_else(|_| DirectUrls {
+            title: entry.title.clone(),
+            video_url: entry.url.clone(),
+            audio_urls: Vec::new(),
+            duration: None,
+            error: Some("Extraction failed".to_string()),
+            resolved: 0,
+            warnings: Vec::new(),
+        });
+        Some((index, entry, direct))
+    }
+}
+
+/// Helper to fetch direct URLs, title and duration using yt-dlp
+///
+/// Falls back to the original URL and `default_title` on failure.
+fn fetch_direct_urls(
+    config: &Config,
+    selection: &FormatSelection,
+    url: &str,
+    default_title: &str,
+) -> DirectUrls {
+    let fallback = DirectUrls {
+        title: default_title.to_string(),
+        video_url: url.to_string(),
+        audio_urls: Vec::new(),
+        duration: None,
+        error: None,
+        resolved: 0,
+        warnings: Vec::new(),
+    };
+    if !selection.prefetch {
+        return fallback;
+    }
+
+    let ttl = Duration::from_secs(60 * config.url_cache_ttl.unwrap_or(DEFAULT_URL_CACHE_TTL));
+    let key = url_cache_key(selection, url);
+    if !ttl.is_zero()
+        && let Some(direct) = crate::cache::get::<DirectUrls>("urls", &key, ttl)
+    {
+        log::debug!("Using cached direct URL for: {}", url);
+        return direct;
+    }
+
+    log::debug!("Fetching direct URL for: {}", url);
+    let mut command = ytdl_command(config);
+    if config.idle_priority == Some(true) {
+        lower_priority(&mut command);
+    }
+    command.arg("-f").arg(&selection.format);
+    if !selection.sort.is_empty() {
+        command.arg("-S").arg(selection.sort.join(","));
+    }
+    if selection.check_formats {
+        command.arg("--check-formats");
+    }
+    if multiple_audio(&selection.format) {
+        command.arg("--audio-multistreams");
+    }
+    command.arg("--dump-json");
+    if let Some(template) = &selection.title_template {
+        command
+            .arg("--print")
+            .arg(format!("{PREFIX_TEMPLATE_TITLE}{template}"));
+    }
+    command.arg(url);
+
+    match ytdl_output(config, &mut command) {
+        Ok(None) => DirectUrls {
+            error: Some("Cancelled".to_string()),
+            ..fallback
+        },
+        Ok(Some(output)) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let Some((media, template_title)) = parse_resolved(&stdout) else {
+                log::warn!("yt-dlp returned insufficient output. Using original URL as fallback.");
+                return fallback;
+            };
+            let Some((mut video_url, mut audio_urls)) = media.urls() else {
+                log::warn!("yt-dlp returned no URL. Using original URL as fallback.");
+                return fallback;
+            };
+
+            // Live streams of the site are played from streamlink's URL
+            if media.is_live == Some(true)
+                && let Some(quality) = &selection.streamlink
+                && let Some(stream) = crate::streamlink::stream_url(config, url, quality)
+            {
+                video_url = stream;
+                audio_urls.clear();
+            }
+            let warnings = crate::warnings::classify(&String::from_utf8_lossy(&output.stderr));
+            for warning in &warnings {
+                log::warn!("yt-dlp warning: {}", warning.describe());
+            }
+            let title = template_title.unwrap_or(media.title);
+            log::debug!("Extracted Title: {}", title);
+            log::debug!("Extracted Video URL: {}", video_url);
+            for audio in &audio_urls {
+                log::debug!("Extracted Audio URL: {}", audio);
+            }
+            let direct = DirectUrls {
+                title,
+                video_url,
+                audio_urls,
+                duration: media.duration,
+                error: None,
+                resolved: chrono::Local::now().timestamp(),
+                warnings,
+            };
+            // URLs of live streams change while they are live
+            if !ttl.is_zero() && media.is_live != Some(true) {
+                crate::cache::put("urls", &key, &direct, ttl);
+            }
+            direct
+        }
+        Ok(Some(output)) => {
+            log::warn!("yt-dlp returned an error. Using original URL as fallback.");
+            DirectUrls {
+                error: Some(String::from_utf8_lossy(&output.stderr).to_string()),
+                ..fallback
+            }
+        }
+        Err(e @ Error::YtdlTimeout(_)) => {
+            log::warn!("{}. Using original URL as fallback.", e);
+            DirectUrls {
+                error: Some(e.to_string()),
+                ..fallback
+            }
+        }
+        Err(e) => {
+            log::warn!(
+                "Failed to execute yt-dlp ({}). Using original URL as fallback.",
+                e
+            );
+            fallback
+        }
+    }
+}
+
+/// Returns the key of the resolved URLs in the cache, the video ID if known
+///
+/// The format selection is part of the key, since it selects other URLs.
+fn url_cache_key(selection: &FormatSelection, url: &str) -> String {
+    let id = crate::url::youtube_id(url).unwrap_or(url);
+    format!(
+        "{id} {} {} {}",
+        selection.format,
+        selection.sort.join(","),
+        selection.title_template.as_deref().unwrap_or_default()
+    )
+}
+
+/// Parse the metadata and the templated title printed by yt-dlp
+///
+/// The templated title is marked, since `--print` and `--dump-json` lines
+/// may come in any order.
+fn parse_resolved(stdout: &str) -> Option<(ResolvedMedia, Option<String>)> {
+    let mut media = None;
+    let mut template_title = None;
+    for line in stdout.lines() {
+        if let Some(title) = line.strip_prefix(PREFIX_TEMPLATE_TITLE) {
+            template_title = Some(title.trim().to_string()).filter(|v| !v.is_empty());
+        } else if line.starts_with('{') {
+            media = serde_json::from_str(line).ok();
+        }
+    }
+    Some((media?, template_title))
+}
+
+/// Switch the running mpv to the `audio_device` of the profile of the link
+fn set_audio_device(proto: &Protocol, config: &Config, conn: &mut Connection) {
+    let profile_name = proto
+        .profile
+        .or_else(|| crate::auto_profile::select(config));
+    let Some(device) = config
+        .profile(profile_name)
+        .and_then(|v| v.audio_device.as_ref())
+    else {
+        return;
+    };
+    match conn.send_all(&[json!(["set_property", "audio-device", device])]) {
+        Ok(replies) if replies[0].is_some() => log::info!("Switched audio device: {}", device),
+        _ => log::warn!("Failed to switch audio device: {}", device),
+    }
+}
+
+/// Returns the time the direct URL was resolved, `None` if mpv loads the
+/// original URL
+fn prefetched(video_url: &str, resolved: i64, url: &str) -> Option<i64> {
+    (video_url != url && resolved > 0).then_some(resolved)
+}
+
+/// Resolve the direct URLs of a queued item again, replacing its expiring
+/// entry at `index` of the playlist
+///
+/// Returns the refreshed item, or `None` if resolving failed.
+pub fn refresh_item(
+    config: &Config,
+    conn: &mut Connection,
+    index: usize,
+    item: &QueueItem,
+) -> Result<Option<QueueItem>, Error> {
+    let format = item
+        .format
+        .clone()
+        .unwrap_or_else(|| DEFAULT_YTDL_FORMAT.to_string());
+    replace_item(config, conn, index, item, format, None)
+}
+
+/// Resolve the playing item at `index` again below `height`, continuing
+/// playback at `position`
+///
+/// Returns the downgraded item, or `None` if resolving failed.
+pub fn downgrade_item(
+    config: &Config,
+    conn: &mut Connection,
+    index: usize,
+    item: &QueueItem,
+    height: u64,
+    position: f64,
+) -> Result<Option<QueueItem>, Error> {
+    let format = format!("bestvideo[height<{height}]+bestaudio/best[height<{height}]");
+    replace_item(config, conn, index, item, format, Some(position))
+}
+
+/// Resolve the item by `format`, replacing its entry at `index`
+///
+/// With `start`, the entry is playing, so the new entry plays from `start`
+/// in its place.
+fn replace_item(
+    config: &Config,
+    conn: &mut Connection,
+    index: usize,
+    item: &QueueItem,
+    format: String,
+    start: Option<f64>,
+) -> Result<Option<QueueItem>, Error> {
+    let selection = FormatSelection {
+        format,
+        sort: Vec::new(),
+        check_formats: false,
+        prefetch: true,
+        title_template: None,
+        streamlink: None,
+    };
+    // Cached URLs may be as old as the replaced ones
+    let config = Config {
+        url_cache_ttl: Some(0),
+        ..config.clone()
+    };
+    let direct = fetch_direct_urls(&config, &selection, &item.url, &item.title);
+    let Some(resolved) = prefetched(&direct.video_url, direct.resolved, &item.url)
+        .filter(|_| direct.error.is_none())
+    else {
+        return Ok(None);
+    };
+
+    let mut options = loadfile_options(&config, &item.title, &item.url, &direct.audio_urls);
+    if let Some(start) = start {
+        options.insert("start".to_string(), json!(start.to_string()));
+    }
+    let Some(appended) = conn.append(&direct.video_url, options, &item.title)? else {
+        return Ok(None);
+    };
+    let appended = match appended {
+        -1 => crate::mpv_ipc::get_property(&conn.stream, "playlist-count")
+            .and_then(|v| v.as_i64())
+            .map_or(-1, |v| v - 1),
+        v => v,
+    };
+    if appended < 0 {
+        return Ok(None);
+    }
+
+    let commands = match start {
+        // Move the new entry in front of the old one, then remove that
+        None => vec![
+            json!(["playlist-move", appended, index]),
+            json!(["playlist-remove", index + 1]),
+        ],
+        // Play the new entry after the old one, so playback doesn't skip
+        // to the next item when the old one is removed
+        Some(_) => vec![
+            json!(["playlist-move", appended, index + 1]),
+            json!(["playlist-play-index", index + 1]),
+            json!(["playlist-remove", index]),
+        ],
+    };
+    conn.send_all(&commands)?;
+
+    Ok(Some(QueueItem {
+        filename: direct.video_url,
+        resolved: Some(resolved),
+        format: Some(selection.format),
+        ..item.clone()
+    }))
+}
+
+/// Drop the entries whose video is already in the queue, by `Config.dedupe`
+///
+/// Returns `false` if no entries are left.
+fn dedupe(config: &Config, stream: &Stream, entries: &mut Vec<Entry>) -> bool {
+    let mode = config.dedupe.as_ref().unwrap_or(&Dedupe::Allow);
+    if *mode == Dedupe::Allow {
+        return true;
+    }
+    let queued = crate::queue::queued(stream);
+    let is_queued = |entry: &Entry| {
+        let id = crate::url::video_id(&entry.url);
+        queued.iter().any(|v| crate::url::video_id(v) == id)
+    };
+    let duplicates = entries.iter().filter(|v| is_queued(v)).count();
+    if duplicates == 0 {
+        return true;
+    }
+
+    // Dismissed dialogs skip the duplicates
+    if *mode == Dedupe::Ask {
+        let text = format!(
+            "{} of {} items are already in the queue.",
+            duplicates,
+            entries.len()
+        );
+        let labels = ["Skip them", "Enqueue anyway"];
+        if crate::dialog::choose(config, &text, &labels) == Some(1) {
+            return true;
+        }
+    }
+    entries.retain(|entry| {
+        let queued = is_queued(entry);
+        if queued {
+            log::info!("Already in the queue, skipping: {}", entry.title);
+        }
+        !queued
+    });
+    if entries.is_empty() {
+        crate::notify::show(
+            "Already in the queue",
+            &format!("{duplicates} items skipped"),
+        );
+    }
+    !entries.is_empty()
+}
+
+/// Add the URL to the playlist of mpv, returns the index of its entry
+///
+/// With `idle`, items are appended by `append-play`, so the first one
+/// starts playing. With `next` (`playnext`), the first item is inserted
+/// after the playing one and the following ones after the item before them,
+/// since inserting each after the playing one would reverse their order.
+fn load_entry(
+    conn: &mut Connection,
+    idle: bool,
+    next: Option<&mut Option<i64>>,
+    url: &str,
+    options: serde_json::Map<String, serde_json::Value>,
+    title: &str,
+) -> Result<Option<i64>, Error> {
+    if idle {
+        return conn.load(url, "append-play", options, title);
+    }
+    let Some(next) = next else {
+        return conn.append(url, options, title);
+    };
+    let Some(previous) = *next else {
+        let index = conn.load(url, "insert-next", options, title)?;
+        // Without the index of the entry, the next items are inserted after
+        // the playing one as well
+        *next = index.filter(|v| *v >= 0);
+        return Ok(index);
+    };
+
+    let Some(index) = conn.append(url, options, title)? else {
+        return Ok(None);
+    };
+    conn.send_all(&[json!(["playlist-move", index, previous + 1])])?;
+    *next = Some(previous + 1);
+    Ok(*next)
+}
+
+/// Show the enqueued item on the OSD of mpv, disabled by `Config.osd`
+fn show_added(config: &Config, conn: &mut Connection, title: &str) {
+    if config.osd == Some(false) {
+        return;
+    }
+    let duration = config.osd_duration.unwrap_or(DEFAULT_OSD_DURATION);
+    let text = format!("Added to queue: {title}");
+    // Best-effort, the item is enqueued anyway
+    let _ = conn.send_all(&[json!(["show-text", text, duration])]);
+}
+
+/// Ask whether to enqueue the entries beyond the queue limits, behind the
+/// `pending` items of `crate::queue::pending`
+///
+/// Returns `false` if the enqueue is refused, also when the dialog can't be
+/// shown, e.g. in headless mode.
+fn limit_queue(config: &Config, pending: (usize, f64), entries: &mut Vec<Entry>) -> bool {
+    let durations: Vec<Option<f64>> = entries.iter().map(|v| v.duration).collect();
+    let fitting = crate::queue::fitting(config, pending, &durations);
+    if fitting == entries.len() {
+        return true;
+    }
+
+    let text = format!(
+        "Enqueueing {} items exceeds the queue limits, only {} fit.",
+        entries.len(),
+        fitting
+    );
+    let first = format!("Enqueue {fitting}");
+    let all = format!("Enqueue all {}", entries.len());
+    let mut labels = vec!["Cancel", all.as_str()];
+    if fitting > 0 {
+        labels.insert(0, first.as_str());
+    }
+
+    match crate::dialog::choose(config, &text, &labels).map(|v| labels[v]) {
+        Some(v) if v == first => {
+            entries.truncate(fitting);
+            true
+        }
+        Some(v) if v == all => true,
+        _ => {
+            log::info!("{} Not enqueued.", text);
+            crate::notify::show("Queue limit reached", &text);
+            false
+        }
+    }
+}
+
+/// Record the added items, print and notify the queue summary with the
+/// warnings of yt-dlp
+fn finish_enqueue(config: &Config, stream: &Stream, added: &[QueueItem], warnings: &[Warning]) {
+    crate::queue::record(added);
+    crate::queue::save_queue(stream);
+
+    // Warnings explain degraded quality or speed of the enqueued items
+    let mut summary = crate::queue::summary(stream, added);
+    for warning in warnings {
+        summary.push_str(&format!("\n{}", warning.describe()));
+    }
+    println!("{}", summary);
+    crate::notify::send(config, "mpv-handler", &summary);
+}
+
+/// Write the metadata sidecar of an enqueued item, if enabled by the config
+fn write_sidecar(
+    config: &Config,
+    stream: &Stream,
+    item: &QueueItem,
+    audio_urls: &[String],
+    index: i64,
+) {
+    if config.sidecars == Some(true) {
+        let position = usize::try_from(index).ok();
+        let sidecar = crate::sidecar::Sidecar::new(item, audio_urls, position);
+        crate::sidecar::write(stream, &sidecar);
+    }
+}
+
+/// Helper to build the per-file options of a `loadfile` command
+///
+/// `url` is the original URL of the item, which may differ from the loaded
+/// direct URL.
+fn loadfile_options(
+    config: &Config,
+    title: &str,
+    url: &str,
+    audio_urls: &[String],
+) -> serde_json::Map<String, serde_json::Value> {
+    let mut opts = serde_json::Map::new();
+    opts.insert("title".to_string(), json!(title));
+    // Multiple audio tracks are attached by repeated options, see `loadfile_arg`
+    match audio_urls {
+        [] => {}
+        [audio] => {
+            opts.insert("audio-file".to_string(), json!(audio));
+        }
+        audio => {
+            opts.insert("audio-file".to_string(), json!(audio));
+        }
+    }
+
+    // Prefetched direct URLs lose the video ID, so pass it to the script
+    if let Some(sb) = &config.sponsorblock
+        && let Some(id) = crate::url::youtube_id(url)
+    {
+        let script_opts = format!("{},{PREFIX_SB_VIDEO_ID}{id}", sponsorblock(&sb.categories));
+        opts.insert("script-opts".to_string(), json!(script_opts));
+    }
+
+    opts
+}
+
+/// Start the item at its position in the history, see
+/// `crate::history::resume_positions`
+fn resume(
+    options: &mut serde_json::Map<String, serde_json::Value>,
+    positions: &HashMap<String, f64>,
+    url: &str,
+) {
+    if let Some(position) = positions.get(url) {
+        log::info!(
+            "Resuming at {}: {}",
+            crate::queue::format_duration(*position),
+            url
+        );
+        options.insert("start".to_string(), json!(position.to_string()));
+    }
+}
+
+/// Returns `true` if an alternative of the format selector merges more than
+/// one audio format, which requires `--audio-multistreams`
+fn multiple_audio(format: &str) -> bool {
+    format.split('/').any(|alternative| {
+        alternative
+            .split('+')
+            .filter(|v| {
+                let name = v
+                    .trim_start_matches('(')
+                    .split('[')
+                    .next()
+                    .unwrap_or_default();
+                name == "bestaudio" || name == "ba"
+            })
+            .count()
+            > 1
+    })
+}
+
+/// Helper to build the initial mpv command line options
+fn build_mpv_options(
+    proto: &Protocol,
+    config: &Config,
+    selection: &FormatSelection,
+) -> Vec<String> {
+    let mut options: Vec<String> = Vec::new();
+    let profile_name = proto
+        .profile
+        .or_else(|| crate::auto_profile::select(config));
+
+    if let Some(v) = proto.cookies
+        && let Some(v) = cookies(v)
+    {
+        options.push(v);
+    }
+    if let Some(v) = profile_name {
+        options.push(profile(v));
+    }
+    if let Some(v) = config.profile(profile_name) {
+        options.extend(profile_options(v));
+    }
+    if let Some(v) = formats(&selection.sort) {
+        options.push(v);
+    }
+    if let Some(v) = &proto.v_title {
+        options.push(v_title(v));
+    }
+    if let Some(v) = &proto.subfile {
+        options.push(subfile(v));
+    }
+    if let Some(v) = proto.startat
+        && let Some(v) = resolve_startat(config, &proto.url, v)
+    {
+        options.push(startat(&v));
+    }
+    if let Some(v) = proto.screen.or(config.screen) {
+        options.extend(crate::window::screen_options(v));
+    }
+    if let Some((a, b)) = proto.abloop {
+        options.extend(abloop(a, b));
+    }
+    if proto.dvr == Some(true) {
+        options.push(format!("{PREFIX_RAW_OPTIONS}{OPTION_LIVE_FROM_START}"));
+    }
+    if proto.audio_only == Some(true) {
+        options.extend(OPTIONS_AUDIO_ONLY.map(String::from));
+    }
+    if multiple_audio(&selection.format) {
+        options.push(format!("{PREFIX_RAW_OPTIONS}{OPTION_AUDIO_MULTISTREAMS}"));
+    }
+    if let Some(v) = &config.ytdl {
+        options.push(yt_path(v));
+    }
+    if let Some(v) = impersonate(config) {
+        options.push(format!("{PREFIX_IMPERSONATE}{v}"));
+    }
+    if let Some(v) = &config.title_lang {
+        options.push(format!(
+            "{PREFIX_RAW_OPTIONS}extractor-args={PREFIX_TITLE_LANG}{v}"
+        ));
+    }
+    if let Some(v) = &config.sponsorblock {
+        options.push(format!(
+            "{PREFIX_SCRIPT_OPTS}{}",
+            sponsorblock(&v.categories)
+        ));
+    }
+
+    log::debug!("Protocol: {:?}", proto);
+    log::debug!("Options: {:?}", options);
+
+    options
+}
+
+/// Helper to manage a new mpv instance for a playlist
+///
+/// Playback starts at the `start` entry, preceding entries are moved in
+/// front of it once enqueued.
+fn handle_playlist_in_new_instance(
+    child: &mut std::process::Child,
+    config: &Config,
+    playlist_entries: &[Entry],
+    start: usize,
+    selection: &FormatSelection,
+    profile: Option<&str>,
+    tags: &[String],
+) -> Result<(), Error> {
+    if let Some(socket_path) = &config.socket(profile) {
+        // Wait for the socket to be created, or kill the idle mpv instance
+        let mut conn = match Connection::wait(socket_path, NEW_SOCKET_TIMEOUT) {
+            Ok(v) => v,
+            Err(e) => {
+                child.kill().ok();
+                return Err(e);
+            }
+        };
+
+        // 1. Load the first video (don't pre-extract, let mpv do it)
+        let first = &playlist_entries[start];
+        println!("Playing: {}", first.url);
+        let first_title = match first.range {
+            Some(_) => first.title.clone(),
+            None => crate::dearrow::title(config, &first.url).unwrap_or(first.title.clone()),
+        };
+        let positions = crate::history::resume_positions(config);
+        let mut first_opts = loadfile_options(config, &first_title, &first.url, &[]);
+        resume(&mut first_opts, &positions, &first.url);
+        clip(&mut first_opts, first.range);
+        conn.send_all(&[json!(["loadfile", &first.url, "replace", first_opts])])?;
+
+        let first_item = QueueItem {
+            title: first_title,
+            url: first.url.clone(),
+            filename: first.url.clone(),
+            duration: first.duration,
+            profile: profile.map(String::from),
+            tags: tags.to_vec(),
+            resolved: None,
+            format: None,
+        };
+        write_sidecar(config, &conn.stream, &first_item, &[], 0);
+        let mut added = vec![first_item];
+
+        // 2. Enqueue the rest of the items (pre-extracting for performance),
+        // the following ones first
+        let preceding = &playlist_entries[..start];
+        let mut batch = Batch::new(
+            playlist_entries[start + 1..]
+                .iter()
+                .chain(preceding)
+                .cloned()
+                .collect(),
+        );
+        let mut moved = 0;
+        let mut warnings = Vec::new();
+        let mut resolver = Resolver::new(config, selection);
+        while let Some((_, entry, direct)) = resolver.next(&mut batch) {
+            crate::warnings::merge(&mut warnings, &direct.warnings);
+            if let Some(err) = &direct.error {
+                batch.fail(entry, err);
+                continue;
+            }
+            let video_title = match entry.range {
+                Some(_) => entry.title.clone(),
+                None => crate::dearrow::title(config, &entry.url).unwrap_or(direct.title),
+            };
+            let mut opts = loadfile_options(config, &video_title, &entry.url, &direct.audio_urls);
+            resume(&mut opts, &positions, &entry.url);
+            clip(&mut opts, entry.range);
+
+            let mut index = match conn.append(&direct.video_url, opts, &video_title) {
+                Ok(Some(index)) => index,
+                Ok(None) => {
+                    log::warn!("mpv refused to load '{}'", entry.title);
+                    continue;
+                }
+                Err(e) => {
+                    log::warn!("Failed to enqueue '{}': {}", entry.title, e);
+                    break;
+                }
+            };
+            if index >= 0
+                && preceding.contains(&entry)
+                && conn
+                    .send_all(&[json!(["playlist-move", index, moved])])
+                    .is_ok_and(|v| v[0].is_some())
+            {
+                index = moved;
+                moved += 1;
+            }
+            println!("Enqueued: {}", entry.title);
+            let item = QueueItem {
+                title: video_title,
+                url: entry.url.clone(),
+                resolved: prefetched(&direct.video_url, direct.resolved, &entry.url),
+                format: Some(selection.format.clone()),
+                filename: direct.video_url,
+                duration: direct.duration.or(entry.duration),
+                profile: profile.map(String::from),
+                tags: tags.to_vec(),
+            };
+            write_sidecar(config, &conn.stream, &item, &direct.audio_urls, index);
+            added.push(item);
+        }
+
+        crate::retry::record(config, &batch.failed);
+        finish_enqueue(config, &conn.stream, &added, &warnings);
+
+        // Keep the stream alive until mpv exits by not dropping it.
+        // We can't easily wait for the child and hold the stream, so we detach.
+        // This is a simplification; a more robust solution might use threads.
+        std::mem::forget(conn);
+    }
+    Ok(())
+}
+
+fn cookies(cookies: &str) -> Option<String> {
+    let p = cookies_path(cookies)?;
+    let cookies = p.display();
+    Some(format!("{PREFIX_COOKIES}{cookies}"))
+}
+
+/// Returns the path of the cookies file in the `cookies` config directory
+pub(crate) fn cookies_path(cookies: &str) -> Option<std::path::PathBuf> {
+    let mut p = crate::config::get_config_dir()?;
+    p.push("cookies");
+    p.push(cookies);
+
+    if p.exists() {
+        Some(p)
+    } else {
+        log::warn!("Cookies file not found: {}", p.display());
+        None
+    }
+}
+
+fn profile(profile: &str) -> String {
+    format!("{PREFIX_PROFILE}{profile}")
+}
+
+/// Returns the options of a profile config
+fn profile_options(profile: &Profile) -> Vec<String> {
+    let mut options: Vec<String> = Vec::new();
+    if profile.loudnorm == Some(true) {
+        options.extend(OPTIONS_LOUDNORM.map(String::from));
+    }
+    if profile.gapless == Some(true) {
+        options.extend(OPTIONS_GAPLESS.map(String::from));
+    }
+    if let Some(v) = &profile.audio_device {
+        options.push(format!("{PREFIX_AUDIO_DEVICE}{v}"));
+    }
+    options
+}
+
+/// Returns the yt-dlp output template of `title_from` of the link or config
+///
+/// Only `ytdlp:TEMPLATE` values are supported, e.g. `ytdlp:%(artist)s - %(track)s`.
+fn title_template(proto: &Protocol, config: &Config) -> Option<String> {
+    let value = proto
+        .title_from
+        .as_deref()
+        .or(config.title_from.as_deref())?;
+    match value.strip_prefix(PREFIX_TITLE_FROM_YTDL) {
+        Some(template) => Some(template.to_string()),
+        None => {
+            log::warn!("Ignoring unsupported title_from: {}", value);
+            None
+        }
+    }
+}
+
+/// Returns the yt-dlp format-sort fields of the link and config
+pub(crate) fn format_sort(
+    quality: Option<&str>,
+    v_codec: Option<&str>,
+    max_bitrate: Option<u32>,
+) -> Vec<String> {
+    let mut f: Vec<String> = Vec::new();
+    if let Some(v) = quality {
+        let i: String = v.matches(char::is_numeric).collect();
+        f.push(format!("res:{}", i));
+    }
+    if let Some(v) = v_codec {
+        f.push(format!("+vcodec:{}", v))
+    }
+    if let Some(v) = max_bitrate {
+        f.push(format!("tbr:{}", v))
+    }
+    f
+}
+
+fn formats(sort: &[String]) -> Option<String> {
+    if sort.is_empty() {
+        None
+    } else {
+        Some(format!("{PREFIX_FORMATS}{}", sort.join(",")))
+    }
+}
+
+/// Prefer audio formats of `lang` for each `bestaudio` of the format selector
+///
+/// `bestaudio[ext=m4a]` becomes `(bestaudio[ext=m4a][language^=en]/bestaudio[ext=m4a])`,
+/// so the default audio track is still used if no format matches.
+fn prefer_audio_lang(format: &str, lang: &str) -> String {
+    let mut result = String::new();
+    let mut token = String::new();
+
+    let flush = |token: &mut String, result: &mut String| {
+        let name = token.split('[').next().unwrap_or_default();
+        if name == "bestaudio" || name == "ba" {
+            result.push_str(&format!("({token}[language^={lang}]/{token})"));
+        } else {
+            result.push_str(token);
+        }
+        token.clear();
+    };
+
+    let mut depth = 0;
+    for c in format.chars() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            '+' | '/' | ',' | '(' | ')' if depth == 0 => {
+                flush(&mut token, &mut result);
+                result.push(c);
+                continue;
+            }
+            _ => {}
+        }
+        token.push(c);
+    }
+    flush(&mut token, &mut result);
+
+    result
+}
+
+/// Returns the bitrate cap in kbit/s of `max_bitrate` and the bandwidth probe
+///
+/// Only 80% of the probed bandwidth is used, leaving headroom for the audio.
+fn max_bitrate(config: &Config) -> Option<u32> {
+    let probed = config.bandwidth_probe.as_deref().and_then(|url| {
+        if let Some(v) = crate::cache::get("bandwidth", url, BANDWIDTH_CACHE_TTL) {
+            return Some(v);
+        }
+        let v = crate::http::download_speed(config, url, BANDWIDTH_PROBE_SECONDS)? / 10 * 8;
+        log::debug!("Estimated bandwidth: {} kbit/s", v);
+        crate::cache::put("bandwidth", url, &v, BANDWIDTH_CACHE_TTL);
+        Some(v)
+    });
+
+    match (config.max_bitrate, probed) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn v_title(v_title: &str) -> String {
+    format!("{PREFIX_V_TITLE}{v_title}")
+}
+
+fn subfile(subfile: &str) -> String {
+    format!("{PREFIX_SUBFILE}{subfile}")
+}
+
+fn startat(startat: &str) -> String {
+    format!("{PREFIX_STARTAT}{startat}")
+}
+
+/// Returns an entry per chapter of the video, which play only their part
+///
+/// Returns no entries if the video has no chapters, so it's played whole.
+fn fetch_chapters(config: &Config, url: &str) -> Vec<Entry> {
+    let mut command = ytdl_command(config);
+    command
+        .arg("--ignore-no-formats-error")
+        .arg("--skip-download")
+        .arg("--no-playlist")
+        .arg("--print")
+        .arg("%(chapters)j")
+        .arg("--print")
+        .arg("%(title)s")
+        .arg(url);
+    let output = match ytdl_output(config, &mut command) {
+        Ok(Some(v)) if v.status.success() => v,
+        _ => {
+            log::warn!("Failed to fetch the chapters of: {}", url);
+            return Vec::new();
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    let (chapters, title) = (
+        lines.next().unwrap_or_default(),
+        lines.next().unwrap_or(url),
+    );
+    let entries = chapter_entries(chapters, title, url);
+    if entries.is_empty() {
+        log::info!("No chapters, playing the whole video: {}", url);
+    }
+    entries
+}
+
+/// Returns the entries of the chapters of the `%(chapters)j` output
+fn chapter_entries(chapters: &str, title: &str, url: &str) -> Vec<Entry> {
+    let chapters: Vec<serde_json::Value> =
+        serde_json::from_str(chapters.trim()).unwrap_or_default();
+    chapters
+        .iter()
+        .enumerate()
+        .filter_map(|(i, chapter)| {
+            let start = chapter["start_time"].as_f64()?;
+            let end = chapter["end_time"].as_f64()?;
+            let name = match chapter["title"].as_str() {
+                Some(name) => format!("{title} - {name}"),
+                None => format!("{title} ({}/{})", i + 1, chapters.len()),
+            };
+            Some(Entry {
+                title: name,
+                url: url.to_string(),
+                duration: Some(end - start),
+                upload_date: None,
+                range: Some((start, end)),
+            })
+        })
+        .collect()
+}
+
+/// Play only the part of the entry, see `Entry.range`
+fn clip(options: &mut serde_json::Map<String, serde_json::Value>, range: Option<(f64, f64)>) {
+    if let Some((start, end)) = range {
+        options.insert("start".to_string(), json!(start.to_string()));
+        options.insert("end".to_string(), json!(end.to_string()));
+    }
+}
+
+/// Resolve `chapter:N` and `chapter:NAME` values of `startat` to seconds
+///
+/// Chapters are numbered from 1, names match case-insensitively by prefix.
+/// Other values are passed to mpv as they are.
+fn resolve_startat(config: &Config, url: &str, value: &str) -> Option<String> {
+    let Some(chapter) = value.strip_prefix(PREFIX_STARTAT_CHAPTER) else {
+        return Some(value.to_string());
+    };
+
+    let mut command = ytdl_command(config);
+    command
+        .arg("--ignore-no-formats-error")
+        .arg("--skip-download")
+        .arg("--print")
+        .arg("%(chapters)j")
+        .arg(url);
+    let output = ytdl_output(config, &mut command).ok()??;
+
+    let start = chapter_start(&String::from_utf8_lossy(&output.stdout), chapter);
+    if start.is_none() {
+        log::warn!("Chapter not found, ignoring startat: {}", chapter);
+    }
+    start.map(|v| v.to_string())
+}
+
+/// Returns the start time of the chapter from the `%(chapters)j` output
+fn chapter_start(chapters: &str, chapter: &str) -> Option<f64> {
+    let chapters: Vec<serde_json::Value> = serde_json::from_str(chapters.trim()).ok()?;
+
+    let found = match chapter.parse::<usize>() {
+        Ok(n) => chapters.get(n.checked_sub(1)?),
+        Err(_) => {
+            let name = chapter.replace("%20", " ").replace('+', " ").to_lowercase();
+            chapters.iter().find(|v| {
+                v["title"]
+                    .as_str()
+                    .is_some_and(|title| title.to_lowercase().starts_with(&name))
+            })
+        }
+    };
+    found?["start_time"].as_f64()
+}
+
+fn abloop(a: f64, b: f64) -> [String; 2] {
+    [
+        format!("{PREFIX_AB_LOOP_A}{a}"),
+        format!("{PREFIX_AB_LOOP_B}{b}"),
+    ]
+}
+
+fn yt_path(yt_path: &str) -> String {
+    format!("{PREFIX_YT_PATH}{yt_path}")
+}
+
+/// Script option read by the companion `mpv-handler-sponsorblock.lua`
+///
+/// Categories are separated by `|`, since `,` separates script options.
+fn sponsorblock(categories: &[String]) -> String {
+    format!("{PREFIX_SB_CATEGORIES}{}", categories.join("|"))
+}
+
+#[test]
+fn test_profile_option() {
+    let p = profile("low-latency");
+    assert_eq!(p, "--profile=low-latency");
+}
+
+#[test]
+fn test_profile_options() {
+    let p = profile_options(&Profile {
+        loudnorm: Some(true),
+        ..Default::default()
+    });
+    assert_eq!(
+        p,
+        [
+            "--af-append=lavfi=[loudnorm=I=-16:TP=-1.5:LRA=11]",
+            "--replaygain=track"
+        ]
+    );
+
+    let p = profile_options(&Profile {
+        gapless: Some(true),
+        ..Default::default()
+    });
+    assert_eq!(p, ["--gapless-audio=yes", "--prefetch-playlist=yes"]);
+
+    let p = profile_options(&Profile {
+        audio_device: Some("pulse/hdmi".to_string()),
+        ..Default::default()
+    });
+    assert_eq!(p, ["--audio-device=pulse/hdmi"]);
+
+    let p = profile_options(&Profile::default());
+    assert!(p.is_empty());
+}
+
+#[test]
+fn test_formats_option() {
+    let q = formats(&format_sort(Some("720p"), None, None));
+    assert_eq!(q.unwrap(), "--ytdl-raw-options-append=format-sort=res:720");
+
+    let v = formats(&format_sort(None, Some("vp9"), None));
+    assert_eq!(
+        v.unwrap(),
+        "--ytdl-raw-options-append=format-sort=+vcodec:vp9"
+    );
+
+    let qv = formats(&format_sort(Some("720p"), Some("vp9"), None));
+    assert_eq!(
+        qv.unwrap(),
+        "--ytdl-raw-options-append=format-sort=res:720,+vcodec:vp9"
+    );
+
+    let qb = formats(&format_sort(Some("1080p"), None, Some(2500)));
+    assert_eq!(
+        qb.unwrap(),
+        "--ytdl-raw-options-append=format-sort=res:1080,tbr:2500"
+    );
+
+    assert_eq!(formats(&format_sort(None, None, None)), None);
+}
+
+#[test]
+fn test_prefer_audio_lang() {
+    assert_eq!(
+        prefer_audio_lang("bestvideo[height<=?1080]+bestaudio/best", "en"),
+        "bestvideo[height<=?1080]+(bestaudio[language^=en]/bestaudio)/best"
+    );
+    assert_eq!(
+        prefer_audio_lang("bv*+ba[ext=m4a]/b", "de"),
+        "bv*+(ba[ext=m4a][language^=de]/ba[ext=m4a])/b"
+    );
+    assert_eq!(prefer_audio_lang("best", "en"), "best");
+}
+
+#[test]
+fn test_v_title_option() {
+    let t = v_title("Hello World!");
+    assert_eq!(t, "--title=Hello World!");
+}
+
+#[test]
+fn test_subfile_option() {
+    let s = subfile("http://example.com/en.ass");
+    assert_eq!(s, "--sub-file=http://example.com/en.ass");
+}
+
+#[test]
+fn test_startat_option() {
+    let s = startat("233");
+    assert_eq!(s, "--start=233");
+}
+
+#[test]
+fn test_chapter_entries() {
+    let chapters = r#"[
+        {"start_time": 0.0, "end_time": 60.0, "title": "Intro"},
+        {"start_time": 60.0, "end_time": 300.5}
+    ]"#;
+    let entries = chapter_entries(chapters, "Mix", "https://example.com/v");
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].title, "Mix - Intro");
+    assert_eq!(entries[0].range, Some((0.0, 60.0)));
+    assert_eq!(entries[1].title, "Mix (2/2)");
+    assert_eq!(entries[1].duration, Some(240.5));
+    assert!(chapter_entries("NA", "Mix", "https://example.com/v").is_empty());
+}
+
+#[test]
+fn test_chapter_start() {
+    let chapters = r#"[
+        {"start_time": 0.0, "end_time": 60.0, "title": "Intro"},
+        {"start_time": 60.0, "end_time": 300.5, "title": "Main Topic"},
+        {"start_time": 300.5, "end_time": 420.0, "title": "Outro"}
+    ]"#;
+
+    assert_eq!(chapter_start(chapters, "2"), Some(60.0));
+    assert_eq!(chapter_start(chapters, "3"), Some(300.5));
+    assert_eq!(chapter_start(chapters, "main%20topic"), Some(60.0));
+    assert_eq!(chapter_start(chapters, "Out"), Some(300.5));
+    assert_eq!(chapter_start(chapters, "0"), None);
+    assert_eq!(chapter_start(chapters, "4"), None);
+    assert_eq!(chapter_start("NA", "1"), None);
+}
+
+#[test]
+fn test_filter_entries() {
+    let titles = [
+        ("Episode 1", Some(1800.0)),
+        ("Clip #shorts", Some(45.0)),
+        ("Episode 2 #shorts", None),
+        ("Trailer", Some(120.0)),
+    ];
+    let filtered = |proto: &Protocol, config: &Config| -> Vec<String> {
+        let mut entries: Vec<Entry> = titles
+            .iter()
+            .map(|(title, duration)| Entry {
+                title: title.to_string(),
+                url: "https://example.com/".to_string(),
+                duration: *duration,
+                upload_date: None,
+                range: None,
+            })
+            .collect();
+        filter_entries(proto, config, &mut entries);
+        entries.into_iter().map(|v| v.title).collect()
+    };
+
+    let mut proto = Protocol::from_url("https://example.com/playlist".to_string());
+    let mut config = Config::default();
+    assert_eq!(filtered(&proto, &config).len(), 4);
+
+    config.filter = Some(crate::config::EntryFilter {
+        include: Vec::new(),
+        exclude: vec!["#shorts".to_string()],
+        ..Default::default()
+    });
+    assert_eq!(filtered(&proto, &config), ["Episode 1", "Trailer"]);
+
+    proto.filter = Some("^Episode".to_string());
+    assert_eq!(filtered(&proto, &config), ["Episode 1"]);
+
+    config.filter = None;
+    proto.filter = Some("!^Episode".to_string());
+    assert_eq!(filtered(&proto, &config), ["Clip #shorts", "Trailer"]);
+
+    // Unknown durations are kept
+    proto.filter = None;
+    proto.min_duration = Some(60.0);
+    assert_eq!(
+        filtered(&proto, &config),
+        ["Episode 1", "Episode 2 #shorts", "Trailer"]
+    );
+    proto.max_duration = Some(600.0);
+    assert_eq!(filtered(&proto, &config), ["Episode 2 #shorts", "Trailer"]);
+}
+
+#[test]
+fn test_title_template() {
+    let mut proto = Protocol::from_url("https://music.youtube.com/watch?v=1".to_string());
+    let mut config = Config {
+        title_from: Some("ytdlp:%(artist)s - %(track)s".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        title_template(&proto, &config),
+        Some("%(artist)s - %(track)s".to_string())
+    );
+
+    proto.title_from = Some("ytdlp:%(uploader)s: %(title)s".to_string());
+    assert_eq!(
+        title_template(&proto, &config),
+        Some("%(uploader)s: %(title)s".to_string())
+    );
+
+    proto.title_from = None;
+    config.title_from = Some("%(title)s".to_string());
+    assert_eq!(title_template(&proto, &config), None);
+}
+
+#[test]
+fn test_playlist_end() {
+    let mut proto = Protocol::from_url("https://www.youtube.com/playlist?list=PL1".to_string());
+    let mut config = Config::default();
+    assert_eq!(playlist_end(&proto, &config), None);
+
+    proto.items = Some(5);
+    assert_eq!(playlist_end(&proto, &config), Some(5));
+    config.playlist_end = Some(3);
+    assert_eq!(playlist_end(&proto, &config), Some(3));
+
+    // Counted from the clicked video
+    config.playlist_end = None;
+    proto.url = "https://www.youtube.com/playlist?list=PL1&index=10".to_string();
+    assert_eq!(playlist_end(&proto, &config), Some(14));
+    proto.url = "https://www.youtube.com/watch?v=Ggkn2f5e-IU&list=PL1".to_string();
+    assert_eq!(playlist_end(&proto, &config), None);
+
+    proto.url = "https://www.youtube.com/playlist?list=PL1".to_string();
+    proto.min_duration = Some(60.0);
+    assert_eq!(playlist_end(&proto, &config), None);
+}
+
+#[test]
+fn test_playlist_start() {
+    let entries: Vec<Entry> = ["a", "b", "c"]
+        .iter()
+        .map(|id| Entry {
+            title: id.to_string(),
+            url: format!("https://www.youtube.com/watch?v={id}"),
+            duration: None,
+            upload_date: None,
+            range: None,
+        })
+        .collect();
+
+    let url = "https://www.youtube.com/watch?v=b&list=PL1";
+    assert_eq!(playlist_start(url, &entries), Some(1));
+    let url = "https://www.youtube.com/playlist?list=PL1&index=3";
+    assert_eq!(playlist_start(url, &entries), Some(2));
+    let url = "https://www.youtube.com/watch?v=x&list=PL1&index=4";
+    assert_eq!(playlist_start(url, &entries), None);
+    let url = "https://www.youtube.com/watch?v=b";
+    assert_eq!(playlist_start(url, &entries), None);
+}
+
+#[test]
+fn test_loadfile_audio() {
+    let audio = [
+        "https://a.com/1?x=1,2".to_string(),
+        "https://a.com/2".to_string(),
+    ];
+
+    let opts = loadfile_options(&Config::default(), "T", "https://example.com/", &audio[..1]);
+    assert_eq!(opts["audio-file"], json!("https://a.com/1?x=1,2"));
+    let opts = loadfile_options(&Config::default(), "T", "https://example.com/", &audio);
+    assert_eq!(opts["audio-file"], json!(audio));
+
+    assert!(multiple_audio("bv+ba[language=en]+ba[language=de]/best"));
+    assert!(!multiple_audio("bv*+ba/b"));
+    assert!(!multiple_audio("bestvideo+bestaudio"));
+}
+
+#[test]
+fn test_sort_entries() {
+    let entry = |title: &str, duration: Option<f64>, upload_date: Option<&str>| Entry {
+        title: title.to_string(),
+        url: format!("https://example.com/{title}"),
+        duration,
+        upload_date: upload_date.map(String::from),
+        range: None,
+    };
+    let titles =
+        |entries: &[Entry]| -> Vec<String> { entries.iter().map(|v| v.title.clone()).collect() };
+    let mut entries = vec![
+        entry("b", Some(300.0), Some("20240301")),
+        entry("C", None, Some("20240101")),
+        entry("a", Some(60.0), None),
+    ];
+
+    sort_entries(&mut entries, &"duration".parse().unwrap());
+    assert_eq!(titles(&entries), ["a", "b", "C"]);
+    sort_entries(&mut entries, &"-date".parse().unwrap());
+    assert_eq!(titles(&entries), ["b", "C", "a"]);
+    sort_entries(&mut entries, &"title".parse().unwrap());
+    assert_eq!(titles(&entries), ["a", "b", "C"]);
+
+    assert_eq!(
+        upload_date(&json!({"timestamp": 1700000000})),
+        Some("20231114".to_string())
+    );
+}
+
+#[test]
+fn test_rejected_option() {
+    assert_eq!(
+        rejected_option("Setting commandline option --gapless-audio=yes failed.\n"),
+        Some("--gapless-audio=yes")
+    );
+    assert_eq!(
+        rejected_option("Setting commandline option --foo= failed."),
+        Some("--foo=")
+    );
+    assert_eq!(
+        rejected_option("Error parsing option foo (option not found)"),
+        None
+    );
+}
+
+#[test]
+fn test_abloop_option() {
+    let l = abloop(90.0, 120.5);
+    assert_eq!(l, ["--ab-loop-a=90", "--ab-loop-b=120.5"]);
+}
+
+#[test]
+fn test_yt_path_option() {
+    let y = yt_path("/usr/bin/yt-dlp");
+    assert_eq!(y, "--script-opts=ytdl_hook-ytdl_path=/usr/bin/yt-dlp");
+}
+
+#[test]
+fn test_sponsorblock_option() {
+    let s = sponsorblock(&["sponsor".to_string(), "selfpromo".to_string()]);
+    assert_eq!(s, "mpv_handler-sponsorblock=sponsor|selfpromo");
+}
+
+#[test]
+fn test_impersonate_available() {
+    let targets = "[info] Available impersonate targets
+Client         OS                Source
+---------------------------------------
+Chrome-124     Macos-14          curl_cffi
+Edge           -                 curl_cffi (unavailable)
+Safari-17.0    Macos-14          curl_cffi";
+
+    assert!(impersonate_available(targets, "chrome"));
+    assert!(impersonate_available(targets, "chrome-124:macos-14"));
+    assert!(impersonate_available(targets, "Safari"));
+    assert!(!impersonate_available(targets, "edge"));
+    assert!(!impersonate_available(targets, "firefox"));
+}
+
+#[test]
+fn test_channel_url() {
+    assert!(is_channel_url("https://www.youtube.com/@example"));
+    assert!(is_channel_url("https://www.youtube.com/@example/videos"));
+    assert!(is_channel_url("https://youtube.com/channel/UCxxxx"));
+    assert!(is_channel_url("https://m.youtube.com/c/example"));
+    assert!(!is_channel_url(
+        "https://www.youtube.com/watch?v=Ggkn2f5e-IU"
+    ));
+    assert!(!is_channel_url("https://example.com/@example"));
+
+    assert_eq!(
+        channel_uploads_url("https://www.youtube.com/@example"),
+        "https://www.youtube.com/@example/videos"
+    );
+    assert_eq!(
+        channel_uploads_url("https://www.youtube.com/@example/featured"),
+        "https://www.youtube.com/@example/videos"
+    );
+    assert_eq!(
+        channel_uploads_url("https://www.youtube.com/channel/UCxxxx/"),
+        "https://www.youtube.com/channel/UCxxxx/videos"
+    );
+    assert_eq!(
+        channel_uploads_url("https://www.youtube.com/@example/streams"),
+        "https://www.youtube.com/@example/streams"
+    );
+    assert_eq!(
+        channel_uploads_url("https://youtube.com/"),
+        "https://youtube.com/"
+    );
+}
+
+#[test]
+fn test_url_cache_key() {
+    let mut selection = FormatSelection {
+        format: "best".to_string(),
+        sort: Vec::new(),
+        check_formats: false,
+        prefetch: true,
+        title_template: None,
+        streamlink: None,
+    };
+    let key = url_cache_key(
+        &selection,
+        "https://www.youtube.com/watch?v=Ggkn2f5e-IU&t=10",
+    );
+    assert_eq!(
+        key,
+        url_cache_key(&selection, "https://youtu.be/Ggkn2f5e-IU")
+    );
+
+    selection.sort = vec!["res:720".to_string()];
+    assert_ne!(
+        key,
+        url_cache_key(&selection, "https://youtu.be/Ggkn2f5e-IU")
+    );
+}
+
+#[test]
+fn test_parse_resolved() {
+    // Titles with newlines stay in one JSON line
+    let stdout = format!(
+        "{}\n{PREFIX_TEMPLATE_TITLE}Artist - Track\n",
+        json!({
+            "title": "Line\nbreak",
+            "requested_formats": [
+                { "url": "https://cdn.example.com/a.m4a", "vcodec": "none" },
+                { "url": "https://cdn.example.com/v.mp4", "vcodec": "avc1" },
+            ],
+            "duration": 61.5,
+            "uploader": "Uploader",
+        })
+    );
+    let (media, title) = parse_resolved(&stdout).unwrap();
+    assert_eq!(title, Some("Artist - Track".to_string()));
+    assert_eq!(media.title, "Line\nbreak");
+    assert_eq!(media.duration, Some(61.5));
+    assert_eq!(media.uploader, Some("Uploader".to_string()));
+    assert_eq!(
+        media.urls(),
+        Some((
+            "https://cdn.example.com/v.mp4".to_string(),
+            vec!["https://cdn.example.com/a.m4a".to_string()]
+        ))
+    );
+
+    let (media, title) = parse_resolved(
+        "{\"title\": \"T\", \"url\": \"https://cdn.example.com/v.mp4\", \"is_live\": true}",
+    )
+    .unwrap();
+    assert_eq!(title, None);
+    assert_eq!(media.is_live, Some(true));
+    assert_eq!(media.urls().unwrap().1, Vec::<String>::new());
+
+    assert!(parse_resolved("Title\nhttps://cdn.example.com/v.mp4\n").is_none());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_exec_new_instance() {
+    use crate::runner::{CannedRunner, Runner};
+
+    let dir = std::env::temp_dir().join(format!("mpv-handler-test-new-{}", std::process::id()));
+    let runner = std::sync::Arc::new(CannedRunner::new());
+    let config = Config {
+        mpv: Some("mpv".to_string()),
+        socket: Some(dir.join("mpvsocket").display().to_string()),
+        log_dir: Some(dir.display().to_string()),
+        dialog: Some(crate::config::DialogBackend::Disabled),
+        metered: Some(false),
+        runner: Runner::new(runner.clone()),
+        url_cache_ttl: Some(0),
+        ..Default::default()
+    };
+
+    // No running mpv, so a single video opens a new instance
+    let proto = Protocol {
+        enqueue: Some(true),
+        ..Protocol::from_url("https://example.com/v.mp4".to_string())
+    };
+    exec(&proto, &config).unwrap();
+
+    let commands = runner.commands();
+    assert_eq!(commands.len(), 1);
+    assert_eq!(commands[0][0], "mpv");
+    assert!(commands[0].ends_with(&["--".to_string(), proto.url.clone()]));
+    assert!(!commands[0].contains(&"--idle=yes".to_string()));
+
+    // A named instance is launched on its own socket
+    let music = dir.join("mpv-music").display().to_string();
+    let config = Config {
+        instances: Some(HashMap::from([(
+            "music".to_string(),
+            crate::config::Instance {
+                socket: music.clone(),
+                profile: None,
+            },
+        )])),
+        ..config
+    };
+    let proto = Protocol {
+        instance: Some("music"),
+        ..Protocol::from_url("https://example.com/v.mp4".to_string())
+    };
+    exec(&proto, &config).unwrap();
+    assert!(runner.commands()[1].contains(&format!("--input-ipc-server={music}")));
+
+    let proto = Protocol {
+        instance: Some("video"),
+        ..proto
+    };
+    assert!(matches!(
+        exec(&proto, &config),
+        Err(Error::UnknownInstance(_))
+    ));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_exec_enqueue_playlist() {
+    use crate::runner::{CannedRunner, Runner};
+    use std::io::{BufRead, Write};
+    use std::sync::{Arc, Mutex};
+
+    let dir = std::env::temp_dir().join(format!("mpv-handler-test-enqueue-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let socket = dir.join("mpvsocket");
+
+    // mpv replying to every command, but refusing to load files
+    let loaded = Arc::new(Mutex::new(Vec::new()));
+    let listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+    let received = loaded.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = stream.unwrap();
+            let received = received.clone();
+            std::thread::spawn(move || {
+                let mut writer = stream.try_clone().unwrap();
+                for line in std::io::BufReader::new(stream).lines() {
+                    let Ok(line) = line else {
+                        break;
+                    };
+                    let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+                    let reply = match request["command"][0].as_str() {
+                        Some("loadfile") => {
+                            received.lock().unwrap().push(request["command"][1].clone());
+                            json!({ "error": "loading failed", "request_id": request["request_id"] })
+                        }
+                        _ => {
+                            json!({ "data": 131077, "error": "success", "request_id": request["request_id"] })
+                        }
+                    };
+                    let _ = writer.write_all((reply.to_string() + "\n").as_bytes());
+                }
+            });
+        }
+    });
+
+    let runner = Arc::new(
+        CannedRunner::new()
+            .reply(
+                "--flat-playlist",
+                "{\"title\": \"One\", \"url\": \"https://example.com/1\"}\n\
+                 {\"title\": \"Two\", \"url\": \"https://example.com/2\"}\n",
+            )
+            .reply(
+                "--dump-json",
+                "{\"title\": \"Title\", \"url\": \"https://cdn.example.com/v.mp4\", \"duration\": 180}\n",
+            ),
+    );
+    let config = Config {
+        socket: Some(socket.display().to_string()),
+        dialog: Some(crate::config::DialogBackend::Disabled),
+        metered: Some(false),
+        runner: Runner::new(runner.clone()),
+        url_cache_ttl: Some(0),
+        ..Default::default()
+    };
+
+    // The playlist is resolved and enqueued to the running mpv
+    let proto = Protocol {
+        enqueue: Some(true),
+        ..Protocol::from_url("https://www.youtube.com/watch?v=1&list=PL1".to_string())
+    };
+    exec(&proto, &config).unwrap();
+
+    let commands = runner.commands();
+    assert!(commands[0].contains(&"--flat-playlist".to_string()));
+    let resolved = commands
+        .iter()
+        .filter(|v| !v.contains(&"--flat-playlist".to_string()));
+    assert_eq!(resolved.count(), 2);
+    assert!(!commands.iter().any(|v| v[0] == "mpv"));
+    assert_eq!(
+        *loaded.lock().unwrap(),
+        vec![json!("https://cdn.example.com/v.mp4"); 2]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,573 @@
+use crate::config::PlaylistSort;
+use crate::error::Error;
+use crate::plugins::Plugins;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schemes {
+    Mpv,
+    MpvDebug,
+}
+
+/// How the `split` parameter expands a single video into queue entries
+#[derive(Clone, Debug, PartialEq)]
+pub enum Split {
+    /// One entry per chapter
+    Chapters,
+}
+
+impl std::str::FromStr for Split {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chapters" => Ok(Split::Chapters),
+            _ => Err(format!("unknown split: {s}")),
+        }
+    }
+}
+
+/// Time within which the same link is ignored as a duplicate click
+const REPEAT_WINDOW: std::time::Duration = std::time::Duration::from_secs(5);
+
+const SAFE_PROTOS: [&str; 11] = [
+    "http", "https", "ftp", "ftps", "rtmp", "rtmps", "rtmpe", "rtmpt", "rtmpts", "rtmpte", "data",
+];
+
+/// Protocol of mpv-handler
+///
+/// ```text
+/// mpv://PLUGINS/ENCODED_URL/?PARAMETERS=VALUES
+/// mpv-debug://PLUGINS/ENCODED_URL/?PARAMETERS=VALUES
+/// ```
+///
+/// PLUGINS:
+/// - play
+/// - feed
+/// - download
+/// - handler-test, without ENCODED_URL and PARAMETERS
+///
+/// ENCODED_URL:
+/// - URL-safe base64 encoded URL
+///
+/// PARAMETERS:
+/// - cookies
+/// - profile
+/// - quality
+/// - v_codec
+/// - v_title
+/// - subfile
+/// - startat
+/// - enqueue
+/// - items
+/// - abloop
+/// - pickformat
+/// - datasaver
+/// - dvr
+/// - sort
+/// - filter
+/// - min_duration
+/// - max_duration
+/// - title_from
+/// - tag
+/// - new
+/// - screen
+/// - subs
+/// - pickitems
+/// - playnext
+/// - split
+/// - audioonly
+/// - instance
+#[derive(Clone, Debug, PartialEq)]
+pub struct Protocol<'a> {
+    pub scheme: Schemes,
+    pub plugin: Plugins,
+    pub url: String,
+    pub cookies: Option<&'a str>,
+    pub profile: Option<&'a str>,
+    pub quality: Option<&'a str>,
+    pub v_codec: Option<&'a str>,
+    pub v_title: Option<String>,
+    pub subfile: Option<String>,
+    pub startat: Option<&'a str>,
+    pub enqueue: Option<bool>,
+    pub items: Option<usize>,
+    pub abloop: Option<(f64, f64)>,
+    pub pickformat: Option<bool>,
+    pub datasaver: Option<bool>,
+    pub dvr: Option<bool>,
+    pub sort: Option<PlaylistSort>,
+    pub filter: Option<String>,
+    pub min_duration: Option<f64>,
+    pub max_duration: Option<f64>,
+    pub title_from: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub new_instance: Option<bool>,
+    pub screen: Option<u32>,
+    pub subs: Option<&'a str>,
+    pub pickitems: Option<bool>,
+    pub play_next: Option<bool>,
+    pub split: Option<Split>,
+    pub audio_only: Option<bool>,
+    pub instance: Option<&'a str>,
+}
+
+impl Protocol<'_> {
+    /// Returns `Protocol` which plays the given URL with default parameters
+    ///
+    /// Used by commands which enqueue URLs without being called by a link.
+    pub fn from_url(url: String) -> Protocol<'static> {
+        Protocol {
+            scheme: Schemes::Mpv,
+            plugin: Plugins::Play,
+            url,
+            cookies: None,
+            profile: None,
+            quality: None,
+            v_codec: None,
+            v_title: None,
+            subfile: None,
+            startat: None,
+            enqueue: Some(true),
+            items: None,
+            abloop: None,
+            pickformat: None,
+            datasaver: None,
+            dvr: None,
+            sort: None,
+            filter: None,
+            min_duration: None,
+            max_duration: None,
+            title_from: None,
+            tags: None,
+            new_instance: None,
+            screen: None,
+            subs: None,
+            pickitems: None,
+            play_next: None,
+            split: None,
+            audio_only: None,
+            instance: None,
+        }
+    }
+}
+
+impl<'a> Protocol<'a> {
+    /// Parse the given argument and returns `Protocol`
+    pub fn parse(arg: &'a str) -> Result<Protocol<'a>, Error> {
+        let scheme;
+        let plugin;
+        let url;
+        let mut cookies: Option<&str> = None;
+        let mut profile: Option<&str> = None;
+        let mut quality: Option<&str> = None;
+        let mut v_codec: Option<&'a str> = None;
+        let mut v_title: Option<String> = None;
+        let mut subfile: Option<String> = None;
+        let mut startat: Option<&'a str> = None;
+        let mut enqueue: Option<bool> = None;
+        let mut items: Option<usize> = None;
+        let mut abloop: Option<(f64, f64)> = None;
+        let mut pickformat: Option<bool> = None;
+        let mut datasaver: Option<bool> = None;
+        let mut dvr: Option<bool> = None;
+        let mut sort: Option<PlaylistSort> = None;
+        let mut filter: Option<String> = None;
+        let mut min_duration: Option<f64> = None;
+        let mut max_duration: Option<f64> = None;
+        let mut title_from: Option<String> = None;
+        let mut tags: Option<Vec<String>> = None;
+        let mut new_instance: Option<bool> = None;
+        let mut screen: Option<u32> = None;
+        let mut subs: Option<&str> = None;
+        let mut pickitems: Option<bool> = None;
+        let mut play_next: Option<bool> = None;
+        let mut split: Option<Split> = None;
+        let mut audio_only: Option<bool> = None;
+        let mut instance: Option<&str> = None;
+
+        let mut i: usize;
+
+        // Check scheme `mpv://` and `mpv-debug://`
+        (i, scheme) = if let Some(s) = arg.find("://") {
+            match &arg[..s] {
+                "mpv" => (s + "://".len(), Schemes::Mpv),
+                "mpv-debug" => (s + "://".len(), Schemes::MpvDebug),
+                _ => return Err(Error::IncorrectProtocol(arg.to_string())),
+            }
+        } else {
+            return Err(Error::IncorrectProtocol(arg.to_string()));
+        };
+
+        // `mpv://handler-test` verifies the installation, without a URL
+        if arg[i..].trim_end_matches('/') == "handler-test" {
+            return Ok(Protocol {
+                scheme,
+                plugin: Plugins::HandlerTest,
+                ..Protocol::from_url(String::new())
+            });
+        }
+
+        // Get plugin
+        (i, plugin) = if let Some(s) = arg[i..].find('/') {
+            match &arg[i..i + s] {
+                "play" => (i + s + 1, Plugins::Play),
+                "feed" => (i + s + 1, Plugins::Feed),
+                "download" => (i + s + 1, Plugins::Download),
+                _ => return Err(Error::IncorrectProtocol(arg.to_string())),
+            }
+        } else {
+            return Err(Error::IncorrectProtocol(arg.to_string()));
+        };
+
+        // Get url and decode by base64
+        (i, url) = if let Some(s) = arg[i..].find('/') {
+            (i + s + 1, decode_url(&arg[i..i + s])?)
+        } else {
+            (arg.len(), decode_url(&arg[i..])?)
+        };
+
+        // Get parameters
+        if let Some(s) = arg[i..].find('?') {
+            let params: Vec<&str> = arg[i + s + 1..].split('&').collect();
+
+            for param in params {
+                let data: Vec<&str> = param.split_terminator('=').collect();
+
+                if data.len() != 2 {
+                    return Err(Error::IncorrectProtocol(arg.to_string()));
+                }
+
+                let k = data[0];
+                let v = data[1];
+
+                match k {
+                    "cookies" => cookies = Some(v),
+                    "profile" => profile = Some(v),
+                    "quality" => quality = Some(v),
+                    "v_codec" => v_codec = Some(v),
+                    "v_title" => v_title = Some(decode_txt(v)?),
+                    "subfile" => subfile = Some(decode_url(v)?),
+                    "startat" => startat = Some(v),
+                    "enqueue" => enqueue = Some(parse_value(v, arg)?),
+                    "items" => items = Some(parse_value(v, arg)?),
+                    "abloop" => abloop = Some(parse_abloop(v, arg)?),
+                    "pickformat" => pickformat = Some(parse_flag(v, arg)?),
+                    "datasaver" => datasaver = Some(parse_flag(v, arg)?),
+                    "dvr" => dvr = Some(parse_flag(v, arg)?),
+                    "sort" => sort = Some(parse_value(v, arg)?),
+                    "filter" => filter = Some(parse_filter(v, arg)?),
+                    "min_duration" => min_duration = Some(parse_seconds(v, arg)?),
+                    "max_duration" => max_duration = Some(parse_seconds(v, arg)?),
+                    "title_from" => title_from = Some(decode_txt(v)?),
+                    "tag" => tags = Some(parse_tags(v, arg)?),
+                    "new" => new_instance = Some(parse_flag(v, arg)?),
+                    "screen" => screen = Some(parse_value(v, arg)?),
+                    "subs" => subs = Some(v),
+                    "pickitems" => pickitems = Some(parse_flag(v, arg)?),
+                    "playnext" => play_next = Some(parse_flag(v, arg)?),
+                    "split" => split = Some(parse_value(v, arg)?),
+                    "audioonly" => audio_only = Some(parse_flag(v, arg)?),
+                    "instance" => instance = Some(v),
+                    _ => {}
+                };
+            }
+        }
+
+        Ok(Protocol {
+            scheme,
+            plugin,
+            url,
+            cookies,
+            profile,
+            quality,
+            v_codec,
+            v_title,
+            subfile,
+            startat,
+            enqueue,
+            items,
+            abloop,
+            pickformat,
+            datasaver,
+            dvr,
+            sort,
+            filter,
+            min_duration,
+            max_duration,
+            title_from,
+            tags,
+            new_instance,
+            screen,
+            subs,
+            pickitems,
+            play_next,
+            split,
+            audio_only,
+            instance,
+        })
+    }
+}
+
+/// Returns `true` if the same link was handled within the last seconds, by a
+/// double-click or a retry of the browser, and records the link otherwise
+///
+/// Links are compared by their whole payload, so the same video with other
+/// parameters isn't a repeat. Each link is recorded by a marker file in the
+/// cache directory, created atomically, so only one of two handlers started
+/// at once goes on.
+pub fn repeated(link: &str) -> bool {
+    match crate::config::get_cache_dir() {
+        Some(mut dir) => {
+            dir.push("links");
+            repeated_in(&dir, link, REPEAT_WINDOW)
+        }
+        None => false,
+    }
+}
+
+/// Check and record the link by its marker file in `dir`, markers older than
+/// `window` are removed
+fn repeated_in(dir: &std::path::Path, link: &str, window: std::time::Duration) -> bool {
+    if std::fs::create_dir_all(dir).is_err() {
+        return false;
+    }
+    let expired = |path: &std::path::Path| {
+        std::fs::metadata(path)
+            .and_then(|v| v.modified())
+            .is_ok_and(|v| v.elapsed().is_ok_and(|v| v > window))
+    };
+    for file in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+        if expired(&file.path()) {
+            let _ = std::fs::remove_file(file.path());
+        }
+    }
+
+    let marker = dir.join(format!("{:x}", md5::compute(link)));
+    let created = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&marker);
+    match created {
+        Ok(_) => false,
+        Err(e) => e.kind() == std::io::ErrorKind::AlreadyExists,
+    }
+}
+
+/// Parse parameter value, returns `IncorrectProtocol` error on failure
+fn parse_value<T: std::str::FromStr>(value: &str, arg: &str) -> Result<T, Error> {
+    value
+        .parse::<T>()
+        .map_err(|_| Error::IncorrectProtocol(arg.to_string()))
+}
+
+/// Parse flag value `1`, `0`, `true` or `false`
+fn parse_flag(value: &str, arg: &str) -> Result<bool, Error> {
+    match value {
+        "1" | "true" => Ok(true),
+        "0" | "false" => Ok(false),
+        _ => Err(Error::IncorrectProtocol(arg.to_string())),
+    }
+}
+
+/// Parse encoded title filter, `!` prefix excludes matching entries
+fn parse_filter(value: &str, arg: &str) -> Result<String, Error> {
+    let filter = decode_txt(value)?;
+    match regex::Regex::new(filter.strip_prefix('!').unwrap_or(&filter)) {
+        Ok(_) => Ok(filter),
+        Err(_) => Err(Error::IncorrectProtocol(arg.to_string())),
+    }
+}
+
+/// Parse duration value in `[[HH:]MM:]SS` format
+fn parse_seconds(value: &str, arg: &str) -> Result<f64, Error> {
+    crate::queue::parse_duration(value).ok_or(Error::IncorrectProtocol(arg.to_string()))
+}
+
+/// Parse comma-separated tags, e.g. `work,conference`
+fn parse_tags(value: &str, arg: &str) -> Result<Vec<String>, Error> {
+    let tags: Vec<String> = value.split(',').map(String::from).collect();
+    if tags.iter().any(|v| v.is_empty()) {
+        return Err(Error::IncorrectProtocol(arg.to_string()));
+    }
+    Ok(tags)
+}
+
+/// Parse A-B loop value `A-B`, times in `[[HH:]MM:]SS` format
+fn parse_abloop(value: &str, arg: &str) -> Result<(f64, f64), Error> {
+    let (a, b) = value
+        .split_once('-')
+        .ok_or(Error::IncorrectProtocol(arg.to_string()))?;
+
+    match (
+        crate::queue::parse_duration(a),
+        crate::queue::parse_duration(b),
+    ) {
+        (Some(a), Some(b)) if a < b => Ok((a, b)),
+        _ => Err(Error::IncorrectProtocol(arg.to_string())),
+    }
+}
+
+/// Decode base64 data (URL-safe) and return `String`
+fn decode_txt(data: &str) -> Result<String, Error> {
+    Ok(String::from_utf8(base64::Engine::decode(
+        &base64::prelude::BASE64_URL_SAFE_NO_PAD,
+        data,
+    )?)?)
+}
+
+/// Decode base64 data (URL-safe) and check URL protocol
+///
+/// Allowed protocols:
+///
+/// ```text
+/// "http", "https", "ftp", "ftps", "rtmp", "rtmps",
+/// "rtmpe", "rtmpt", "rtmpts", "rtmpte", "data"
+/// ```
+fn decode_url(data: &str) -> Result<String, Error> {
+    let url = decode_txt(data)?;
+    check_url(&url)?;
+    Ok(url)
+}
+
+/// Check the protocol of a URL received from elsewhere, allowing the same
+/// protocols as `decode_url`
+pub fn check_url(url: &str) -> Result<(), Error> {
+    match url.find("://") {
+        Some(s) if SAFE_PROTOS.contains(&&url[..s]) => Ok(()),
+        Some(s) => Err(Error::DangerousVideoProtocol(url[..s].to_string())),
+        None => Err(Error::IncorrectVideoURL(url.to_string())),
+    }
+}
+
+#[test]
+fn test_protocol_parse() {
+    // All parameters
+    let proto =
+        Protocol::parse("mpv://play/aHR0cHM6Ly93d3cueW91dHViZS5jb20vd2F0Y2g_dj1HZ2tuMmY1ZS1JVQ/?cookies=www.youtube.com.txt&profile=low-latency&quality=1080p&v_codec=av01&v_title=VGl0bGU&subfile=aHR0cDovL2V4YW1wbGUuY29tL2VuLmFzcw&startat=233&enqueue=true&items=5&abloop=90-2:00&pickformat=1&datasaver=1&dvr=1&sort=-duration&filter=ISNzaG9ydHM&min_duration=60&max_duration=1:00:00&title_from=eXRkbHA6JShhcnRpc3QpcyAtICUodHJhY2spcw&tag=work,conference&new=1&screen=1&subs=en&pickitems=1&playnext=1&split=chapters&audioonly=1&instance=music").unwrap();
+
+    assert_eq!(proto.scheme, Schemes::Mpv);
+    assert_eq!(proto.plugin, Plugins::Play);
+    assert_eq!(proto.url, "https://www.youtube.com/watch?v=Ggkn2f5e-IU");
+    assert_eq!(proto.cookies, Some("www.youtube.com.txt"));
+    assert_eq!(proto.profile, Some("low-latency"));
+    assert_eq!(proto.quality, Some("1080p"));
+    assert_eq!(proto.v_codec, Some("av01"));
+    assert_eq!(proto.v_title, Some("Title".to_string()));
+    assert_eq!(proto.subfile, Some("http://example.com/en.ass".to_string()));
+    assert_eq!(proto.startat, Some("233"));
+    assert_eq!(proto.enqueue, Some(true));
+    assert_eq!(proto.items, Some(5));
+    assert_eq!(proto.abloop, Some((90.0, 120.0)));
+    assert_eq!(proto.pickformat, Some(true));
+    assert_eq!(proto.datasaver, Some(true));
+    assert_eq!(proto.dvr, Some(true));
+    assert_eq!(proto.sort, Some("-duration".parse().unwrap()));
+    assert_eq!(proto.filter, Some("!#shorts".to_string()));
+    assert_eq!(proto.min_duration, Some(60.0));
+    assert_eq!(proto.max_duration, Some(3600.0));
+    assert_eq!(
+        proto.title_from,
+        Some("ytdlp:%(artist)s - %(track)s".to_string())
+    );
+    assert_eq!(
+        proto.tags,
+        Some(vec!["work".to_string(), "conference".to_string()])
+    );
+    assert_eq!(proto.new_instance, Some(true));
+    assert_eq!(proto.screen, Some(1));
+    assert_eq!(proto.subs, Some("en"));
+    assert_eq!(proto.pickitems, Some(true));
+    assert_eq!(proto.play_next, Some(true));
+    assert_eq!(proto.split, Some(Split::Chapters));
+    assert_eq!(proto.audio_only, Some(true));
+    assert_eq!(proto.instance, Some("music"));
+
+    let proto = Protocol::parse("mpv-debug://handler-test/").unwrap();
+    assert_eq!(proto.plugin, Plugins::HandlerTest);
+
+    // Incorrect parameter value
+    assert!(
+        Protocol::parse(
+            "mpv://play/aHR0cHM6Ly93d3cueW91dHViZS5jb20vd2F0Y2g_dj1HZ2tuMmY1ZS1JVQ/?items=all"
+        )
+        .is_err()
+    );
+    assert!(
+        Protocol::parse(
+            "mpv://play/aHR0cHM6Ly93d3cueW91dHViZS5jb20vd2F0Y2g_dj1HZ2tuMmY1ZS1JVQ/?abloop=120-90"
+        )
+        .is_err()
+    );
+    // Invalid regex `(`
+    assert!(
+        Protocol::parse(
+            "mpv://play/aHR0cHM6Ly93d3cueW91dHViZS5jb20vd2F0Y2g_dj1HZ2tuMmY1ZS1JVQ/?filter=KA"
+        )
+        .is_err()
+    );
+
+    // No parameter and last slash
+    let proto =
+        Protocol::parse("mpv://play/aHR0cHM6Ly93d3cueW91dHViZS5jb20vd2F0Y2g_dj1HZ2tuMmY1ZS1JVQ")
+            .unwrap();
+
+    assert_eq!(proto.scheme, Schemes::Mpv);
+    assert_eq!(proto.plugin, Plugins::Play);
+    assert_eq!(proto.url, "https://www.youtube.com/watch?v=Ggkn2f5e-IU");
+
+    // No parameter and protocol `mpv`
+    let proto =
+        Protocol::parse("mpv://play/aHR0cHM6Ly93d3cueW91dHViZS5jb20vd2F0Y2g_dj1HZ2tuMmY1ZS1JVQ/")
+            .unwrap();
+
+    assert_eq!(proto.scheme, Schemes::Mpv);
+    assert_eq!(proto.plugin, Plugins::Play);
+    assert_eq!(proto.url, "https://www.youtube.com/watch?v=Ggkn2f5e-IU");
+
+    // No parameter and protocol `mpv-debug`
+    let proto = Protocol::parse(
+        "mpv-debug://play/aHR0cHM6Ly93d3cueW91dHViZS5jb20vd2F0Y2g_dj1HZ2tuMmY1ZS1JVQ",
+    )
+    .unwrap();
+
+    assert_eq!(proto.scheme, Schemes::MpvDebug);
+    assert_eq!(proto.plugin, Plugins::Play);
+    assert_eq!(proto.url, "https://www.youtube.com/watch?v=Ggkn2f5e-IU");
+
+    // Plugin `feed`
+    let proto = Protocol::parse("mpv://feed/aHR0cHM6Ly9leGFtcGxlLmNvbS9mZWVkLnhtbA/").unwrap();
+
+    assert_eq!(proto.plugin, Plugins::Feed);
+    assert_eq!(proto.url, "https://example.com/feed.xml");
+}
+
+#[test]
+fn test_repeated_link() {
+    let dir = std::env::temp_dir().join(format!("mpv-handler-test-links-{}", std::process::id()));
+    let window = std::time::Duration::from_secs(5);
+    let link = "mpv://play/aHR0cHM6Ly9leGFtcGxlLmNvbS8/";
+
+    assert!(!repeated_in(&dir, link, window));
+    assert!(repeated_in(&dir, link, window));
+    assert!(!repeated_in(&dir, &format!("{link}?enqueue=1"), window));
+
+    // Markers older than the window are removed, so the link is new again
+    assert!(!repeated_in(
+        &dir,
+        "mpv://play/other/",
+        std::time::Duration::ZERO
+    ));
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    assert!(!repeated_in(&dir, link, std::time::Duration::ZERO));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_check_url() {
+    assert!(check_url("https://www.youtube.com/watch?v=Ggkn2f5e-IU").is_ok());
+    assert!(check_url("file:///etc/passwd").is_err());
+    assert!(check_url("av://lavfi:sine").is_err());
+    assert!(check_url("edl://%10%https://example.com").is_err());
+    assert!(check_url("/home/user/video.mkv").is_err());
+}
@@ -0,0 +1,432 @@
+use crate::config::Config;
+use crate::error::Error;
+use crate::mpv_ipc::Stream;
+use chrono::{Local, TimeDelta};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Number of enqueued items remembered in the queue state
+const QUEUE_STATE_ITEMS: usize = 1000;
+
+/// Items enqueued by the handler, used to look up metadata which mpv
+/// doesn't know about (e.g. durations of not yet played items)
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct QueueState {
+    pub items: Vec<QueueItem>,
+}
+
+/// Item enqueued by the handler
+///
+/// - `url`: original URL of the item
+/// - `filename`: URL loaded by mpv, may be a prefetched direct URL
+/// - `profile`: mpv profile of the link which enqueued the item
+/// - `tags`: labels of the link which enqueued the item
+/// - `resolved`: Unix time the direct URL of `filename` was resolved, if
+///   prefetched
+/// - `format`: yt-dlp format selector the direct URL was resolved by
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct QueueItem {
+    pub title: String,
+    pub url: String,
+    pub filename: String,
+    pub duration: Option<f64>,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub resolved: Option<i64>,
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+/// Queue of the mpv instance at the last enqueue, reloaded by
+/// `mpv-handler restore` after a crash or reboot
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct SavedQueue {
+    /// Save time as Unix timestamp
+    pub time: i64,
+    /// Position of the playing item, if any
+    pub current: Option<usize>,
+    pub items: Vec<SavedItem>,
+}
+
+/// Item of a saved queue
+///
+/// - `url`: original URL of the item
+/// - `position`: index in the playlist, starting at 0
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SavedItem {
+    pub title: String,
+    pub url: String,
+    pub position: usize,
+}
+
+impl SavedQueue {
+    /// Load saved queue, returns empty queue if it doesn't exist or is broken
+    pub fn load() -> SavedQueue {
+        saved_queue_path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        if let Some(path) = saved_queue_path() {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the queue of the mpv playlist, with the original URLs and
+    /// titles of enqueued items
+    fn from_playlist(playlist: &serde_json::Value, state: &QueueState) -> SavedQueue {
+        let entries = playlist.as_array().map_or(&[][..], Vec::as_slice);
+        let items = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(position, v)| {
+                let filename = v["filename"].as_str()?;
+                let item = state.find(filename);
+                let title = v["title"]
+                    .as_str()
+                    .or(item.map(|v| v.title.as_str()))
+                    .unwrap_or(filename);
+                Some(SavedItem {
+                    title: title.to_string(),
+                    url: item.map_or(filename, |v| &v.url).to_string(),
+                    position,
+                })
+            })
+            .collect();
+
+        SavedQueue {
+            time: Local::now().timestamp(),
+            current: entries.iter().position(|v| v["current"] == true),
+            items,
+        }
+    }
+}
+
+/// Save the current queue of the mpv instance for `mpv-handler restore`
+pub fn save_queue(stream: &Stream) {
+    let Some(playlist) = crate::mpv_ipc::get_property(stream, "playlist") else {
+        return;
+    };
+    let saved = SavedQueue::from_playlist(&playlist, &QueueState::load());
+    if let Err(e) = saved.save() {
+        log::warn!("Failed to save queue: {}", e);
+    }
+}
+
+impl QueueState {
+    /// Load queue state, returns empty state if it doesn't exist or is broken
+    pub fn load() -> QueueState {
+        queue_path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        if let Some(path) = queue_path() {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, serde_json::to_string(self)?)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the item loaded by mpv as `filename`
+    pub fn find(&self, filename: &str) -> Option<&QueueItem> {
+        self.items.iter().rev().find(|v| v.filename == filename)
+    }
+}
+
+/// Append items to the queue state
+pub fn record(items: &[QueueItem]) {
+    let mut state = QueueState::load();
+    state.items.extend_from_slice(items);
+
+    let len = state.items.len();
+    if len > QUEUE_STATE_ITEMS {
+        state.items.drain(..len - QUEUE_STATE_ITEMS);
+    }
+
+    if let Err(e) = state.save() {
+        log::warn!("Failed to save queue state: {}", e);
+    }
+}
+
+/// Returns the original URLs queued by the mpv instances of the other
+/// profiles, by profile name
+///
+/// Only sockets with `{profile}` give each profile its own instance. The
+/// instances of `default` and the profiles of `Config.profiles` are checked.
+pub fn other_queues(config: &Config, profile: Option<&str>) -> Vec<(String, Vec<String>)> {
+    let template = config.socket.as_deref().unwrap_or_default();
+    if !template.contains("{profile}") {
+        return Vec::new();
+    }
+
+    let current = profile.unwrap_or("default");
+    let mut names: Vec<&str> = config
+        .profiles
+        .iter()
+        .flat_map(|v| v.keys().map(String::as_str))
+        .collect();
+    names.push("default");
+    names.sort_unstable();
+    names.dedup();
+
+    let state = QueueState::load();
+    names
+        .into_iter()
+        .filter(|v| *v != current)
+        .filter_map(|name| {
+            let socket = config.socket(Some(name))?;
+            crate::socket::check(&socket).ok()?;
+            let stream = crate::mpv_ipc::open(&socket).ok()?;
+            let playlist = crate::mpv_ipc::get_property(&stream, "playlist")?;
+            Some((name.to_string(), queued_urls(&playlist, &state)))
+        })
+        .collect()
+}
+
+/// Returns the original URLs of the playlist of mpv
+pub fn queued(stream: &Stream) -> Vec<String> {
+    crate::mpv_ipc::get_property(stream, "playlist")
+        .map(|v| queued_urls(&v, &QueueState::load()))
+        .unwrap_or_default()
+}
+
+/// Returns the original URLs of the playlist entries, since mpv may have
+/// loaded prefetched direct URLs
+fn queued_urls(playlist: &serde_json::Value, state: &QueueState) -> Vec<String> {
+    playlist
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v["filename"].as_str())
+        .map(|v| state.find(v).map_or(v, |item| &item.url).to_string())
+        .collect()
+}
+
+/// Returns the summary of an enqueue
+///
+/// e.g. `Enqueued 3 items (25m). Queue now 2h 47m long, ends ~23:40`
+///
+/// The remaining queue time is the remaining time of the current item and
+/// the known durations of the following items.
+pub fn summary(stream: &Stream, added: &[QueueItem]) -> String {
+    let added_duration: f64 = added.iter().filter_map(|v| v.duration).sum();
+    let mut summary = match added.len() {
+        1 => format!("Enqueued \"{}\"", added[0].title),
+        n => format!("Enqueued {} items", n),
+    };
+    if added_duration > 0.0 {
+        summary.push_str(&format!(" ({})", format_duration(added_duration)));
+    }
+
+    if let Some(remaining) = remaining_time(stream) {
+        let ends = Local::now() + TimeDelta::seconds(remaining as i64);
+        summary.push_str(&format!(
+            ". Queue now {} long, ends ~{}",
+            format_duration(remaining),
+            ends.format("%H:%M")
+        ));
+    }
+
+    summary
+}
+
+/// Returns the remaining time of the queue in seconds by IPC
+fn remaining_time(stream: &Stream) -> Option<f64> {
+    use crate::mpv_ipc::get_property;
+
+    let playlist = get_property(stream, "playlist")?;
+    let playlist = playlist.as_array()?;
+    let pos = playlist.iter().position(|v| v["current"] == true);
+
+    let current = get_property(stream, "playtime-remaining")
+        .and_then(|v| v.as_f64())
+        .unwrap_or(0.0);
+
+    let state = QueueState::load();
+    let later: f64 = playlist
+        .iter()
+        .skip(pos.map_or(0, |v| v + 1))
+        .filter_map(|v| v["filename"].as_str())
+        .filter_map(|v| state.find(v)?.duration)
+        .sum();
+
+    let remaining = current + later;
+    (remaining > 0.0).then_some(remaining)
+}
+
+/// Returns the number of items from the current one and their known
+/// remaining time in seconds by IPC
+pub fn pending(stream: &Stream) -> (usize, f64) {
+    let count = crate::mpv_ipc::get_property(stream, "playlist")
+        .and_then(|v| {
+            let playlist = v.as_array()?;
+            let pos = playlist.iter().position(|v| v["current"] == true);
+            Some(playlist.len() - pos.unwrap_or(0))
+        })
+        .unwrap_or(0);
+    (count, remaining_time(stream).unwrap_or(0.0))
+}
+
+/// Returns how many of the new items with the given durations fit
+/// `Config.max_queue_length` and `Config.max_queue_hours`, behind the
+/// `pending` items
+///
+/// Items of unknown duration don't count towards the hours.
+pub fn fitting(config: &Config, pending: (usize, f64), durations: &[Option<f64>]) -> usize {
+    let (count, mut seconds) = pending;
+    let max_length = config.max_queue_length.unwrap_or(usize::MAX);
+    let max_seconds = config.max_queue_hours.map_or(f64::MAX, |v| v * 3600.0);
+
+    let mut fitting = 0;
+    for duration in durations {
+        seconds += duration.unwrap_or(0.0);
+        if count + fitting >= max_length || seconds > max_seconds {
+            break;
+        }
+        fitting += 1;
+    }
+    fitting
+}
+
+/// Parse duration in `[[HH:]MM:]SS` format and returns seconds
+pub fn parse_duration(value: &str) -> Option<f64> {
+    value.trim().split(':').try_fold(0.0, |acc, v| {
+        v.parse::<f64>()
+            .ok()
+            .filter(|v| *v >= 0.0)
+            .map(|v| acc * 60.0 + v)
+    })
+}
+
+/// Format seconds as `2h 47m`, `12m` or `45s`
+pub fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    match (seconds / 3600, seconds % 3600 / 60) {
+        (0, 0) => format!("{}s", seconds),
+        (0, m) => format!("{}m", m),
+        (h, m) => format!("{}h {}m", h, m),
+    }
+}
+
+fn saved_queue_path() -> Option<PathBuf> {
+    let mut path = crate::config::get_state_dir()?;
+    path.push("saved-queue.json");
+    Some(path)
+}
+
+fn queue_path() -> Option<PathBuf> {
+    let mut path = crate::config::get_state_dir()?;
+    path.push("queue.json");
+    Some(path)
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("45"), Some(45.0));
+    assert_eq!(parse_duration("3:45"), Some(225.0));
+    assert_eq!(parse_duration("1:02:03"), Some(3723.0));
+    assert_eq!(parse_duration("NA"), None);
+    assert_eq!(parse_duration(""), None);
+}
+
+#[test]
+fn test_queued_urls() {
+    let state = QueueState {
+        items: vec![QueueItem {
+            title: "Talk".to_string(),
+            url: "https://www.youtube.com/watch?v=Ggkn2f5e-IU".to_string(),
+            filename: "https://example.com/video.mp4".to_string(),
+            duration: None,
+            profile: Some("music".to_string()),
+            tags: Vec::new(),
+            resolved: None,
+            format: None,
+        }],
+    };
+    let playlist = serde_json::json!([
+        { "filename": "https://example.com/video.mp4" },
+        { "filename": "/music/a.flac" },
+    ]);
+    assert_eq!(
+        queued_urls(&playlist, &state),
+        [
+            "https://www.youtube.com/watch?v=Ggkn2f5e-IU",
+            "/music/a.flac"
+        ]
+    );
+}
+
+#[test]
+fn test_saved_queue() {
+    let state = QueueState {
+        items: vec![QueueItem {
+            title: "Talk".to_string(),
+            url: "https://www.youtube.com/watch?v=Ggkn2f5e-IU".to_string(),
+            filename: "https://example.com/video.mp4".to_string(),
+            duration: None,
+            profile: None,
+            tags: Vec::new(),
+            resolved: None,
+            format: None,
+        }],
+    };
+    let playlist = serde_json::json!([
+        { "filename": "/music/a.flac", "title": "A" },
+        { "filename": "https://example.com/video.mp4", "current": true },
+    ]);
+
+    let saved = SavedQueue::from_playlist(&playlist, &state);
+    assert_eq!(saved.current, Some(1));
+    assert_eq!(
+        saved.items,
+        [
+            SavedItem {
+                title: "A".to_string(),
+                url: "/music/a.flac".to_string(),
+                position: 0,
+            },
+            SavedItem {
+                title: "Talk".to_string(),
+                url: "https://www.youtube.com/watch?v=Ggkn2f5e-IU".to_string(),
+                position: 1,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_queue_fitting() {
+    let config = Config {
+        max_queue_length: Some(5),
+        max_queue_hours: Some(1.0),
+        ..Default::default()
+    };
+    let durations = [Some(600.0); 10];
+    assert_eq!(fitting(&config, (0, 0.0), &durations), 5);
+    assert_eq!(fitting(&config, (3, 0.0), &durations), 2);
+    assert_eq!(fitting(&config, (0, 3000.0), &durations), 1);
+    assert_eq!(fitting(&config, (5, 0.0), &durations), 0);
+    assert_eq!(fitting(&config, (0, 0.0), &[None; 3]), 3);
+    assert_eq!(fitting(&Config::default(), (100, 1e6), &durations), 10);
+}
+
+#[test]
+fn test_format_duration() {
+    assert_eq!(format_duration(45.0), "45s");
+    assert_eq!(format_duration(720.0), "12m");
+    assert_eq!(format_duration(10020.0), "2h 47m");
+}
@@ -0,0 +1,190 @@
+use crate::config::Config;
+use crate::mpv_ipc::Connection;
+use crate::observer::{Listener, Playback};
+use crate::queue::{QueueItem, QueueState};
+
+/// Default minutes after which prefetched direct URLs are resolved again,
+/// direct URLs of YouTube expire after about 6 hours
+const DEFAULT_REFRESH_AFTER: u64 = 300;
+
+/// Times an item waits for the cache before it's downgraded
+const DOWNGRADE_STALLS: usize = 3;
+
+/// Resolves the expiring direct URL of the next item again, before it is
+/// played, unless disabled by `Config.refresh_after = 0`
+pub struct Refresher {
+    config: Config,
+}
+
+impl Refresher {
+    pub fn new(config: Config) -> Refresher {
+        Refresher { config }
+    }
+}
+
+impl Listener for Refresher {
+    fn now_playing(&mut self, playback: &Playback) {
+        let minutes = self.config.refresh_after.unwrap_or(DEFAULT_REFRESH_AFTER);
+        let Some(next) = playback.pos.map(|v| v as usize + 1) else {
+            return;
+        };
+        let Some(socket_path) = self.config.socket(None) else {
+            return;
+        };
+
+        // Resolving takes a while, so it doesn't hold up the other listeners
+        let config = self.config.clone();
+        let duration = playback.duration.unwrap_or_default();
+        std::thread::spawn(move || {
+            if crate::socket::check(&socket_path).is_err() {
+                return;
+            }
+            let Ok(mut conn) = Connection::connect(&socket_path) else {
+                return;
+            };
+            let Some(playlist) = crate::mpv_ipc::get_property(&conn.stream, "playlist") else {
+                return;
+            };
+            let Some(filename) = playlist[next]["filename"].as_str() else {
+                return;
+            };
+            let state = QueueState::load();
+            let Some(item) = state.find(filename) else {
+                return;
+            };
+
+            // The next item starts once the playing one finished
+            let starts = chrono::Local::now().timestamp() + duration as i64;
+            if !expires(item, starts, minutes) {
+                return;
+            }
+            log::info!("Refreshing the expiring direct URL of: {}", item.title);
+            match crate::plugins::play::refresh_item(&config, &mut conn, next, item) {
+                Ok(Some(item)) => crate::queue::record(&[item]),
+                Ok(None) => log::warn!("Failed to refresh: {}", item.title),
+                Err(e) => log::warn!("Failed to refresh '{}': {}", item.title, e),
+            }
+        });
+    }
+}
+
+/// Resolves the playing item again below its resolution once it repeatedly
+/// waited for the cache, enabled by `Config.downgrade_on_buffering = true`
+///
+/// Only prefetched items are downgraded, playback continues at the same
+/// position.
+pub struct Downgrader {
+    config: Config,
+    stalls: usize,
+}
+
+impl Downgrader {
+    pub fn new(config: Config) -> Downgrader {
+        Downgrader { config, stalls: 0 }
+    }
+
+    /// Count a stall of the playing item, returns `true` if it's downgraded
+    fn stalled(&mut self) -> bool {
+        self.stalls += 1;
+        self.stalls == DOWNGRADE_STALLS
+    }
+}
+
+impl Listener for Downgrader {
+    fn now_playing(&mut self, _playback: &Playback) {
+        self.stalls = 0;
+    }
+
+    fn buffering(&mut self, playback: &Playback) {
+        if !self.stalled() {
+            return;
+        }
+        let Some(index) = playback.pos.map(|v| v as usize) else {
+            return;
+        };
+        let Some(socket_path) = self.config.socket(None) else {
+            return;
+        };
+
+        let config = self.config.clone();
+        std::thread::spawn(move || {
+            if crate::socket::check(&socket_path).is_err() {
+                return;
+            }
+            let Ok(mut conn) = Connection::connect(&socket_path) else {
+                return;
+            };
+            let get = |name| crate::mpv_ipc::get_property(&conn.stream, name);
+            let (Some(playlist), Some(height), Some(position)) = (
+                get("playlist"),
+                get("height").and_then(|v| v.as_u64()),
+                get("time-pos").and_then(|v| v.as_f64()),
+            ) else {
+                return;
+            };
+            let Some(filename) = playlist[index]["filename"].as_str() else {
+                return;
+            };
+            let state = QueueState::load();
+            let Some(item) = state.find(filename).filter(|v| v.resolved.is_some()) else {
+                return;
+            };
+
+            log::info!(
+                "Downgrading the buffering item below {}p: {}",
+                height,
+                item.title
+            );
+            match crate::plugins::play::downgrade_item(
+                &config, &mut conn, index, item, height, position,
+            ) {
+                Ok(Some(item)) => crate::queue::record(&[item]),
+                Ok(None) => log::warn!("Failed to downgrade: {}", item.title),
+                Err(e) => log::warn!("Failed to downgrade '{}': {}", item.title, e),
+            }
+        });
+    }
+}
+
+/// Returns `true` if the prefetched URL of the item is older than `minutes`
+/// at `starts` (Unix time)
+fn expires(item: &QueueItem, starts: i64, minutes: u64) -> bool {
+    minutes > 0
+        && item
+            .resolved
+            .is_some_and(|v| starts - v >= (minutes * 60) as i64)
+}
+
+#[test]
+fn test_refresh_expires() {
+    let mut item = QueueItem {
+        title: "Title".to_string(),
+        url: "https://www.youtube.com/watch?v=Ggkn2f5e-IU".to_string(),
+        filename: "https://example.com/video.mp4".to_string(),
+        duration: None,
+        profile: None,
+        tags: Vec::new(),
+        resolved: Some(0),
+        format: None,
+    };
+    assert!(expires(&item, 300 * 60, 300));
+    assert!(!expires(&item, 299 * 60, 300));
+    assert!(!expires(&item, 300 * 60, 0));
+
+    // Items loaded by their original URL never expire
+    item.resolved = None;
+    assert!(!expires(&item, 300 * 60, 300));
+}
+
+#[test]
+fn test_downgrade_stalls() {
+    let mut downgrader = Downgrader::new(Config::default());
+    assert!(!downgrader.stalled());
+    assert!(!downgrader.stalled());
+    assert!(downgrader.stalled());
+    // Downgraded once per item
+    assert!(!downgrader.stalled());
+
+    downgrader.now_playing(&Playback::default());
+    assert!(!downgrader.stalled());
+}
@@ -0,0 +1,326 @@
+use crate::config::Config;
+use crate::error::Error;
+use crate::plugins::play::Entry;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Reasons of failures, matched against the error messages of yt-dlp
+const FAILURE_REASONS: [(&str, &[&str]); 5] = [
+    (
+        "age-restricted",
+        &["confirm your age", "age-restricted", "age restricted"],
+    ),
+    (
+        "geo-blocked",
+        &[
+            "available in your country",
+            "geo restriction",
+            "geo-restricted",
+        ],
+    ),
+    (
+        "private",
+        &["Private video", "members-only", "Join this channel"],
+    ),
+    (
+        "unavailable",
+        &[
+            "Video unavailable",
+            "has been removed",
+            "account associated",
+        ],
+    ),
+    (
+        "network",
+        &[
+            "HTTP Error",
+            "timed out",
+            "Unable to download",
+            "Connection",
+            "getaddrinfo",
+        ],
+    ),
+];
+
+/// Items whose extraction failed, retried by `mpv-handler retry`
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RetryList {
+    pub items: Vec<FailedItem>,
+}
+
+/// Item whose extraction failed with `error`
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FailedItem {
+    pub title: String,
+    pub url: String,
+    pub error: String,
+}
+
+impl RetryList {
+    /// Load retry list, returns empty list if it doesn't exist or is broken
+    pub fn load() -> RetryList {
+        retry_path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        if let Some(path) = retry_path() {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, serde_json::to_string(self)?)?;
+        }
+        Ok(())
+    }
+}
+
+impl FailedItem {
+    pub fn entry(&self) -> Entry {
+        Entry {
+            title: self.title.clone(),
+            url: self.url.clone(),
+            duration: None,
+            upload_date: None,
+            range: None,
+        }
+    }
+}
+
+/// Entries of a batch, failed extractions are retried once after the batch
+pub struct Batch {
+    entries: Vec<Entry>,
+    len: usize,
+    next: usize,
+    pub failed: Vec<FailedItem>,
+}
+
+impl Batch {
+    pub fn new(entries: Vec<Entry>) -> Batch {
+        crate::cancel::clear();
+        Batch {
+            len: entries.len(),
+            entries,
+            next: 0,
+            failed: Vec::new(),
+        }
+    }
+
+    /// Retry the entry after the batch, or mark it failed if it was retried
+    ///
+    /// Entries may fail after later ones were taken, e.g. when resolved in
+    /// parallel, so retried entries are looked up instead of counted.
+    pub fn fail(&mut self, entry: Entry, stderr: &str) {
+        if !self.entries[self.len..].contains(&entry) {
+            log::warn!("Failed to extract '{}', retrying later", entry.title);
+            self.entries.push(entry);
+        } else {
+            self.failed.push(FailedItem {
+                title: entry.title,
+                url: entry.url,
+                error: error_message(stderr),
+            });
+        }
+    }
+}
+
+impl Iterator for Batch {
+    type Item = (usize, Entry);
+
+    /// Returns the next entry and its position in the batch
+    ///
+    /// Returns `None` once cancelled by `mpv-handler cancel`.
+    fn next(&mut self) -> Option<(usize, Entry)> {
+        if self.next < self.entries.len() && crate::cancel::is_requested() {
+            log::info!(
+                "Batch cancelled, skipping {} items",
+                self.entries.len() - self.next
+            );
+            self.next = self.entries.len();
+            crate::cancel::honored();
+            return None;
+        }
+
+        let entry = self.entries.get(self.next)?.clone();
+        self.next += 1;
+        Some(((self.next - 1) % self.len + 1, entry))
+    }
+}
+
+/// Add the failed items of a batch to the retry list and report them
+pub fn record(config: &Config, items: &[FailedItem]) {
+    if items.is_empty() {
+        return;
+    }
+
+    let mut list = RetryList::load();
+    list.items
+        .retain(|v| !items.iter().any(|item| item.url == v.url));
+    list.items.extend_from_slice(items);
+
+    if let Err(e) = list.save() {
+        log::warn!("Failed to save retry list: {}", e);
+    }
+
+    report(config, items);
+}
+
+/// Summarize the failed items of a batch in a notification and a question
+/// dialog, which offers to retry them
+fn report(config: &Config, items: &[FailedItem]) {
+    let summary = format!("{} items failed to enqueue", items.len());
+    let details: Vec<String> = items
+        .iter()
+        .map(|v| format!("• {} ({})", v.title, reason(&v.error)))
+        .collect();
+    let details = details.join("\n");
+
+    log::info!("{}:\n{}", summary, details);
+    log::info!("Run `mpv-handler retry` to try them again");
+    crate::notify::send(config, &summary, &details);
+
+    let text = format!("{}:\n\n{}", summary, details);
+    if crate::dialog::choose(config, &text, &["Retry", "Close"]) == Some(0)
+        && let Err(e) = retry_all(config)
+    {
+        log::warn!("Failed to retry: {}", e);
+    }
+}
+
+/// Enqueue the items whose extraction failed, failing items are kept
+pub fn retry_all(config: &Config) -> Result<(), Error> {
+    let mut list = RetryList::load();
+    if list.items.is_empty() {
+        println!("No failed items to retry");
+        return Ok(());
+    }
+
+    // Items failing again are recorded by the batch, so the list is emptied
+    // first
+    let items: Vec<FailedItem> = list.items.drain(..).collect();
+    list.save()?;
+
+    println!("Retrying {} items", items.len());
+    let entries: Vec<Entry> = items.iter().map(FailedItem::entry).collect();
+    let proto = crate::protocol::Protocol::from_url(entries[0].url.clone());
+    let result = crate::plugins::play::play(&proto, config, entries);
+    if result.is_err() {
+        // The batch stopped early, e.g. without a socket, so the items are kept
+        let mut list = RetryList::load();
+        list.items
+            .retain(|v| !items.iter().any(|item| item.url == v.url));
+        list.items.extend(items);
+        list.save()?;
+    }
+    result
+}
+
+/// Returns the reason of a failure, e.g. `geo-blocked`
+fn reason(error: &str) -> &str {
+    FAILURE_REASONS
+        .iter()
+        .find(|(_, patterns)| patterns.iter().any(|v| error.contains(v)))
+        .map_or("extraction failed", |(reason, _)| reason)
+}
+
+/// Returns the last error message printed by yt-dlp
+fn error_message(stderr: &str) -> String {
+    let mut lines = stderr.lines().map(str::trim).filter(|v| !v.is_empty());
+    let error = lines.clone().rfind(|v| v.starts_with("ERROR:"));
+    match error.or_else(|| lines.next_back()) {
+        Some(v) => v.trim_start_matches("ERROR:").trim().to_string(),
+        None => "Unknown error".to_string(),
+    }
+}
+
+fn retry_path() -> Option<PathBuf> {
+    let mut path = crate::config::get_state_dir()?;
+    path.push("retry.json");
+    Some(path)
+}
+
+#[test]
+fn test_batch_retry() {
+    let entry = |v: &str| Entry {
+        title: v.to_string(),
+        url: format!("https://example.com/{v}"),
+        duration: None,
+        upload_date: None,
+        range: None,
+    };
+    let mut batch = Batch::new(vec![entry("a"), entry("b")]);
+
+    assert_eq!(batch.next(), Some((1, entry("a"))));
+    batch.fail(entry("a"), "ERROR: [generic] a: HTTP Error 503\n");
+    assert_eq!(batch.next(), Some((2, entry("b"))));
+    // Retry pass after the batch
+    assert_eq!(batch.next(), Some((1, entry("a"))));
+    batch.fail(
+        entry("a"),
+        "WARNING: slow\nERROR: [generic] a: HTTP Error 503\n",
+    );
+    assert_eq!(batch.next(), None);
+
+    assert_eq!(
+        batch.failed,
+        vec![FailedItem {
+            title: "a".to_string(),
+            url: "https://example.com/a".to_string(),
+            error: "[generic] a: HTTP Error 503".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn test_batch_retry_taken_ahead() {
+    let entry = |v: &str| Entry {
+        title: v.to_string(),
+        url: format!("https://example.com/{v}"),
+        duration: None,
+        upload_date: None,
+        range: None,
+    };
+    let mut batch = Batch::new(vec![entry("a"), entry("b")]);
+
+    // Both taken before the first one fails, like the parallel resolver
+    assert_eq!(batch.next(), Some((1, entry("a"))));
+    assert_eq!(batch.next(), Some((2, entry("b"))));
+    batch.fail(entry("a"), "ERROR: HTTP Error 503\n");
+    assert_eq!(batch.next(), Some((1, entry("a"))));
+    batch.fail(entry("a"), "ERROR: HTTP Error 503\n");
+    assert_eq!(batch.next(), None);
+    assert_eq!(batch.failed.len(), 1);
+}
+
+#[test]
+fn test_failure_reason() {
+    assert_eq!(
+        reason("[youtube] abc: Sign in to confirm your age."),
+        "age-restricted"
+    );
+    assert_eq!(
+        reason("[youtube] abc: The uploader has not made this video available in your country"),
+        "geo-blocked"
+    );
+    assert_eq!(
+        reason("[youtube] abc: This video is not available in your country"),
+        "geo-blocked"
+    );
+    assert_eq!(
+        reason("Unable to download API page: HTTP Error 503"),
+        "network"
+    );
+    assert_eq!(reason("[youtube] abc: Private video"), "private");
+    assert_eq!(reason("Unsupported URL"), "extraction failed");
+}
+
+#[test]
+fn test_error_message() {
+    assert_eq!(
+        error_message("ERROR: Video unavailable\n"),
+        "Video unavailable"
+    );
+    assert_eq!(error_message("Killed\n"), "Killed");
+    assert_eq!(error_message(""), "Unknown error");
+}
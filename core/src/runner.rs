@@ -0,0 +1,186 @@
+use std::process::{Child, Command, ExitStatus, Output};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Runs the external programs of the handler, yt-dlp and mpv
+///
+/// The handler runs them through `Config.runner`, so tests and library users
+/// can replace them, e.g. by `CannedRunner`.
+pub trait CommandRunner: Send + Sync {
+    /// Run the command to completion like `Command::output`
+    ///
+    /// Returns `None` if the command was cancelled by `mpv-handler cancel`.
+    fn output(&self, command: &mut Command) -> std::io::Result<Option<Output>>;
+
+    /// Run the command like `output`, killing it once `timeout` elapsed
+    ///
+    /// Returns a `TimedOut` error if it was killed. Runners without timeouts
+    /// run it like `output`.
+    fn output_timeout(
+        &self,
+        command: &mut Command,
+        _timeout: Duration,
+    ) -> std::io::Result<Option<Output>> {
+        self.output(command)
+    }
+
+    /// Start the command like `Command::spawn`
+    fn spawn(&self, command: &mut Command) -> std::io::Result<Child>;
+}
+
+/// Runs the commands as they are
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn output(&self, command: &mut Command) -> std::io::Result<Option<Output>> {
+        crate::cancel::output(command)
+    }
+
+    fn output_timeout(
+        &self,
+        command: &mut Command,
+        timeout: Duration,
+    ) -> std::io::Result<Option<Output>> {
+        crate::cancel::output_timeout(command, Some(timeout))
+    }
+
+    fn spawn(&self, command: &mut Command) -> std::io::Result<Child> {
+        command.spawn()
+    }
+}
+
+/// Shared `CommandRunner` of `Config.runner`, `SystemRunner` by default
+#[derive(Clone)]
+pub struct Runner(Arc<dyn CommandRunner>);
+
+impl Runner {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Runner {
+        Runner(runner)
+    }
+}
+
+impl Default for Runner {
+    fn default() -> Runner {
+        Runner(Arc::new(SystemRunner))
+    }
+}
+
+impl std::fmt::Debug for Runner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Runner")
+    }
+}
+
+impl std::ops::Deref for Runner {
+    type Target = dyn CommandRunner;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+/// Runner returning canned outputs instead of running the programs, e.g. to
+/// test the playback logic without mpv or yt-dlp installed
+///
+/// Commands are recorded as their program and arguments. The output of a
+/// command is the stdout of the first rule whose argument it has, commands
+/// without a rule fail. Spawned commands run a program exiting right away
+/// instead.
+#[derive(Default)]
+pub struct CannedRunner {
+    rules: Vec<(String, String)>,
+    commands: Mutex<Vec<Vec<String>>>,
+}
+
+impl CannedRunner {
+    pub fn new() -> CannedRunner {
+        CannedRunner::default()
+    }
+
+    /// Reply with `stdout` to commands with the argument `argument`
+    pub fn reply(mut self, argument: &str, stdout: &str) -> CannedRunner {
+        self.rules.push((argument.to_string(), stdout.to_string()));
+        self
+    }
+
+    /// Returns the recorded commands, oldest first
+    pub fn commands(&self) -> Vec<Vec<String>> {
+        self.commands.lock().map(|v| v.clone()).unwrap_or_default()
+    }
+
+    fn record(&self, command: &Command) -> Vec<String> {
+        let args: Vec<String> = std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|v| v.to_string_lossy().to_string())
+            .collect();
+        if let Ok(mut commands) = self.commands.lock() {
+            commands.push(args.clone());
+        }
+        args
+    }
+}
+
+impl CommandRunner for CannedRunner {
+    fn output(&self, command: &mut Command) -> std::io::Result<Option<Output>> {
+        let args = self.record(command);
+        let stdout = self
+            .rules
+            .iter()
+            .find(|(argument, _)| args[1..].contains(argument))
+            .map(|(_, stdout)| stdout.clone());
+
+        Ok(Some(Output {
+            status: exit_status(stdout.is_some()),
+            stdout: stdout.unwrap_or_default().into_bytes(),
+            stderr: Vec::new(),
+        }))
+    }
+
+    fn spawn(&self, command: &mut Command) -> std::io::Result<Child> {
+        self.record(command);
+
+        #[cfg(unix)]
+        let mut exiting = Command::new("true");
+        #[cfg(windows)]
+        let mut exiting = {
+            let mut command = Command::new("cmd");
+            command.arg("/C").arg("exit 0");
+            command
+        };
+        exiting.spawn()
+    }
+}
+
+fn exit_status(success: bool) -> ExitStatus {
+    #[cfg(unix)]
+    let status = std::os::unix::process::ExitStatusExt::from_raw(if success { 0 } else { 1 << 8 });
+    #[cfg(windows)]
+    let status = std::os::windows::process::ExitStatusExt::from_raw(if success { 0 } else { 1 });
+    status
+}
+
+#[test]
+fn test_canned_runner() {
+    let runner = CannedRunner::new().reply("--flat-playlist", "{}\n");
+
+    let output = runner
+        .output(Command::new("yt-dlp").arg("--flat-playlist").arg("URL"))
+        .unwrap()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"{}\n");
+
+    let output = runner
+        .output(Command::new("yt-dlp").arg("--get-url"))
+        .unwrap()
+        .unwrap();
+    assert!(!output.status.success());
+
+    assert_eq!(
+        runner.commands(),
+        vec![
+            vec!["yt-dlp", "--flat-playlist", "URL"],
+            vec!["yt-dlp", "--get-url"],
+        ]
+    );
+}
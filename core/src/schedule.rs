@@ -0,0 +1,162 @@
+use crate::config::Config;
+use crate::error::Error;
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Seconds until a not yet live item is checked again
+pub const RECHECK_SECONDS: i64 = 60;
+
+/// Messages of yt-dlp when a premiere or live event hasn't started yet
+const UPCOMING_ERRORS: [&str; 4] = [
+    "Premieres in",
+    "Premiere will begin",
+    "live event will begin",
+    "will begin in",
+];
+
+/// Not yet live items waiting to be enqueued by `mpv-handler daemon`
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Schedule {
+    pub items: Vec<ScheduledItem>,
+}
+
+/// Item scheduled at `time` (Unix timestamp)
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ScheduledItem {
+    pub title: String,
+    pub url: String,
+    pub time: i64,
+}
+
+impl Schedule {
+    /// Load schedule, returns empty schedule if it doesn't exist or is broken
+    pub fn load() -> Schedule {
+        schedule_path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        if let Some(path) = schedule_path() {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, serde_json::to_string(self)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns `true` if the yt-dlp error is about a not yet live item
+pub fn is_upcoming_error(stderr: &str) -> bool {
+    UPCOMING_ERRORS.iter().any(|v| stderr.contains(v))
+}
+
+/// Returns the item if it is an upcoming premiere or live event
+///
+/// Without a known release time, it is checked again after `RECHECK_SECONDS`.
+pub fn probe(config: &Config, url: &str) -> Option<ScheduledItem> {
+    let mut command = crate::plugins::play::ytdl_command(config);
+    command
+        .arg("--ignore-no-formats-error")
+        .arg("--skip-download")
+        .arg("--print")
+        .arg("%(live_status)s %(release_timestamp)s %(title)s")
+        .arg(url);
+    let output = crate::plugins::play::ytdl_output(config, &mut command).ok()??;
+
+    parse_probe(&String::from_utf8_lossy(&output.stdout), url)
+}
+
+fn parse_probe(stdout: &str, url: &str) -> Option<ScheduledItem> {
+    let mut fields = stdout.trim().splitn(3, ' ');
+    if fields.next()? != "is_upcoming" {
+        return None;
+    }
+
+    let time = fields
+        .next()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| Local::now().timestamp() + RECHECK_SECONDS);
+    let title = fields.next().unwrap_or(url).to_string();
+
+    Some(ScheduledItem {
+        title,
+        url: url.to_string(),
+        time,
+    })
+}
+
+/// Ask the user whether to schedule the item with a question dialog
+///
+/// Returns `true` if the item was scheduled.
+pub fn offer(config: &Config, item: ScheduledItem) -> bool {
+    let start = match Local.timestamp_opt(item.time, 0).single() {
+        Some(v) => v.format("%a %H:%M").to_string(),
+        None => "a later time".to_string(),
+    };
+    let text = format!(
+        "\"{}\" starts at {}.\n\nEnqueue it automatically when it goes live?",
+        item.title, start
+    );
+
+    if crate::dialog::choose(config, &text, &["Schedule", "Skip"]) != Some(0) {
+        log::info!("Not scheduled: {}", item.title);
+        return false;
+    }
+
+    let mut schedule = Schedule::load();
+    schedule.items.retain(|v| v.url != item.url);
+    schedule.items.push(item.clone());
+    if let Err(e) = schedule.save() {
+        log::warn!("Failed to save schedule: {}", e);
+        return false;
+    }
+
+    let body = format!("\"{}\" will be enqueued at {}", item.title, start);
+    println!("Scheduled: {}", body);
+    crate::notify::send(config, "mpv-handler", &body);
+    true
+}
+
+fn schedule_path() -> Option<PathBuf> {
+    let mut path = crate::config::get_state_dir()?;
+    path.push("schedule.json");
+    Some(path)
+}
+
+#[test]
+fn test_upcoming_error() {
+    assert!(is_upcoming_error(
+        "ERROR: [youtube] abc: Premieres in 5 hours"
+    ));
+    assert!(is_upcoming_error(
+        "ERROR: [youtube] abc: This live event will begin in 2 days."
+    ));
+    assert!(!is_upcoming_error(
+        "ERROR: [youtube] abc: Video unavailable"
+    ));
+}
+
+#[test]
+fn test_upcoming_probe() {
+    let url = "https://www.youtube.com/watch?v=abc";
+
+    let item = parse_probe("is_upcoming 1767225600 New Year Premiere\n", url).unwrap();
+    assert_eq!(
+        item,
+        ScheduledItem {
+            title: "New Year Premiere".to_string(),
+            url: url.to_string(),
+            time: 1767225600,
+        }
+    );
+
+    let item = parse_probe("is_upcoming NA Live", url).unwrap();
+    assert!(item.time > Local::now().timestamp());
+
+    assert_eq!(parse_probe("not_live NA Video", url), None);
+    assert_eq!(parse_probe("", url), None);
+}
@@ -0,0 +1,179 @@
+use crate::config::Config;
+use crate::observer::{Listener, Playback};
+use serde_json::json;
+
+const LISTENBRAINZ_URL: &str = "https://api.listenbrainz.org/1/submit-listens";
+const LASTFM_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+
+/// Tracks shorter than this are never scrobbled
+const MIN_TRACK_SECONDS: f64 = 30.0;
+/// Tracks played this long are scrobbled regardless of their duration
+const MAX_PLAY_SECONDS: f64 = 240.0;
+
+/// Scrobbles finished tracks of music profiles, enabled by `Config.scrobble`
+pub struct Scrobbler {
+    config: Config,
+    /// Start time of the playing track (Unix timestamp)
+    started: i64,
+}
+
+impl Scrobbler {
+    pub fn new(config: Config) -> Scrobbler {
+        Scrobbler { config, started: 0 }
+    }
+}
+
+impl Listener for Scrobbler {
+    fn now_playing(&mut self, _playback: &Playback) {
+        self.started = chrono::Utc::now().timestamp();
+    }
+
+    fn finished(&mut self, playback: &Playback, played: f64) {
+        let Some(scrobble) = &self.config.scrobble else {
+            return;
+        };
+
+        // Only items enqueued with a music profile are scrobbled
+        let state = crate::queue::QueueState::load();
+        let profile = playback
+            .path
+            .as_deref()
+            .and_then(|v| state.find(v))
+            .and_then(|v| v.profile.as_deref());
+        if !profile.is_some_and(|v| scrobble.profiles.iter().any(|p| p == v)) {
+            return;
+        }
+
+        if !should_scrobble(playback.duration, played) {
+            return;
+        }
+        let Some((artist, track)) = track(playback) else {
+            return;
+        };
+
+        let timestamp = self.started.to_string();
+        if let Some(token) = &scrobble.listenbrainz_token {
+            let body = json!({
+                "listen_type": "single",
+                "payload": [{
+                    "listened_at": self.started,
+                    "track_metadata": { "artist_name": artist, "track_name": track },
+                }],
+            });
+            let auth = format!("Token {token}");
+            if crate::http::post_json(&self.config, LISTENBRAINZ_URL, &auth, &body) {
+                log::info!("Scrobbled to ListenBrainz: {} - {}", artist, track);
+            } else {
+                log::warn!("Failed to scrobble to ListenBrainz: {} - {}", artist, track);
+            }
+        }
+        if let (Some(key), Some(secret), Some(sk)) = (
+            &scrobble.lastfm_api_key,
+            &scrobble.lastfm_api_secret,
+            &scrobble.lastfm_session_key,
+        ) {
+            let mut params = vec![
+                ("method", "track.scrobble"),
+                ("artist", artist.as_str()),
+                ("track", track.as_str()),
+                ("timestamp", timestamp.as_str()),
+                ("api_key", key.as_str()),
+                ("sk", sk.as_str()),
+            ];
+            let signature = lastfm_signature(&params, secret);
+            params.push(("api_sig", &signature));
+            params.push(("format", "json"));
+            if crate::http::post_form(&self.config, LASTFM_URL, &params) {
+                log::info!("Scrobbled to Last.fm: {} - {}", artist, track);
+            } else {
+                log::warn!("Failed to scrobble to Last.fm: {} - {}", artist, track);
+            }
+        }
+    }
+}
+
+/// Returns `true` if the track was played long enough to be scrobbled
+///
+/// Like Last.fm's rules, tracks longer than 30 seconds are scrobbled after
+/// half of their duration or 4 minutes.
+fn should_scrobble(duration: Option<f64>, played: f64) -> bool {
+    match duration {
+        Some(v) if v <= MIN_TRACK_SECONDS => false,
+        Some(v) => played >= (v / 2.0).min(MAX_PLAY_SECONDS),
+        None => played >= MAX_PLAY_SECONDS,
+    }
+}
+
+/// Returns the artist and track name of the playback
+///
+/// Titles like `Artist - Track` are split, otherwise the artist is the
+/// uploader without the ` - Topic` suffix of YouTube Music channels.
+fn track(playback: &Playback) -> Option<(String, String)> {
+    let title = playback.title.as_deref()?;
+    if let Some((artist, track)) = title.split_once(" - ") {
+        return Some((artist.trim().to_string(), track.trim().to_string()));
+    }
+
+    let uploader = playback.uploader.as_deref()?;
+    let artist = uploader.strip_suffix(" - Topic").unwrap_or(uploader);
+    Some((artist.to_string(), title.to_string()))
+}
+
+/// Returns the `api_sig` of a Last.fm request
+///
+/// MD5 of the parameters sorted by name and concatenated, then the secret.
+fn lastfm_signature(params: &[(&str, &str)], secret: &str) -> String {
+    let mut params = params.to_vec();
+    params.sort();
+    let mut data: String = params.iter().map(|(k, v)| format!("{k}{v}")).collect();
+    data.push_str(secret);
+    format!("{:x}", md5::compute(data))
+}
+
+#[test]
+fn test_should_scrobble() {
+    assert!(!should_scrobble(Some(20.0), 20.0));
+    assert!(should_scrobble(Some(200.0), 100.0));
+    assert!(!should_scrobble(Some(200.0), 99.0));
+    assert!(should_scrobble(Some(3600.0), 240.0));
+    assert!(!should_scrobble(None, 200.0));
+}
+
+#[test]
+fn test_scrobble_track() {
+    let mut playback = Playback {
+        title: Some("Artist - Track".to_string()),
+        uploader: Some("Uploader".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        track(&playback),
+        Some(("Artist".to_string(), "Track".to_string()))
+    );
+
+    playback.title = Some("Track".to_string());
+    playback.uploader = Some("Artist - Topic".to_string());
+    assert_eq!(
+        track(&playback),
+        Some(("Artist".to_string(), "Track".to_string()))
+    );
+
+    playback.uploader = None;
+    assert_eq!(track(&playback), None);
+}
+
+#[test]
+fn test_lastfm_signature() {
+    let params = [
+        ("method", "track.scrobble"),
+        ("artist", "Artist"),
+        ("track", "Track"),
+        ("timestamp", "1700000000"),
+        ("api_key", "key"),
+        ("sk", "session"),
+    ];
+    assert_eq!(
+        lastfm_signature(&params, "secret"),
+        "d53da87476a7c4977fe6264c8fc4795e"
+    );
+}
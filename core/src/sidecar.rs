@@ -0,0 +1,96 @@
+use crate::mpv_ipc::Stream;
+use crate::queue::QueueItem;
+use chrono::Local;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Metadata of an enqueued item, written as `sidecars/<time>.json` into the
+/// temporary session directory of the mpv instance if enabled by `Config.sidecars`
+///
+/// External scripts and overlays can read the queue metadata without
+/// speaking mpv IPC.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Sidecar<'a> {
+    pub title: &'a str,
+    /// Original URL of the item
+    pub url: &'a str,
+    /// URLs loaded by mpv, may be prefetched direct URLs
+    pub video_url: &'a str,
+    pub audio_urls: &'a [String],
+    pub duration: Option<f64>,
+    pub profile: Option<&'a str>,
+    pub tags: &'a [String],
+    /// Enqueue time in RFC 3339 format
+    pub enqueued: String,
+    /// Index in the playlist when enqueued, if known
+    pub position: Option<usize>,
+}
+
+impl Sidecar<'_> {
+    pub fn new<'a>(
+        item: &'a QueueItem,
+        audio_urls: &'a [String],
+        position: Option<usize>,
+    ) -> Sidecar<'a> {
+        Sidecar {
+            title: &item.title,
+            url: &item.url,
+            video_url: &item.filename,
+            audio_urls,
+            duration: item.duration,
+            profile: item.profile.as_deref(),
+            tags: &item.tags,
+            enqueued: Local::now().to_rfc3339(),
+            position,
+        }
+    }
+}
+
+/// Write the sidecar into the session directory of the mpv instance
+///
+/// Failures are only reported, sidecars are best-effort.
+pub fn write(stream: &Stream, sidecar: &Sidecar) {
+    let Some(dir) = session_dir(stream) else {
+        return;
+    };
+    let path = dir.join(format!("{}.json", Local::now().timestamp_micros()));
+
+    let result = std::fs::create_dir_all(&dir)
+        .map_err(crate::error::Error::from)
+        .and_then(|_| Ok(serde_json::to_string_pretty(sidecar)?))
+        .and_then(|data| Ok(std::fs::write(&path, data)?));
+    if let Err(e) = result {
+        log::warn!("Failed to write sidecar {}: {}", path.display(), e);
+    }
+}
+
+/// Returns the sidecar directory of the mpv instance, removed after it exits
+fn session_dir(stream: &Stream) -> Option<PathBuf> {
+    let pid = crate::mpv_ipc::get_property(stream, "pid")?.as_u64()?;
+    let mut path = crate::temp::session_dir(pid.try_into().ok()?)?;
+    path.push("sidecars");
+    Some(path)
+}
+
+#[test]
+fn test_sidecar_json() {
+    let item = QueueItem {
+        title: "Title".to_string(),
+        url: "https://www.youtube.com/watch?v=Ggkn2f5e-IU".to_string(),
+        filename: "https://example.com/video.mp4".to_string(),
+        duration: Some(60.0),
+        profile: None,
+        tags: Vec::new(),
+        resolved: None,
+        format: None,
+    };
+    let audio = ["https://example.com/audio.m4a".to_string()];
+    let sidecar = Sidecar::new(&item, &audio, Some(2));
+    let value = serde_json::to_value(&sidecar).unwrap();
+
+    assert_eq!(value["url"], "https://www.youtube.com/watch?v=Ggkn2f5e-IU");
+    assert_eq!(value["video_url"], "https://example.com/video.mp4");
+    assert_eq!(value["audio_urls"][0], "https://example.com/audio.m4a");
+    assert_eq!(value["position"], 2);
+    assert!(value["enqueued"].as_str().is_some());
+}
@@ -0,0 +1,203 @@
+use crate::config::{Config, QueueBackend};
+use crate::error::Error;
+use crate::plugins::play::Entry;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Default number of items kept by a rolling M3U playlist
+const DEFAULT_M3U_KEEP: usize = 100;
+
+/// Format of the direct URLs pushed to MPD, which can't extract pages itself
+const MPD_FORMAT: &str = "bestaudio/best";
+
+/// Queue receiving the enqueued items instead of the playlist of mpv,
+/// selected by the `queue` of `Config.profiles`
+pub trait Sink {
+    /// Append the entries to the end of the queue
+    fn push(&mut self, entries: &[Entry]) -> Result<(), Error>;
+}
+
+/// Returns the sink of the backend, `None` for the playlist of mpv
+pub fn open<'a>(config: &'a Config, backend: &'a QueueBackend) -> Option<Box<dyn Sink + 'a>> {
+    match backend {
+        QueueBackend::Mpv => None,
+        QueueBackend::Mpd { address } => Some(Box::new(Mpd { config, address })),
+        QueueBackend::M3u { path, keep, .. } => Some(Box::new(M3u {
+            path: Path::new(path),
+            keep: keep.unwrap_or(DEFAULT_M3U_KEEP),
+        })),
+    }
+}
+
+/// Queue of an MPD server, items are added by their direct audio URL
+pub struct Mpd<'a> {
+    config: &'a Config,
+    address: &'a str,
+}
+
+impl Sink for Mpd<'_> {
+    fn push(&mut self, entries: &[Entry]) -> Result<(), Error> {
+        let failed = |e: std::io::Error| Error::SinkFailed(format!("MPD {}: {}", self.address, e));
+        let mut stream = TcpStream::connect(self.address).map_err(failed)?;
+        let mut reader = BufReader::new(stream.try_clone().map_err(failed)?);
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(failed)?;
+        if !line.starts_with("OK MPD") {
+            return Err(Error::SinkFailed(format!("{} is not MPD", self.address)));
+        }
+
+        for entry in entries {
+            let Some(url) = direct_url(self.config, &entry.url) else {
+                log::warn!("Failed to resolve for MPD, skipping: {}", entry.title);
+                continue;
+            };
+            writeln!(stream, "add {}", mpd_quote(&url)).map_err(failed)?;
+            line.clear();
+            reader.read_line(&mut line).map_err(failed)?;
+            if line.starts_with("ACK") {
+                log::warn!("MPD refused '{}': {}", entry.title, line.trim());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the direct URL of the best audio of the page
+fn direct_url(config: &Config, url: &str) -> Option<String> {
+    let mut command = crate::plugins::play::ytdl_command(config);
+    command
+        .arg("--format")
+        .arg(MPD_FORMAT)
+        .arg("--get-url")
+        .arg("--no-playlist")
+        .arg(url);
+    let output = crate::plugins::play::ytdl_output(config, &mut command).ok()??;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let url = stdout.lines().next()?.trim();
+    url.contains("://").then(|| url.to_string())
+}
+
+/// Returns the argument quoted for the protocol of MPD
+fn mpd_quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Rolling M3U playlist file, keeping the latest `keep` items
+///
+/// Items are written by their page URL, for players extracting them by
+/// yt-dlp like mpv and VLC.
+pub struct M3u<'a> {
+    path: &'a Path,
+    keep: usize,
+}
+
+impl Sink for M3u<'_> {
+    fn push(&mut self, entries: &[Entry]) -> Result<(), Error> {
+        let existing = std::fs::read_to_string(self.path).unwrap_or_default();
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(self.path, rolling(&existing, entries, self.keep))?;
+        Ok(())
+    }
+}
+
+/// Returns the M3U playlist with the entries appended to the items of
+/// `existing`, dropping the oldest items beyond `keep`
+fn rolling(existing: &str, entries: &[Entry], keep: usize) -> String {
+    let mut items: Vec<(String, String)> = Vec::new();
+    let mut title = None;
+    for line in existing.lines().map(str::trim).filter(|v| !v.is_empty()) {
+        match line.strip_prefix("#EXTINF:") {
+            Some(info) => title = info.split_once(',').map(|(_, v)| v.to_string()),
+            None if line.starts_with('#') => {}
+            None => items.push((title.take().unwrap_or_default(), line.to_string())),
+        }
+    }
+    items.extend(entries.iter().map(|v| (v.title.clone(), v.url.clone())));
+    let skip = items.len().saturating_sub(keep);
+
+    let mut playlist = String::from("#EXTM3U\n");
+    for (title, url) in items.into_iter().skip(skip) {
+        playlist.push_str(&format!("#EXTINF:-1,{title}\n{url}\n"));
+    }
+    playlist
+}
+
+/// Serve the M3U playlists of `Config.profiles` with a `listen` address
+/// over HTTP, from `mpv-handler daemon`
+pub fn serve(config: &Arc<Config>) {
+    for backend in config.profiles.iter().flat_map(|v| v.values()) {
+        if let Some(QueueBackend::M3u {
+            path,
+            listen: Some(address),
+            ..
+        }) = &backend.queue
+        {
+            let (path, address) = (path.clone(), address.clone());
+            std::thread::spawn(move || serve_m3u(&path, &address));
+        }
+    }
+}
+
+/// Respond to every request with the playlist file
+fn serve_m3u(path: &str, address: &str) {
+    let listener = match TcpListener::bind(address) {
+        Ok(v) => v,
+        Err(e) => {
+            log::warn!("Failed to serve M3U playlist on {}: {}", address, e);
+            return;
+        }
+    };
+    log::info!("Serving M3U playlist {} on {}", path, address);
+
+    for mut stream in listener.incoming().flatten() {
+        // Only the request line and headers are read, the path is ignored
+        let mut request = [0; 4096];
+        if stream.read(&mut request).is_err() {
+            continue;
+        }
+        let body = std::fs::read_to_string(path).unwrap_or_else(|_| "#EXTM3U\n".to_string());
+        let _ = write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: audio/x-mpegurl\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+    }
+}
+
+#[test]
+fn test_mpd_quote() {
+    assert_eq!(
+        mpd_quote("https://example.com/a"),
+        r#""https://example.com/a""#
+    );
+    assert_eq!(mpd_quote(r#"a"b\c"#), r#""a\"b\\c""#);
+}
+
+#[test]
+fn test_rolling_m3u() {
+    let entry = |n: usize| Entry {
+        title: format!("Title {n}"),
+        url: format!("https://example.com/{n}"),
+        duration: None,
+        upload_date: None,
+        range: None,
+    };
+
+    let playlist = rolling("", &[entry(1), entry(2)], 100);
+    assert_eq!(
+        playlist,
+        "#EXTM3U\n#EXTINF:-1,Title 1\nhttps://example.com/1\n#EXTINF:-1,Title 2\nhttps://example.com/2\n"
+    );
+
+    // The oldest items are dropped beyond `keep`
+    let playlist = rolling(&playlist, &[entry(3)], 2);
+    assert_eq!(
+        playlist,
+        "#EXTM3U\n#EXTINF:-1,Title 2\nhttps://example.com/2\n#EXTINF:-1,Title 3\nhttps://example.com/3\n"
+    );
+}
@@ -0,0 +1,282 @@
+use crate::config::Config;
+use crate::error::Error;
+
+/// Check the mpv socket before connecting to it
+///
+/// The socket must be owned by the current user and not be accessible by
+/// others, otherwise anyone could inject commands into the queue. Sockets
+/// readable by the owner's group only are fixed to `0600`.
+pub fn check(path: &str) -> Result<(), Error> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let Ok(meta) = std::fs::symlink_metadata(path) else {
+            return Ok(());
+        };
+        let insecure = |reason: &str| Error::InsecureSocket(path.to_string(), reason.to_string());
+
+        if meta.file_type().is_symlink() {
+            return Err(insecure("is a symlink"));
+        }
+        if meta.uid() != current_uid() {
+            return Err(insecure("owned by another user"));
+        }
+        if meta.mode() & 0o002 != 0 {
+            return Err(insecure("world-writable"));
+        }
+        if meta.mode() & 0o077 != 0 {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = path;
+    }
+
+    Ok(())
+}
+
+/// Prepare the path of a socket which mpv is going to create
+///
+/// Creates the parent directory only accessible by the current user, and
+/// checks an existing socket. Stale sockets are removed.
+pub fn prepare(path: &str) -> Result<(), Error> {
+    if crate::mpv_ipc::is_tcp(path) {
+        return Ok(());
+    }
+    remove_stale(path);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+
+        if let Some(dir) = std::path::Path::new(path).parent()
+            && !dir.as_os_str().is_empty()
+            && !dir.exists()
+        {
+            std::fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(dir)?;
+        }
+    }
+
+    check(path)
+}
+
+/// Directory of the sockets of third-party mpv instances, e.g. `/tmp/mpvsocket`
+#[cfg(unix)]
+const SCAN_DIR: &str = "/tmp";
+/// File name prefix of the scanned sockets of `SCAN_DIR`
+#[cfg(unix)]
+const SCAN_PREFIX: &str = "mpvsocket";
+
+/// Returns the known mpv sockets, which may not be running
+///
+/// The sockets of the config, its profiles and its instances, and on Unix
+/// the `/tmp/mpvsocket*` sockets of scripts and other launchers.
+pub fn known(config: &Config) -> Vec<String> {
+    let mut sockets: Vec<String> = std::iter::once(None)
+        .chain(
+            config
+                .profiles
+                .iter()
+                .flat_map(|v| v.keys().map(|v| Some(v.as_str()))),
+        )
+        .filter_map(|v| config.socket(v))
+        .chain(
+            config
+                .instances
+                .iter()
+                .flat_map(|v| v.values().map(|v| v.socket.clone())),
+        )
+        .collect();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+
+        let files = std::fs::read_dir(SCAN_DIR).into_iter().flatten().flatten();
+        sockets.extend(
+            files
+                .filter(|v| v.file_name().to_string_lossy().starts_with(SCAN_PREFIX))
+                .filter(|v| v.file_type().is_ok_and(|v| v.is_socket()))
+                .map(|v| v.path().display().to_string()),
+        );
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    sockets.retain(|v| seen.insert(v.clone()));
+    sockets
+}
+
+/// Remove the socket if it was left by a crashed mpv, refusing connections
+///
+/// Returns `true` if it was removed. Named pipes vanish with mpv, so only
+/// Unix sockets are checked.
+pub fn remove_stale(path: &str) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+
+        let is_socket = std::fs::symlink_metadata(path).is_ok_and(|v| v.file_type().is_socket());
+        let refused = || {
+            std::os::unix::net::UnixStream::connect(path)
+                .is_err_and(|e| e.kind() == std::io::ErrorKind::ConnectionRefused)
+        };
+        if is_socket && refused() && std::fs::remove_file(path).is_ok() {
+            log::info!("Removed stale mpv socket: {}", path);
+            return true;
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = path;
+    }
+
+    false
+}
+
+/// Remove the stale sockets of the instances forced by the `new` parameter,
+/// named by the queue socket and the process ID, e.g. `/tmp/mpvsocket-1234`
+pub fn remove_stale_instances(socket: &str) {
+    let path = std::path::Path::new(socket);
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return;
+    };
+    let prefix = format!("{}-", name.to_string_lossy());
+    let dir = match dir.as_os_str().is_empty() {
+        true => std::path::Path::new("."),
+        false => dir,
+    };
+    let Ok(files) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for file in files.flatten() {
+        let name = file.file_name().to_string_lossy().into_owned();
+        if let Some(pid) = name.strip_prefix(&prefix)
+            && !pid.is_empty()
+            && pid.bytes().all(|v| v.is_ascii_digit())
+        {
+            remove_stale(&file.path().to_string_lossy());
+        }
+    }
+}
+
+/// Restrict the socket created by a new mpv instance to `0600` once it exists
+///
+/// Gives up after a few seconds, e.g. if mpv failed to start.
+pub fn secure_when_created(path: &str) {
+    #[cfg(unix)]
+    {
+        let path = path.to_string();
+        std::thread::spawn(move || {
+            for _ in 0..50 {
+                if std::path::Path::new(&path).exists() {
+                    if let Err(e) = check(&path) {
+                        log::warn!("{}", e);
+                    }
+                    return;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+        });
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = path;
+    }
+}
+
+#[cfg(unix)]
+pub fn current_uid() -> u32 {
+    // SAFETY: `getuid` has no preconditions and always succeeds
+    unsafe { libc::getuid() }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_socket_check() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut path = std::env::temp_dir();
+    path.push(format!("mpv-handler-test-{}", std::process::id()));
+    let path_str = path.to_str().unwrap();
+
+    // Missing sockets are created by mpv later
+    assert!(check(path_str).is_ok());
+
+    std::fs::write(&path, "").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o666)).unwrap();
+    assert!(check(path_str).is_err());
+
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o660)).unwrap();
+    assert!(check(path_str).is_ok());
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_remove_stale_socket() {
+    use std::os::unix::net::UnixListener;
+
+    let dir = std::env::temp_dir().join(format!("mpv-handler-test-stale-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let socket = dir.join("mpvsocket");
+    let socket_str = socket.to_str().unwrap();
+
+    // Sockets of running instances are kept
+    let listener = UnixListener::bind(&socket).unwrap();
+    assert!(!remove_stale(socket_str));
+    drop(listener);
+    assert!(socket.exists());
+    assert!(remove_stale(socket_str));
+    assert!(!socket.exists());
+
+    // Other files are never removed
+    std::fs::write(&socket, "").unwrap();
+    assert!(!remove_stale(socket_str));
+    std::fs::remove_file(&socket).unwrap();
+
+    drop(UnixListener::bind(dir.join("mpvsocket-1234")).unwrap());
+    drop(UnixListener::bind(dir.join("mpvsocket-live")).unwrap());
+    remove_stale_instances(socket_str);
+    assert!(!dir.join("mpvsocket-1234").exists());
+    assert!(dir.join("mpvsocket-live").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_known_sockets() {
+    let config = Config {
+        socket: Some("/run/mpv-{profile}".to_string()),
+        profiles: Some(std::collections::HashMap::from([(
+            "music".to_string(),
+            crate::config::Profile::default(),
+        )])),
+        instances: Some(std::collections::HashMap::from([(
+            "video".to_string(),
+            crate::config::Instance {
+                socket: "/run/mpv-default".to_string(),
+                profile: None,
+            },
+        )])),
+        ..Default::default()
+    };
+
+    // The socket of the instance is the default socket
+    let sockets = known(&config);
+    assert_eq!(sockets[..2], ["/run/mpv-default", "/run/mpv-music"]);
+    assert_eq!(
+        sockets.iter().filter(|v| *v == "/run/mpv-default").count(),
+        1
+    );
+}
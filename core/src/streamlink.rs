@@ -0,0 +1,61 @@
+use crate::config::Config;
+use std::process::Command;
+
+/// Returns the stream qualities of streamlink for the `quality` parameter,
+/// falling back to the best one, e.g. `720p,720p60,best` for `720p`
+pub fn quality(quality: Option<&str>) -> String {
+    match quality {
+        Some(v) => format!("{v},{v}60,best"),
+        None => "best".to_string(),
+    }
+}
+
+/// Returns `true` if yt-dlp printed `is_live` as true
+pub fn is_live(value: &str) -> bool {
+    value.trim() == "True"
+}
+
+/// Resolve the direct URL of a live stream with streamlink
+///
+/// Returns `None` if streamlink failed, e.g. when it isn't installed.
+pub fn stream_url(config: &Config, url: &str, quality: &str) -> Option<String> {
+    let path = config.streamlink.as_deref().unwrap_or("streamlink");
+    let mut command = Command::new(path);
+    command.arg("--stream-url").arg(url).arg(quality);
+
+    let output = match config.runner.output(&mut command) {
+        Ok(Some(v)) if v.status.success() => v,
+        _ => {
+            log::warn!("streamlink failed, using yt-dlp for: {}", url);
+            return None;
+        }
+    };
+    let stream = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    log::debug!("Resolved live stream by streamlink: {}", stream);
+    stream.contains("://").then_some(stream)
+}
+
+/// Returns the title of the URL if it's a live stream, probed by yt-dlp
+pub fn probe_live(config: &Config, url: &str) -> Option<String> {
+    let mut command = crate::plugins::play::ytdl_command(config);
+    command
+        .arg("--skip-download")
+        .arg("--print")
+        .arg("%(is_live)s %(title)s")
+        .arg(url);
+    let output = crate::plugins::play::ytdl_output(config, &mut command).ok()??;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (live, title) = stdout.trim().split_once(' ')?;
+    is_live(live).then(|| title.to_string())
+}
+
+#[test]
+fn test_streamlink_quality() {
+    assert_eq!(quality(Some("720p")), "720p,720p60,best");
+    assert_eq!(quality(None), "best");
+
+    assert!(is_live("True\n"));
+    assert!(!is_live("False"));
+    assert!(!is_live("NA"));
+}
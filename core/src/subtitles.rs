@@ -0,0 +1,139 @@
+use crate::config::Config;
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Subtitle formats in order of preference, mpv plays all of them
+const FORMATS: [&str; 3] = ["vtt", "srt", "ass"];
+
+/// Subtitle track listed in the metadata of yt-dlp
+#[derive(Debug, PartialEq)]
+pub struct Track {
+    pub title: String,
+    pub url: String,
+    pub ext: String,
+}
+
+/// Returns the uploaded and auto-generated subtitle tracks of the language
+/// from the `--dump-json` metadata of yt-dlp
+///
+/// Regional variants are used if the exact language isn't provided, e.g.
+/// `en-US` for `en`.
+pub fn tracks(info: &Value, lang: &str) -> Vec<Track> {
+    [
+        ("subtitles", "Uploaded"),
+        ("automatic_captions", "Auto-generated"),
+    ]
+    .into_iter()
+    .filter_map(|(field, kind)| {
+        let languages = info.get(field)?.as_object()?;
+        let (code, formats) = languages.get_key_value(lang).or_else(|| {
+            languages
+                .iter()
+                .find(|(code, _)| code.starts_with(&format!("{lang}-")))
+        })?;
+        let formats = formats.as_array()?;
+        let format = FORMATS
+            .iter()
+            .find_map(|ext| formats.iter().find(|v| v["ext"] == *ext))
+            .or(formats.first())?;
+
+        Some(Track {
+            title: format!("{kind} ({code})"),
+            url: format["url"].as_str()?.to_string(),
+            ext: format["ext"].as_str().unwrap_or("vtt").to_string(),
+        })
+    })
+    .collect()
+}
+
+/// Download the uploaded and auto-generated subtitles of the language
+///
+/// The files are named by the track title, which mpv shows as the title of
+/// the external tracks. Returns the downloaded files, empty on failure.
+pub fn fetch(config: &Config, url: &str, lang: &str) -> Vec<PathBuf> {
+    let mut command = crate::plugins::play::ytdl_command(config);
+    command
+        .arg("--skip-download")
+        .arg("--dump-json")
+        .arg("--no-playlist")
+        .arg(url);
+    let info = match crate::plugins::play::ytdl_output(config, &mut command) {
+        Ok(Some(v)) if v.status.success() => serde_json::from_slice(&v.stdout).ok(),
+        _ => None,
+    };
+    let Some(info) = info else {
+        log::warn!("Failed to fetch the subtitles of: {}", url);
+        return Vec::new();
+    };
+
+    let Some(mut dir) = crate::config::get_cache_dir() else {
+        return Vec::new();
+    };
+    dir.push("subtitles");
+    dir.push(format!("{:x}", md5::compute(url)));
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        log::warn!("Failed to create the subtitle directory: {}", e);
+        return Vec::new();
+    }
+
+    let tracks = tracks(&info, lang);
+    if tracks.is_empty() {
+        log::info!("No '{}' subtitles for: {}", lang, url);
+    }
+    tracks
+        .into_iter()
+        .filter_map(|track| {
+            let path = dir.join(format!("{}.{}", track.title, track.ext));
+            let mut command = crate::http::curl(config);
+            command
+                .arg("--fail")
+                .arg("--output")
+                .arg(&path)
+                .arg(&track.url);
+            match config.runner.output(&mut command) {
+                Ok(Some(v)) if v.status.success() => Some(path),
+                _ => {
+                    log::warn!("Failed to download the subtitles: {}", track.title);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_subtitle_tracks() {
+    let info = serde_json::json!({
+        "subtitles": {
+            "en-US": [
+                {"ext": "json3", "url": "https://example.com/up.json3"},
+                {"ext": "srt", "url": "https://example.com/up.srt"},
+            ],
+        },
+        "automatic_captions": {
+            "en": [
+                {"ext": "srv1", "url": "https://example.com/auto.srv1"},
+                {"ext": "vtt", "url": "https://example.com/auto.vtt"},
+            ],
+            "de": [{"ext": "vtt", "url": "https://example.com/de.vtt"}],
+        },
+    });
+
+    assert_eq!(
+        tracks(&info, "en"),
+        vec![
+            Track {
+                title: "Uploaded (en-US)".to_string(),
+                url: "https://example.com/up.srt".to_string(),
+                ext: "srt".to_string(),
+            },
+            Track {
+                title: "Auto-generated (en)".to_string(),
+                url: "https://example.com/auto.vtt".to_string(),
+                ext: "vtt".to_string(),
+            },
+        ]
+    );
+    assert_eq!(tracks(&info, "de").len(), 1);
+    assert!(tracks(&info, "fr").is_empty());
+}
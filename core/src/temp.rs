@@ -0,0 +1,161 @@
+use std::path::PathBuf;
+
+/// Returns the temporary directory of the session of process `pid`,
+/// creating it if needed
+///
+/// Sessions are named by the PID of the process using their files, either
+/// the handler itself or an mpv instance, so files of a session outlive
+/// the handler until their mpv instance exits. They are removed by
+/// `cleanup` on exit, and by `recover` after crashes.
+pub fn session_dir(pid: u32) -> Option<PathBuf> {
+    let mut path = base_dir()?;
+    path.push(pid.to_string());
+    if let Err(e) = std::fs::create_dir_all(&path) {
+        log::warn!(
+            "Failed to create temporary directory {}: {}",
+            path.display(),
+            e
+        );
+        return None;
+    }
+    Some(path)
+}
+
+/// Remove the session directory of the handler, called on exit
+pub fn cleanup() {
+    if let Some(mut path) = base_dir() {
+        path.push(std::process::id().to_string());
+        let _ = std::fs::remove_dir_all(path);
+    }
+}
+
+/// Remove session directories left by processes which no longer run,
+/// e.g. after a crash of the handler or mpv
+pub fn recover() {
+    let Some(entries) = base_dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let stale = entry
+            .file_name()
+            .to_str()
+            .and_then(|v| v.parse::<u32>().ok())
+            .is_some_and(|pid| !is_running(pid));
+        if stale {
+            let _ = std::fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+/// Returns the directory of the sessions, private to the user
+fn base_dir() -> Option<PathBuf> {
+    // Linux: $XDG_RUNTIME_DIR/mpv-handler/, or /tmp/mpv-handler-<uid>/
+    #[cfg(unix)]
+    {
+        match dirs::runtime_dir() {
+            Some(mut v) => {
+                v.push("mpv-handler");
+                Some(v)
+            }
+            None => {
+                let mut v = std::env::temp_dir();
+                v.push(format!("mpv-handler-{}", crate::socket::current_uid()));
+                private_dir(v)
+            }
+        }
+    }
+
+    // Windows: %TEMP%\mpv-handler\
+    #[cfg(windows)]
+    {
+        let mut v = std::env::temp_dir();
+        v.push("mpv-handler");
+        Some(v)
+    }
+}
+
+/// Returns `path` after creating it with mode 0700, or after checking an
+/// existing directory there is a real directory only the user can access,
+/// which `/tmp` doesn't guarantee
+#[cfg(unix)]
+fn private_dir(path: PathBuf) -> Option<PathBuf> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    match std::fs::DirBuilder::new().mode(0o700).create(&path) {
+        Ok(()) => return Some(path),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => {
+            log::warn!(
+                "Failed to create temporary directory {}: {}",
+                path.display(),
+                e
+            );
+            return None;
+        }
+    }
+
+    let meta = std::fs::symlink_metadata(&path).ok()?;
+    let reason = if !meta.file_type().is_dir() {
+        "not a directory"
+    } else if meta.uid() != crate::socket::current_uid() {
+        "owned by another user"
+    } else if meta.mode() & 0o077 != 0 {
+        "accessible by other users"
+    } else {
+        return Some(path);
+    };
+    log::warn!(
+        "Ignored insecure temporary directory {}: {}",
+        path.display(),
+        reason
+    );
+    None
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: signal 0 only checks whether the process exists
+    let result = unsafe { libc::kill(pid, 0) };
+    // EPERM: the process exists, but belongs to another user
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+// Stale sessions are kept on Windows
+#[cfg(windows)]
+fn is_running(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(unix)]
+#[test]
+fn test_is_running() {
+    assert!(is_running(std::process::id()));
+
+    let mut child = std::process::Command::new("true").spawn().unwrap();
+    let pid = child.id();
+    child.wait().unwrap();
+    assert!(!is_running(pid));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_private_dir() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut path = std::env::temp_dir();
+    path.push(format!("mpv-handler-test-dir-{}", std::process::id()));
+
+    assert!(private_dir(path.clone()).is_some());
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o700);
+    assert!(private_dir(path.clone()).is_some());
+
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    assert!(private_dir(path.clone()).is_none());
+
+    std::fs::remove_dir(&path).unwrap();
+}
@@ -0,0 +1,123 @@
+use crate::config::Config;
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Decisions of the trust-on-first-use prompt, enabled by `Config.trust_domains`
+///
+/// Stored in `domains.json` of the state directory, which can be edited to
+/// revoke a decision.
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Domains {
+    #[serde(default)]
+    pub allowed: Vec<String>,
+    #[serde(default)]
+    pub denied: Vec<String>,
+}
+
+/// Answer of the prompt
+#[derive(Debug, PartialEq)]
+enum Decision {
+    Always,
+    Once,
+    Never,
+}
+
+impl Domains {
+    /// Load decisions, returns empty decisions if they don't exist or are broken
+    pub fn load() -> Domains {
+        domains_path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        if let Some(path) = domains_path() {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check the domain of the URL, prompting the first time it is seen
+///
+/// Returns `DomainNotAllowed` error if the domain is denied, or the prompt
+/// is dismissed or can't be shown.
+pub fn check(config: &Config, url: &str) -> Result<(), Error> {
+    if config.trust_domains != Some(true) {
+        return Ok(());
+    }
+    let Some(domain) = domain(url) else {
+        return Ok(());
+    };
+
+    let mut domains = Domains::load();
+    if domains.allowed.contains(&domain) {
+        return Ok(());
+    }
+    if domains.denied.contains(&domain) {
+        return Err(Error::DomainNotAllowed(domain));
+    }
+
+    // Dismissed prompts deny this link only
+    let allowed = match ask(config, &domain) {
+        Some(Decision::Once) => return Ok(()),
+        Some(Decision::Always) => true,
+        Some(Decision::Never) => false,
+        None => return Err(Error::DomainNotAllowed(domain)),
+    };
+    if allowed {
+        domains.allowed.push(domain.clone());
+    } else {
+        domains.denied.push(domain.clone());
+    }
+    if let Err(e) = domains.save() {
+        log::warn!("Failed to save domain decisions: {}", e);
+    }
+
+    if !allowed {
+        return Err(Error::DomainNotAllowed(domain));
+    }
+    Ok(())
+}
+
+/// Returns the domain of the URL without `www.`
+fn domain(url: &str) -> Option<String> {
+    let (host, _) = crate::url::split_url(url)?;
+    let host = host.split(':').next()?.trim_start_matches("www.");
+    Some(host.to_ascii_lowercase())
+}
+
+/// Ask whether to allow the domain with a question dialog
+///
+/// Returns `None` if the dialog is dismissed or can't be shown.
+fn ask(config: &Config, domain: &str) -> Option<Decision> {
+    let text = format!("Allow {domain} to open mpv?");
+    match crate::dialog::choose(config, &text, &["Always", "Never", "Once"])? {
+        0 => Some(Decision::Always),
+        1 => Some(Decision::Never),
+        _ => Some(Decision::Once),
+    }
+}
+
+fn domains_path() -> Option<PathBuf> {
+    let mut path = crate::config::get_state_dir()?;
+    path.push("domains.json");
+    Some(path)
+}
+
+#[test]
+fn test_trust_domain() {
+    assert_eq!(
+        domain("https://www.YouTube.com/watch?v=Ggkn2f5e-IU"),
+        Some("youtube.com".to_string())
+    );
+    assert_eq!(
+        domain("http://example.com:8080/v.mp4"),
+        Some("example.com".to_string())
+    );
+}
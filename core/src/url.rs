@@ -0,0 +1,93 @@
+/// Split URL into host and path
+pub fn split_url(url: &str) -> Option<(&str, &str)> {
+    let rest = &url[url.find("://")? + "://".len()..];
+    let rest = rest.split(['?', '#']).next().unwrap_or(rest);
+
+    match rest.find('/') {
+        Some(i) => Some((&rest[..i], &rest[i..])),
+        None => Some((rest, "/")),
+    }
+}
+
+/// Returns the video ID of a YouTube URL
+pub fn youtube_id(url: &str) -> Option<&str> {
+    let (host, path) = split_url(url)?;
+    let host = host.trim_start_matches("www.").trim_start_matches("m.");
+
+    let id = match host {
+        "youtu.be" => path.trim_start_matches('/'),
+        "youtube.com" | "music.youtube.com" => {
+            if let Some(id) = path
+                .strip_prefix("/shorts/")
+                .or_else(|| path.strip_prefix("/live/"))
+                .or_else(|| path.strip_prefix("/embed/"))
+            {
+                id
+            } else {
+                query_value(url, "v")?
+            }
+        }
+        _ => return None,
+    };
+
+    let id = id.split('/').next()?;
+    (!id.is_empty()).then_some(id)
+}
+
+/// Returns the canonical ID of a video URL, the video ID of YouTube URLs
+/// and the URL without fragment otherwise
+pub fn video_id(url: &str) -> &str {
+    youtube_id(url).unwrap_or_else(|| url.split('#').next().unwrap_or(url))
+}
+
+/// Returns the value of a query parameter
+pub fn query_value<'a>(url: &'a str, key: &str) -> Option<&'a str> {
+    let query = url.split_once('?')?.1;
+    let query = query.split('#').next().unwrap_or(query);
+
+    query
+        .split('&')
+        .filter_map(|v| v.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+}
+
+#[test]
+fn test_split_url() {
+    assert_eq!(
+        split_url("https://www.youtube.com/watch?v=Ggkn2f5e-IU"),
+        Some(("www.youtube.com", "/watch"))
+    );
+    assert_eq!(split_url("https://example.com"), Some(("example.com", "/")));
+    assert_eq!(split_url("example.com/video"), None);
+}
+
+#[test]
+fn test_youtube_id() {
+    assert_eq!(
+        youtube_id("https://www.youtube.com/watch?v=Ggkn2f5e-IU&list=PL"),
+        Some("Ggkn2f5e-IU")
+    );
+    assert_eq!(
+        youtube_id("https://youtu.be/Ggkn2f5e-IU?t=10"),
+        Some("Ggkn2f5e-IU")
+    );
+    assert_eq!(
+        youtube_id("https://www.youtube.com/shorts/Ggkn2f5e-IU"),
+        Some("Ggkn2f5e-IU")
+    );
+    assert_eq!(youtube_id("https://www.youtube.com/@example"), None);
+    assert_eq!(youtube_id("https://example.com/watch?v=Ggkn2f5e-IU"), None);
+}
+
+#[test]
+fn test_video_id() {
+    assert_eq!(
+        video_id("https://www.youtube.com/watch?v=Ggkn2f5e-IU&t=10"),
+        video_id("https://youtu.be/Ggkn2f5e-IU")
+    );
+    assert_eq!(
+        video_id("https://example.com/video.mp4#t=10"),
+        "https://example.com/video.mp4"
+    );
+}
@@ -0,0 +1,74 @@
+/// Warning of yt-dlp which degrades the quality or speed of playback
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Warning {
+    /// The site rate limits the requests
+    Throttled,
+    /// Formats are missing, since the signature couldn't be deciphered
+    Signature,
+    /// Downloads are throttled, since the `n` parameter couldn't be deciphered
+    Nsig,
+}
+
+impl Warning {
+    /// Returns the explanation shown to the user
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Warning::Throttled => "The site is rate limiting requests, try again later",
+            Warning::Signature => {
+                "Signature extraction failed, formats may be missing (update yt-dlp)"
+            }
+            Warning::Nsig => "nsig extraction failed, playback may be throttled (update yt-dlp)",
+        }
+    }
+}
+
+/// Returns the significant warnings in the stderr of yt-dlp, each once
+pub fn classify(stderr: &str) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    for line in stderr.lines().filter(|v| v.starts_with("WARNING:")) {
+        let line = line.to_lowercase();
+        let warning = if line.contains("nsig") || line.contains("n challenge") {
+            Warning::Nsig
+        } else if line.contains("signature") {
+            Warning::Signature
+        } else if line.contains("throttl") || line.contains("rate limit") || line.contains("429") {
+            Warning::Throttled
+        } else {
+            continue;
+        };
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+    warnings
+}
+
+/// Add the warnings to `all`, skipping known ones
+pub fn merge(all: &mut Vec<Warning>, warnings: &[Warning]) {
+    for warning in warnings {
+        if !all.contains(warning) {
+            all.push(*warning);
+        }
+    }
+}
+
+#[test]
+fn test_classify_warnings() {
+    let stderr = "[youtube] Ggkn2f5e-IU: Downloading webpage
+WARNING: [youtube] Ggkn2f5e-IU: nsig extraction failed: Some formats may be missing
+WARNING: [youtube] Ggkn2f5e-IU: Signature extraction failed: Some formats may be missing
+WARNING: [youtube] HTTP Error 429: Too Many Requests
+WARNING: [youtube] nsig extraction failed again
+WARNING: [youtube] Falling back to generic n function search";
+    assert_eq!(
+        classify(stderr),
+        vec![Warning::Nsig, Warning::Signature, Warning::Throttled]
+    );
+    assert!(
+        classify("WARNING: [generic] Falling back on generic information extractor").is_empty()
+    );
+
+    let mut all = vec![Warning::Nsig];
+    merge(&mut all, &[Warning::Nsig, Warning::Throttled]);
+    assert_eq!(all, vec![Warning::Nsig, Warning::Throttled]);
+}
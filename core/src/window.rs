@@ -0,0 +1,104 @@
+use crate::config::Config;
+use std::process::Command;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How long to wait for the window of a new mpv instance
+const WINDOW_TIMEOUT: Duration = Duration::from_secs(10);
+const WINDOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Returns the mpv options opening the window on the monitor `screen`,
+/// also when switched to fullscreen
+pub fn screen_options(screen: u32) -> [String; 2] {
+    [
+        format!("--screen={screen}"),
+        format!("--fs-screen={screen}"),
+    ]
+}
+
+/// Move the window of the new mpv process to `Config.workspace`
+///
+/// Uses `swaymsg` in sway sessions and `wmctrl` otherwise. Waits for the
+/// window in a thread, which must be joined if the handler exits first.
+pub fn place(config: &Config, pid: u32) -> Option<JoinHandle<()>> {
+    let workspace = config.workspace.clone()?;
+
+    Some(std::thread::spawn(move || {
+        let started = Instant::now();
+        while started.elapsed() < WINDOW_TIMEOUT {
+            let moved = match std::env::var_os("SWAYSOCK") {
+                Some(_) => sway_move(pid, &workspace),
+                None => wmctrl_move(pid, &workspace),
+            };
+            match moved {
+                Ok(true) => return,
+                Ok(false) => std::thread::sleep(WINDOW_POLL_INTERVAL),
+                Err(e) => {
+                    log::warn!("Failed to move mpv to workspace {}: {}", workspace, e);
+                    return;
+                }
+            }
+        }
+        log::info!("No mpv window to move to workspace {}", workspace);
+    }))
+}
+
+/// Returns `false` if the window doesn't exist yet
+fn sway_move(pid: u32, workspace: &str) -> std::io::Result<bool> {
+    let status = Command::new("swaymsg")
+        .arg(format!("[pid={pid}]"))
+        .arg("move")
+        .arg("container")
+        .arg("to")
+        .arg("workspace")
+        .arg(workspace)
+        .output()?
+        .status;
+    // Fails while no window matches
+    Ok(status.success())
+}
+
+/// Returns `false` if the window doesn't exist yet
+fn wmctrl_move(pid: u32, workspace: &str) -> std::io::Result<bool> {
+    let output = Command::new("wmctrl").arg("-l").arg("-p").output()?;
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let Some(window) = window_of(&listing, pid) else {
+        return Ok(false);
+    };
+
+    // Desktops of wmctrl are numbers, starting at 0
+    Command::new("wmctrl")
+        .arg("-i")
+        .arg("-r")
+        .arg(window)
+        .arg("-t")
+        .arg(workspace)
+        .status()?;
+    Ok(true)
+}
+
+/// Returns the window ID of the process in a `wmctrl -l -p` listing
+///
+/// Lines are `ID DESKTOP PID HOST TITLE`.
+fn window_of(listing: &str, pid: u32) -> Option<&str> {
+    listing.lines().find_map(|line| {
+        let mut columns = line.split_whitespace();
+        let id = columns.next()?;
+        let pid_column = columns.nth(1)?;
+        (pid_column.parse::<u32>().ok()? == pid).then_some(id)
+    })
+}
+
+#[test]
+fn test_window_of() {
+    let listing = "0x02000003 -1 1520   host Desktop\n\
+                   0x03a00003  0 12345  host Video - mpv\n";
+    assert_eq!(window_of(listing, 12345), Some("0x03a00003"));
+    assert_eq!(window_of(listing, 1234), None);
+    assert_eq!(window_of("", 12345), None);
+
+    assert_eq!(
+        screen_options(1),
+        ["--screen=1".to_string(), "--fs-screen=1".to_string()]
+    );
+}
@@ -0,0 +1,97 @@
+-- Skip SponsorBlock segments of the videos played by mpv-handler
+--
+-- Install to `~/.config/mpv/scripts/` and set `[sponsorblock]` in the
+-- mpv-handler config. The handler passes the categories and, for prefetched
+-- direct URLs, the YouTube video ID by `script-opts`.
+
+local utils = require "mp.utils"
+
+local API = "https://sponsor.ajay.app/api/skipSegments"
+
+local segments = {}
+
+local function script_opt(name)
+    local opts = mp.get_property_native("script-opts") or {}
+    local v = opts["mpv_handler-" .. name]
+    if v == nil or v == "" then
+        return nil
+    end
+    return v
+end
+
+local function video_id()
+    local id = script_opt("video_id")
+    if id then
+        return id
+    end
+
+    local path = mp.get_property("path", "")
+    return path:match("youtube%.com/.*[?&]v=([%w_-]+)")
+        or path:match("youtube%.com/shorts/([%w_-]+)")
+        or path:match("youtu%.be/([%w_-]+)")
+end
+
+local function categories()
+    local v = script_opt("sponsorblock")
+    if not v then
+        return nil
+    end
+
+    local list = {}
+    for c in v:gmatch("[^|]+") do
+        list[#list + 1] = string.format("%q", c)
+    end
+    return "[" .. table.concat(list, ",") .. "]"
+end
+
+local function load_segments()
+    segments = {}
+
+    local id, cats = video_id(), categories()
+    if not id or not cats then
+        return
+    end
+
+    local res = mp.command_native({
+        name = "subprocess",
+        capture_stdout = true,
+        playback_only = false,
+        args = {
+            "curl", "--silent", "--get",
+            "--data-urlencode", "videoID=" .. id,
+            "--data-urlencode", "categories=" .. cats,
+            API,
+        },
+    })
+    if res.status ~= 0 then
+        return
+    end
+
+    -- Videos without segments return "Not Found"
+    local data = utils.parse_json(res.stdout)
+    if type(data) ~= "table" then
+        return
+    end
+
+    for _, v in ipairs(data) do
+        segments[#segments + 1] = { v.segment[1], v.segment[2], v.category }
+    end
+    mp.msg.info(string.format("Loaded %d segments for %s", #segments, id))
+end
+
+local function skip_segment(_, pos)
+    if not pos then
+        return
+    end
+
+    for _, s in ipairs(segments) do
+        if pos >= s[1] and pos < s[2] - 0.5 then
+            mp.set_property_number("time-pos", s[2])
+            mp.osd_message("Skipped " .. s[3] .. " segment")
+            return
+        end
+    end
+end
+
+mp.register_event("file-loaded", load_segments)
+mp.observe_property("time-pos", "number", skip_segment)
@@ -0,0 +1,120 @@
+use mpv_handler_core::config::Config;
+use mpv_handler_core::error::Error;
+use mpv_handler_core::plugins::Plugins;
+use mpv_handler_core::protocol::{Protocol, Schemes};
+use std::process::ExitCode;
+
+/// Flag which enqueues the link after the playing item, like `playnext`
+const FLAG_PLAY_NEXT: &str = "--play-next";
+/// Flag which logs debug messages, like links of the `mpv-debug` scheme
+const FLAG_VERBOSE: &str = "--verbose";
+
+/// Run the handler with the arguments of the process, like the binary
+pub fn main() -> ExitCode {
+    mpv_handler_core::logger::init();
+    mpv_handler_core::cancel::install_signal_handlers();
+    mpv_handler_core::temp::recover();
+
+    let result = run();
+    mpv_handler_core::temp::cleanup();
+    mpv_handler_core::cancel::stopped();
+
+    match result {
+        _ if mpv_handler_core::cancel::interrupted() => print_error(Error::Interrupted),
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => print_error(e),
+    }
+}
+
+/// Run handler
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = std::env::args().collect();
+    let verbose = args.iter().any(|v| v == FLAG_VERBOSE);
+    args.retain(|v| v != FLAG_VERBOSE);
+    let play_next = args.len() == 3 && args[1] == FLAG_PLAY_NEXT;
+    if play_next {
+        args.remove(1);
+    }
+    let arg: &str = match args.len() {
+        1 => {
+            print_usage();
+            return Ok(());
+        }
+        _ if !args[1].contains("://") => {
+            let config = Config::load()?;
+            mpv_handler_core::logger::configure(&config, verbose);
+            return crate::commands::exec(&args[1..], &config);
+        }
+        2 => &args[1],
+        _ => return Err(Error::TooManyArgs),
+    };
+
+    let mut proto = Protocol::parse(arg)?;
+    if play_next {
+        proto.play_next = Some(true);
+    }
+
+    // Double-clicks and retries of the browser repeat the link, but the
+    // background worker handles the link it was spawned for
+    if proto.plugin != Plugins::HandlerTest
+        && !mpv_handler_core::background::is_worker()
+        && mpv_handler_core::protocol::repeated(arg)
+    {
+        log::info!("Ignoring the repeated link: {}", arg);
+        return Ok(());
+    }
+
+    let config = Config::load()?;
+    mpv_handler_core::logger::configure(&config, verbose || proto.scheme == Schemes::MpvDebug);
+    // The parent asked already, so the worker doesn't prompt again
+    if !mpv_handler_core::background::is_worker() {
+        mpv_handler_core::trust::check(&config, &proto.url)?;
+    }
+
+    // Debug links keep the console, so their output stays visible
+    if config.background == Some(true)
+        && proto.scheme == Schemes::Mpv
+        && !mpv_handler_core::background::is_worker()
+    {
+        let flags = [(play_next, FLAG_PLAY_NEXT), (verbose, FLAG_VERBOSE)];
+        let mut spawn_args: Vec<&str> = flags.iter().filter(|v| v.0).map(|v| v.1).collect();
+        spawn_args.push(arg);
+        return mpv_handler_core::background::spawn(&spawn_args);
+    }
+
+    // Call plugin by scheme
+    match proto.plugin {
+        Plugins::Play => mpv_handler_core::plugins::play::exec(&proto, &config),
+        Plugins::Feed => mpv_handler_core::plugins::feed::exec(&proto, &config),
+        Plugins::Download => mpv_handler_core::plugins::download::exec(&proto, &config),
+        Plugins::HandlerTest => mpv_handler_core::plugins::handler_test::exec(&proto, &config),
+    }
+}
+
+/// Print usage
+fn print_usage() {
+    let version: &str = option_env!("MPV_HANDLER_VERSION").unwrap_or(env!("CARGO_PKG_VERSION"));
+
+    println!("mpv-handler {}\n", version);
+    println!(
+        "Usage:\n  mpv-handler [--verbose] <url>\n  mpv-handler [--verbose] --play-next <url>\n  mpv-handler [--verbose] <command>\n"
+    );
+    println!(
+        "Commands:\n  subs update    Enqueue new items of subscriptions\n  daemon         Enqueue scheduled premieres when they go live\n  retry          Enqueue items which failed to extract\n  cancel         Stop running batch extractions\n  history export Export the watch history as CSV or JSON\n  stats          Summarize the watch history\n  logs --last    Print the stderr of the latest mpv session\n  queue          List and control the queue of mpv\n  restore        Reload the queue saved at the last enqueue\n  pair           Add, list and revoke devices paired for remote control\n  --clear-cache  Remove cached yt-dlp results\n"
+    );
+}
+
+/// Print error
+fn print_error(e: Error) -> ExitCode {
+    eprint!("{e}");
+
+    match e {
+        Error::Interrupted => return ExitCode::from(mpv_handler_core::cancel::EXIT_INTERRUPTED),
+        _ => std::io::Read::read(&mut std::io::stdin(), &mut []).unwrap(),
+    };
+
+    match e {
+        Error::PlayerExited(code) => ExitCode::from(code),
+        _ => ExitCode::FAILURE,
+    }
+}
@@ -0,0 +1,15 @@
+use mpv_handler_core::config::Config;
+use mpv_handler_core::error::Error;
+
+/// `mpv-handler cancel`
+///
+/// Stop running batch extractions, keeping the items enqueued so far.
+pub fn exec(args: &[String], _config: &Config) -> Result<(), Error> {
+    if !args.is_empty() {
+        return Err(Error::UnknownCommand(format!("cancel {}", args.join(" "))));
+    }
+
+    mpv_handler_core::cancel::request()?;
+    println!("Cancel requested");
+    Ok(())
+}
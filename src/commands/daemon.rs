@@ -0,0 +1,116 @@
+use chrono::Local;
+use mpv_handler_core::config::Config;
+use mpv_handler_core::discord::Presence;
+use mpv_handler_core::error::Error;
+use mpv_handler_core::history::Recorder;
+use mpv_handler_core::observer::{Listener, NowPlaying};
+use mpv_handler_core::protocol::Protocol;
+use mpv_handler_core::refresh::{Downgrader, Refresher};
+use mpv_handler_core::schedule::{RECHECK_SECONDS, Schedule};
+use mpv_handler_core::scrobble::Scrobbler;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Interval between checks of the schedule
+const DAEMON_INTERVAL: Duration = Duration::from_secs(30);
+
+/// `mpv-handler daemon`
+///
+/// Enqueues scheduled premieres and live events once they go live, and
+/// observes mpv for the listeners enabled by the config.
+pub fn exec(args: &[String], config: &Config) -> Result<(), Error> {
+    if !args.is_empty() {
+        return Err(Error::UnknownCommand(format!("daemon {}", args.join(" "))));
+    }
+
+    let config = Arc::new(config.clone());
+    println!("mpv-handler daemon started");
+
+    mpv_handler_core::party::start(&config);
+    mpv_handler_core::sink::serve(&config);
+
+    let mut listeners = listeners(&config);
+    if !listeners.is_empty() {
+        let config = Arc::clone(&config);
+        std::thread::spawn(move || mpv_handler_core::observer::run(&config, &mut listeners));
+    }
+
+    while !mpv_handler_core::cancel::interrupted() {
+        run_schedule(&config);
+        std::thread::sleep(DAEMON_INTERVAL);
+    }
+    Ok(())
+}
+
+/// Returns the playback listeners enabled by the config
+fn listeners(config: &Config) -> Vec<Box<dyn Listener>> {
+    let mut listeners: Vec<Box<dyn Listener>> = Vec::new();
+    if config.history != Some(false) {
+        listeners.push(Box::new(Recorder::new()));
+    }
+    if config.now_playing == Some(true) {
+        listeners.push(Box::new(NowPlaying));
+    }
+    if let Some(v) = &config.discord {
+        listeners.push(Box::new(Presence::new(v.clone())));
+    }
+    if config.scrobble.is_some() {
+        listeners.push(Box::new(Scrobbler::new(config.clone())));
+    }
+    if config.refresh_after != Some(0) {
+        listeners.push(Box::new(Refresher::new(config.clone())));
+    }
+    if config.downgrade_on_buffering == Some(true) {
+        listeners.push(Box::new(Downgrader::new(config.clone())));
+    }
+    listeners
+}
+
+/// Check the due items of the schedule and enqueue the live ones
+fn run_schedule(config: &Arc<Config>) {
+    let mut schedule = Schedule::load();
+    let now = Local::now().timestamp();
+    if !schedule.items.iter().any(|v| v.time <= now) {
+        return;
+    }
+
+    let mut pending = Vec::new();
+    for item in schedule.items.drain(..) {
+        if item.time > now {
+            pending.push(item);
+            continue;
+        }
+
+        // Premieres often start a bit late, check again until live
+        if let Some(mut upcoming) = mpv_handler_core::schedule::probe(config, &item.url) {
+            upcoming.title = item.title;
+            upcoming.time = upcoming.time.max(now + RECHECK_SECONDS);
+            pending.push(upcoming);
+            continue;
+        }
+
+        println!("Now live: {}", item.title);
+        mpv_handler_core::notify::send(config, "Now live", &item.title);
+
+        // Playing may block while a new mpv instance is running
+        let config = Arc::clone(config);
+        std::thread::spawn(move || {
+            let proto = Protocol::from_url(item.url);
+            if let Err(e) = mpv_handler_core::plugins::play::play(&proto, &config, Vec::new()) {
+                log::warn!("Failed to enqueue \"{}\": {}", item.title, e);
+            }
+        });
+    }
+
+    // Items may have been scheduled while checking
+    for item in Schedule::load().items {
+        if item.time > now && !pending.iter().any(|v| v.url == item.url) {
+            pending.push(item);
+        }
+    }
+
+    schedule.items = pending;
+    if let Err(e) = schedule.save() {
+        log::warn!("Failed to save schedule: {}", e);
+    }
+}
@@ -0,0 +1,137 @@
+use chrono::{Local, NaiveDate, TimeZone};
+use mpv_handler_core::config::Config;
+use mpv_handler_core::error::Error;
+use mpv_handler_core::history::HistoryEntry;
+
+/// Export format of `mpv-handler history export`
+#[derive(Debug, PartialEq)]
+enum Format {
+    Csv,
+    Json,
+}
+
+/// `mpv-handler history export --format csv|json [--since YYYY-MM-DD] [--tag TAG]`
+pub fn exec(args: &[String], _config: &Config) -> Result<(), Error> {
+    match args.first().map(String::as_str) {
+        Some("export") => export(&args[1..]),
+        _ => Err(Error::UnknownCommand(format!("history {}", args.join(" ")))),
+    }
+}
+
+/// Print the history recorded by `mpv-handler daemon`
+fn export(args: &[String]) -> Result<(), Error> {
+    let unknown = || Error::UnknownCommand(format!("history export {}", args.join(" ")));
+
+    let mut format = Format::Json;
+    let mut since: Option<i64> = None;
+    let mut tag: Option<&str> = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next().map(String::as_str)) {
+            ("--format", Some("csv")) => format = Format::Csv,
+            ("--format", Some("json")) => format = Format::Json,
+            ("--since", Some(v)) => since = Some(parse_date(v).ok_or_else(unknown)?),
+            ("--tag", Some(v)) => tag = Some(v),
+            _ => return Err(unknown()),
+        }
+    }
+
+    let entries: Vec<HistoryEntry> = mpv_handler_core::history::load()
+        .into_iter()
+        .filter(|v| since.is_none_or(|since| v.time >= since))
+        .filter(|v| tag.is_none_or(|tag| v.tags.iter().any(|v| v == tag)))
+        .collect();
+
+    match format {
+        Format::Csv => print!("{}", to_csv(&entries)),
+        Format::Json => println!("{}", to_json(&entries)?),
+    }
+    Ok(())
+}
+
+/// Returns the local midnight of a `YYYY-MM-DD` date as Unix timestamp
+pub fn parse_date(date: &str) -> Option<i64> {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    let midnight = date.and_hms_opt(0, 0, 0)?;
+    Some(Local.from_local_datetime(&midnight).earliest()?.timestamp())
+}
+
+fn to_json(entries: &[HistoryEntry]) -> Result<String, Error> {
+    let entries: Vec<serde_json::Value> = entries
+        .iter()
+        .map(|v| {
+            let mut value = serde_json::to_value(v)?;
+            value["time"] = serde_json::json!(v.local_time());
+            Ok(value)
+        })
+        .collect::<Result<_, serde_json::Error>>()?;
+    Ok(serde_json::to_string_pretty(&entries)?)
+}
+
+fn to_csv(entries: &[HistoryEntry]) -> String {
+    let mut csv = String::from("time,title,url,domain,uploader,duration,watched,completed,tags\n");
+    for v in entries {
+        let fields = [
+            v.local_time(),
+            v.title.clone(),
+            v.url.clone(),
+            v.domain.clone().unwrap_or_default(),
+            v.uploader.clone().unwrap_or_default(),
+            v.duration.map(|v| v.to_string()).unwrap_or_default(),
+            v.watched.to_string(),
+            v.completed.to_string(),
+            v.tags.join(";"),
+        ];
+        let fields: Vec<String> = fields.iter().map(|v| csv_field(v)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Quote the CSV field if needed, doubling its quotes
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[test]
+fn test_history_csv() {
+    let entry = HistoryEntry {
+        time: 0,
+        title: "Hello, \"World\"".to_string(),
+        url: "https://example.com/v".to_string(),
+        domain: Some("example.com".to_string()),
+        uploader: None,
+        duration: Some(120.0),
+        watched: 115.0,
+        completed: true,
+        tags: vec!["work".to_string(), "talk".to_string()],
+        position: None,
+    };
+
+    let csv = to_csv(std::slice::from_ref(&entry));
+    let line = csv.lines().nth(1).unwrap();
+    assert!(line.ends_with(
+        ",\"Hello, \"\"World\"\"\",https://example.com/v,example.com,,120,115,true,work;talk"
+    ));
+    assert_eq!(
+        csv.lines().next().unwrap(),
+        "time,title,url,domain,uploader,duration,watched,completed,tags"
+    );
+}
+
+#[test]
+fn test_parse_date() {
+    let time = parse_date("2024-03-01").unwrap();
+    let local = Local.timestamp_opt(time, 0).unwrap();
+    assert_eq!(
+        local.format("%Y-%m-%d %H:%M").to_string(),
+        "2024-03-01 00:00"
+    );
+
+    assert_eq!(parse_date("yesterday"), None);
+}
@@ -0,0 +1,22 @@
+use mpv_handler_core::config::Config;
+use mpv_handler_core::error::Error;
+
+/// `mpv-handler logs --last`
+pub fn exec(args: &[String], config: &Config) -> Result<(), Error> {
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["--last"] => last(config),
+        _ => Err(Error::UnknownCommand(format!("logs {}", args.join(" ")))),
+    }
+}
+
+/// Print the stderr of the latest mpv session
+fn last(config: &Config) -> Result<(), Error> {
+    let Some(path) = mpv_handler_core::logs::list(config).pop() else {
+        eprintln!("No mpv session logs yet");
+        return Ok(());
+    };
+
+    eprintln!("{}:", path.display());
+    print!("{}", String::from_utf8_lossy(&std::fs::read(path)?));
+    Ok(())
+}
@@ -0,0 +1,47 @@
+pub mod cancel;
+pub mod daemon;
+pub mod history;
+pub mod logs;
+pub mod pair;
+pub mod queue;
+pub mod restore;
+pub mod retry;
+pub mod stats;
+pub mod subs;
+
+use mpv_handler_core::config::Config;
+use mpv_handler_core::error::Error;
+
+/// Run command by the given arguments
+pub fn exec(args: &[String], config: &Config) -> Result<(), Error> {
+    match args[0].as_str() {
+        "subs" => subs::exec(&args[1..], config),
+        "daemon" => daemon::exec(&args[1..], config),
+        "retry" => retry::exec(&args[1..], config),
+        "cancel" => cancel::exec(&args[1..], config),
+        "history" => history::exec(&args[1..], config),
+        "stats" => stats::exec(&args[1..], config),
+        "logs" => logs::exec(&args[1..], config),
+        "queue" => queue::exec(&args[1..], config),
+        "restore" => restore::exec(&args[1..], config),
+        "pair" => pair::exec(&args[1..], config),
+        "--clear-cache" => clear_cache(&args[1..]),
+        _ => Err(Error::UnknownCommand(args.join(" "))),
+    }
+}
+
+/// `mpv-handler --clear-cache`
+///
+/// Remove the cached yt-dlp results, e.g. resolved URLs and format lists.
+fn clear_cache(args: &[String]) -> Result<(), Error> {
+    if !args.is_empty() {
+        return Err(Error::UnknownCommand(format!(
+            "--clear-cache {}",
+            args.join(" ")
+        )));
+    }
+
+    let removed = mpv_handler_core::cache::clear()?;
+    println!("Cleared {} caches", removed);
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use chrono::{Local, TimeZone};
+use mpv_handler_core::config::Config;
+use mpv_handler_core::error::Error;
+use mpv_handler_core::pairing::Pairings;
+
+/// `mpv-handler pair add|list|revoke [NAME]`
+///
+/// Manage the devices paired for remote control.
+pub fn exec(args: &[String], _config: &Config) -> Result<(), Error> {
+    let unknown = || Error::UnknownCommand(format!("pair {}", args.join(" ")));
+
+    let mut pairings = Pairings::load();
+    match (args.first().map(String::as_str), &args[1.min(args.len())..]) {
+        (Some("add"), [name]) => {
+            let token = pairings.pair(name)?;
+            pairings.save()?;
+            println!("Paired '{}', token (shown only once): {}", name, token);
+        }
+        (Some("list"), []) => {
+            if pairings.devices.is_empty() {
+                println!("No paired devices");
+            }
+            for device in &pairings.devices {
+                let last_seen = device.last_seen.map_or("never".to_string(), time);
+                println!(
+                    "{}\tpaired {}\tlast seen {}",
+                    device.name,
+                    time(device.paired),
+                    last_seen
+                );
+            }
+        }
+        (Some("revoke"), [name]) => {
+            if !pairings.revoke(name) {
+                return Err(unknown());
+            }
+            pairings.save()?;
+            println!("Revoked '{}'", name);
+        }
+        _ => return Err(unknown()),
+    }
+    Ok(())
+}
+
+fn time(timestamp: i64) -> String {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map_or(timestamp.to_string(), |v| {
+            v.format("%Y-%m-%d %H:%M").to_string()
+        })
+}
@@ -0,0 +1,109 @@
+use mpv_handler_core::config::Config;
+use mpv_handler_core::error::Error;
+use mpv_handler_core::mpv_ipc::Connection;
+use mpv_handler_core::queue::QueueState;
+use serde_json::{Value, json};
+
+/// `mpv-handler queue list|clear|next|prev|remove <n>|jump <n>`
+///
+/// Items are numbered from 1, like in `queue list`.
+pub fn exec(args: &[String], config: &Config) -> Result<(), Error> {
+    let unknown = || Error::UnknownCommand(format!("queue {}", args.join(" ")));
+    let index = |v: &str| match v.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n - 1),
+        _ => Err(unknown()),
+    };
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let command = match args[..] {
+        ["list"] => return list(&mut connect(config)?),
+        ["clear"] => json!(["playlist-clear"]),
+        ["next"] => json!(["playlist-next"]),
+        ["prev"] => json!(["playlist-prev"]),
+        ["remove", n] => json!(["playlist-remove", index(n)?]),
+        ["jump", n] => json!(["playlist-play-index", index(n)?]),
+        _ => return Err(unknown()),
+    };
+
+    let mut conn = connect(config)?;
+    match conn
+        .send_all(std::slice::from_ref(&command))?
+        .pop()
+        .flatten()
+    {
+        Some(_) => Ok(()),
+        None => Err(Error::CommandFailed(command.to_string())),
+    }
+}
+
+/// Connect to the socket of the default profile
+fn connect(config: &Config) -> Result<Connection, Error> {
+    let socket = config.socket(None).ok_or(Error::SocketConnectionFailed)?;
+    mpv_handler_core::socket::check(&socket)?;
+    Connection::connect(&socket)
+}
+
+/// Print the playlist of mpv, with the titles and durations of enqueued items
+fn list(conn: &mut Connection) -> Result<(), Error> {
+    let playlist = conn
+        .send_all(&[json!(["get_property", "playlist"])])?
+        .pop()
+        .flatten()
+        .ok_or(Error::CommandFailed("get_property playlist".to_string()))?;
+
+    let state = QueueState::load();
+    for (i, entry) in playlist.as_array().into_iter().flatten().enumerate() {
+        println!("{}", format_entry(i, entry, &state));
+    }
+    Ok(())
+}
+
+/// Returns the line of a playlist entry, e.g. `* 2. Title (12m) [work]`
+fn format_entry(index: usize, entry: &Value, state: &QueueState) -> String {
+    let filename = entry["filename"].as_str().unwrap_or_default();
+    let item = state.find(filename);
+
+    let title = entry["title"]
+        .as_str()
+        .or(item.map(|v| v.title.as_str()))
+        .unwrap_or(filename);
+    let current = if entry["current"] == true { "*" } else { " " };
+    let mut line = format!("{} {}. {}", current, index + 1, title);
+
+    if let Some(item) = item {
+        if let Some(duration) = item.duration {
+            line.push_str(&format!(
+                " ({})",
+                mpv_handler_core::queue::format_duration(duration)
+            ));
+        }
+        if !item.tags.is_empty() {
+            line.push_str(&format!(" [{}]", item.tags.join(", ")));
+        }
+    }
+    line
+}
+
+#[test]
+fn test_format_entry() {
+    let state = QueueState {
+        items: vec![mpv_handler_core::queue::QueueItem {
+            title: "Talk".to_string(),
+            url: "https://www.youtube.com/watch?v=Ggkn2f5e-IU".to_string(),
+            filename: "https://example.com/video.mp4".to_string(),
+            duration: Some(720.0),
+            profile: None,
+            tags: vec!["work".to_string()],
+            resolved: None,
+            format: None,
+        }],
+    };
+
+    let entry = json!({ "filename": "https://example.com/video.mp4", "current": true });
+    assert_eq!(format_entry(1, &entry, &state), "* 2. Talk (12m) [work]");
+
+    let entry = json!({ "filename": "/music/a.flac", "title": "A" });
+    assert_eq!(format_entry(0, &entry, &state), "  1. A");
+    let entry = json!({ "filename": "/music/b.flac" });
+    assert_eq!(format_entry(2, &entry, &state), "  3. /music/b.flac");
+}
@@ -0,0 +1,44 @@
+use chrono::{Local, TimeZone};
+use mpv_handler_core::config::Config;
+use mpv_handler_core::error::Error;
+use mpv_handler_core::plugins::play::Entry;
+use mpv_handler_core::protocol::Protocol;
+use mpv_handler_core::queue::SavedQueue;
+
+/// `mpv-handler restore`
+///
+/// Reload the queue saved at the last enqueue, from the item which was
+/// playing, e.g. after a crash or reboot.
+pub fn exec(args: &[String], config: &Config) -> Result<(), Error> {
+    if !args.is_empty() {
+        return Err(Error::UnknownCommand(format!("restore {}", args.join(" "))));
+    }
+
+    let saved = SavedQueue::load();
+    let entries: Vec<Entry> = saved
+        .items
+        .iter()
+        .filter(|v| saved.current.is_none_or(|current| v.position >= current))
+        .map(|v| Entry {
+            title: v.title.clone(),
+            url: v.url.clone(),
+            duration: None,
+            upload_date: None,
+            range: None,
+        })
+        .collect();
+    if entries.is_empty() {
+        println!("No saved queue to restore");
+        return Ok(());
+    }
+
+    if let Some(time) = Local.timestamp_opt(saved.time, 0).single() {
+        println!(
+            "Restoring {} items saved {}",
+            entries.len(),
+            time.format("%Y-%m-%d %H:%M")
+        );
+    }
+    let proto = Protocol::from_url(entries[0].url.clone());
+    mpv_handler_core::plugins::play::play(&proto, config, entries)
+}
@@ -0,0 +1,13 @@
+use mpv_handler_core::config::Config;
+use mpv_handler_core::error::Error;
+
+/// `mpv-handler retry`
+///
+/// Enqueue the items whose extraction failed, failing items are kept.
+pub fn exec(args: &[String], config: &Config) -> Result<(), Error> {
+    if !args.is_empty() {
+        return Err(Error::UnknownCommand(format!("retry {}", args.join(" "))));
+    }
+
+    mpv_handler_core::retry::retry_all(config)
+}
@@ -0,0 +1,154 @@
+use chrono::{Datelike, Local, TimeZone};
+use mpv_handler_core::config::Config;
+use mpv_handler_core::error::Error;
+use mpv_handler_core::history::HistoryEntry;
+use std::collections::HashMap;
+
+/// Number of entries in the top lists
+const TOP: usize = 5;
+/// Number of the latest weeks listed
+const WEEKS: usize = 8;
+
+/// Summary of the watch history
+#[derive(Debug, Default, PartialEq)]
+struct Stats {
+    items: usize,
+    /// Watched time in seconds
+    watched: f64,
+    /// Average watched share of the items with known duration
+    completion: Option<f64>,
+    /// `(name, items)`, most played first
+    channels: Vec<(String, usize)>,
+    domains: Vec<(String, usize)>,
+    /// `(ISO week, items)`, latest first
+    weeks: Vec<(String, usize)>,
+}
+
+/// `mpv-handler stats`
+pub fn exec(args: &[String], _config: &Config) -> Result<(), Error> {
+    if !args.is_empty() {
+        return Err(Error::UnknownCommand(format!("stats {}", args.join(" "))));
+    }
+
+    let stats = summarize(&mpv_handler_core::history::load());
+    if stats.items == 0 {
+        println!("No watch history, it's recorded by `mpv-handler daemon`");
+        return Ok(());
+    }
+
+    println!("Items watched: {}", stats.items);
+    println!("Watch time:    {}", format_time(stats.watched));
+    if let Some(v) = stats.completion {
+        println!("Completion:    {:.0}%", v * 100.0);
+    }
+    print_counts("Top channels", &stats.channels);
+    print_counts("Top domains", &stats.domains);
+    print_counts("Items per week", &stats.weeks);
+    Ok(())
+}
+
+fn summarize(entries: &[HistoryEntry]) -> Stats {
+    let mut channels: HashMap<&str, usize> = HashMap::new();
+    let mut domains: HashMap<&str, usize> = HashMap::new();
+    let mut weeks: HashMap<String, usize> = HashMap::new();
+    let mut ratios: Vec<f64> = Vec::new();
+
+    for v in entries {
+        if let Some(uploader) = &v.uploader {
+            *channels.entry(uploader).or_default() += 1;
+        }
+        if let Some(domain) = &v.domain {
+            *domains.entry(domain).or_default() += 1;
+        }
+        if let Some(time) = Local.timestamp_opt(v.time, 0).single() {
+            let week = time.iso_week();
+            let key = format!("{}-W{:02}", week.year(), week.week());
+            *weeks.entry(key).or_default() += 1;
+        }
+        if let Some(duration) = v.duration
+            && duration > 0.0
+        {
+            ratios.push((v.watched / duration).min(1.0));
+        }
+    }
+
+    let mut weeks: Vec<(String, usize)> = weeks.into_iter().collect();
+    weeks.sort_by(|a, b| b.0.cmp(&a.0));
+    weeks.truncate(WEEKS);
+
+    Stats {
+        items: entries.len(),
+        watched: entries.iter().map(|v| v.watched).sum(),
+        completion: match ratios.len() {
+            0 => None,
+            n => Some(ratios.iter().sum::<f64>() / n as f64),
+        },
+        channels: top(
+            channels.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+            TOP,
+        ),
+        domains: top(
+            domains.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+            TOP,
+        ),
+        weeks,
+    }
+}
+
+/// Returns the `limit` largest counts, sorted by most items, then by name
+fn top(mut counts: Vec<(String, usize)>, limit: usize) -> Vec<(String, usize)> {
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts.truncate(limit);
+    counts
+}
+
+fn print_counts(heading: &str, counts: &[(String, usize)]) {
+    if counts.is_empty() {
+        return;
+    }
+    println!("\n{}:", heading);
+    for (name, items) in counts {
+        println!("  {:>4}  {}", items, name);
+    }
+}
+
+/// Format seconds as `Hh Mm`
+fn format_time(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).round() as u64;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+#[test]
+fn test_stats_summarize() {
+    let entry = |uploader: &str, domain: &str, duration: Option<f64>, watched: f64| HistoryEntry {
+        time: 1_700_000_000,
+        title: "Title".to_string(),
+        url: format!("https://{}/v", domain),
+        domain: Some(domain.to_string()),
+        uploader: Some(uploader.to_string()),
+        duration,
+        watched,
+        completed: duration.is_some_and(|v| watched >= v * 0.9),
+        tags: Vec::new(),
+        position: None,
+    };
+    let stats = summarize(&[
+        entry("Alice", "youtube.com", Some(100.0), 100.0),
+        entry("Bob", "youtube.com", Some(100.0), 50.0),
+        entry("Alice", "vimeo.com", None, 30.0),
+    ]);
+
+    assert_eq!(stats.items, 3);
+    assert_eq!(stats.watched, 180.0);
+    assert_eq!(stats.completion, Some(0.75));
+    assert_eq!(
+        stats.channels,
+        vec![("Alice".to_string(), 2), ("Bob".to_string(), 1)]
+    );
+    assert_eq!(stats.domains[0], ("youtube.com".to_string(), 2));
+    assert_eq!(stats.weeks.len(), 1);
+    assert_eq!(stats.weeks[0].1, 3);
+
+    assert_eq!(summarize(&[]), Stats::default());
+    assert_eq!(format_time(3725.0), "1h 02m");
+}
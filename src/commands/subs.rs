@@ -0,0 +1,115 @@
+use mpv_handler_core::config::{ChannelOrder, Config};
+use mpv_handler_core::error::Error;
+use mpv_handler_core::plugins::play::{Entry, channel_uploads_url, is_channel_url, probe_playlist};
+use mpv_handler_core::protocol::Protocol;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Number of the latest entries checked per subscription
+const SUBS_PROBE_ITEMS: usize = 30;
+/// Number of seen entries remembered per subscription
+const SUBS_ARCHIVE_ITEMS: usize = 200;
+
+/// Archive of seen entry URLs per subscription URL
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Archive {
+    seen: HashMap<String, Vec<String>>,
+}
+
+/// `mpv-handler subs update`
+pub fn exec(args: &[String], config: &Config) -> Result<(), Error> {
+    match args.first().map(String::as_str) {
+        Some("update") => update(config),
+        _ => Err(Error::UnknownCommand(format!("subs {}", args.join(" ")))),
+    }
+}
+
+/// Enqueue the entries of all subscriptions published since the last run
+///
+/// The first run of a subscription only records its current entries.
+fn update(config: &Config) -> Result<(), Error> {
+    let subscriptions = config.subscriptions.as_deref().unwrap_or_default();
+    if subscriptions.is_empty() {
+        println!("No subscriptions in config");
+        return Ok(());
+    }
+
+    let mut archive = Archive::load()?;
+    let mut new_entries: Vec<Entry> = Vec::new();
+
+    for sub in subscriptions {
+        let url = match is_channel_url(sub) {
+            true => channel_uploads_url(sub),
+            false => sub.clone(),
+        };
+        // Entries are listed newest-first
+        let entries = probe_playlist(config, &url, Some(SUBS_PROBE_ITEMS));
+
+        let seen = archive.seen.entry(sub.clone()).or_default();
+        let first_run = seen.is_empty();
+        let mut unseen: Vec<Entry> = entries
+            .into_iter()
+            .filter(|v| !seen.contains(&v.url))
+            .collect();
+
+        seen.splice(0..0, unseen.iter().map(|v| v.url.clone()));
+        seen.truncate(SUBS_ARCHIVE_ITEMS);
+
+        if first_run {
+            println!("Subscribed: {} ({} items marked as seen)", sub, seen.len());
+            continue;
+        }
+
+        println!("{}: {} new items", sub, unseen.len());
+        new_entries.append(&mut unseen);
+    }
+
+    archive.save()?;
+
+    if new_entries.is_empty() {
+        println!("No new items since last run");
+        return Ok(());
+    }
+
+    if config.channel_order == Some(ChannelOrder::Oldest) {
+        new_entries.reverse();
+    }
+
+    let proto = Protocol::from_url(new_entries[0].url.clone());
+    mpv_handler_core::plugins::play::play(&proto, config, new_entries)
+}
+
+impl Archive {
+    fn load() -> Result<Archive, Error> {
+        match archive_path() {
+            Some(path) if path.exists() => {
+                match serde_json::from_str(&std::fs::read_to_string(&path)?) {
+                    Ok(archive) => Ok(archive),
+                    // Start over rather than blocking every update
+                    Err(e) => {
+                        log::warn!("Ignored corrupt archive {}: {}", path.display(), e);
+                        Ok(Archive::default())
+                    }
+                }
+            }
+            _ => Ok(Archive::default()),
+        }
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        if let Some(path) = archive_path() {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, serde_json::to_string(self)?)?;
+        }
+        Ok(())
+    }
+}
+
+fn archive_path() -> Option<PathBuf> {
+    let mut path = mpv_handler_core::config::get_state_dir()?;
+    path.push("subscriptions.json");
+    Some(path)
+}
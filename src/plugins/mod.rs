@@ -1,6 +0,0 @@
-pub mod play;
-
-#[derive(Debug, PartialEq)]
-pub enum Plugins {
-    Play,
-}